@@ -0,0 +1,152 @@
+//! Order book update benchmarks
+//!
+//! Compares the sorted `PriceLevelBook` against the previous Vec-based merge
+//! used by the Binance exchange, on a 1000-level snapshot receiving bursts of
+//! depth updates.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use global_price_index::models::{Decimal, Order, PriceLevelBook};
+
+/// Number of levels on each side of the snapshot
+const SNAPSHOT_LEVELS: usize = 1000;
+
+/// Mid-market price the snapshot is built around, in cents
+const MID_CENTS: i64 = 5_000_000;
+
+/// Builds a snapshot with bids below 50,000 and asks above it, one tick apart
+fn snapshot() -> (Vec<Order>, Vec<Order>) {
+    let bids = (0..SNAPSHOT_LEVELS as i64)
+        .map(|i| Order {
+            price: Decimal::new(MID_CENTS - (i + 1), 2),
+            quantity: Decimal::ONE,
+        })
+        .collect();
+    let asks = (0..SNAPSHOT_LEVELS as i64)
+        .map(|i| Order {
+            price: Decimal::new(MID_CENTS + (i + 1), 2),
+            quantity: Decimal::ONE,
+        })
+        .collect();
+    (bids, asks)
+}
+
+/// Builds a burst of updates near the top of the book
+///
+/// A third of the updates change existing levels, a third remove levels and
+/// a third add levels between existing ticks.
+fn burst(size: usize, side: i64) -> Vec<Order> {
+    (0..size as i64)
+        .map(|i| {
+            // Offset from the mid in thousandths, so half ticks fall between levels
+            let offset = 10 * ((i % 50) + 1);
+            match i % 3 {
+                0 => Order {
+                    price: Decimal::new(MID_CENTS * 10 + side * offset, 3),
+                    quantity: Decimal::TWO,
+                },
+                1 => Order {
+                    price: Decimal::new(MID_CENTS * 10 + side * offset, 3),
+                    quantity: Decimal::ZERO,
+                },
+                _ => Order {
+                    price: Decimal::new(MID_CENTS * 10 + side * (offset + 5), 3),
+                    quantity: Decimal::new(5, 1),
+                },
+            }
+        })
+        .collect()
+}
+
+/// The Vec-based merge previously used by the Binance exchange
+///
+/// Clones the side, finds levels by linear search and re-sorts the whole
+/// side after every burst.
+fn vec_merge(existing_orders: &mut Vec<Order>, updates: &[Order], is_bids: bool) {
+    let mut all_orders = existing_orders.clone();
+
+    for update in updates {
+        if let Some(existing_idx) = all_orders
+            .iter()
+            .position(|order| order.price == update.price)
+        {
+            if update.quantity > Decimal::ZERO {
+                all_orders[existing_idx].quantity = update.quantity;
+            } else {
+                all_orders.remove(existing_idx);
+            }
+        } else if update.quantity > Decimal::ZERO {
+            all_orders.push(update.clone());
+        }
+    }
+
+    if is_bids {
+        all_orders.sort_by_key(|order| std::cmp::Reverse(order.price));
+    } else {
+        all_orders.sort_by_key(|order| order.price);
+    }
+
+    *existing_orders = all_orders;
+}
+
+/// Benchmarks applying a burst of updates to a 1000-level book
+fn bench_apply_updates(c: &mut Criterion) {
+    let (bids, asks) = snapshot();
+    let mut group = c.benchmark_group("apply_updates");
+
+    for burst_size in [10, 100, 1000] {
+        let bid_updates = burst(burst_size, -1);
+        let ask_updates = burst(burst_size, 1);
+
+        group.bench_with_input(
+            BenchmarkId::new("vec_merge", burst_size),
+            &burst_size,
+            |b, _| {
+                b.iter_batched(
+                    || (bids.clone(), asks.clone()),
+                    |(mut book_bids, mut book_asks)| {
+                        vec_merge(&mut book_bids, black_box(&bid_updates), true);
+                        vec_merge(&mut book_asks, black_box(&ask_updates), false);
+                        (book_bids, book_asks)
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("price_level_book", burst_size),
+            &burst_size,
+            |b, _| {
+                b.iter_batched(
+                    || PriceLevelBook::from_levels(&bids, &asks),
+                    |mut book| {
+                        book.apply_updates(black_box(&bid_updates), black_box(&ask_updates));
+                        book
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmarks reading the best bid and ask
+fn bench_best_levels(c: &mut Criterion) {
+    let (bids, asks) = snapshot();
+    let book = PriceLevelBook::from_levels(&bids, &asks);
+    let mut group = c.benchmark_group("best_bid_ask");
+
+    group.bench_function("vec", |b| {
+        b.iter(|| (black_box(&bids).first(), black_box(&asks).first()))
+    });
+    group.bench_function("price_level_book", |b| {
+        b.iter(|| (black_box(&book).best_bid(), black_box(&book).best_ask()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_apply_updates, bench_best_levels);
+criterion_main!(benches);
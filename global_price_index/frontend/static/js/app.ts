@@ -5,11 +5,11 @@
 interface ExchangePrice {
   exchange: string;      // The name of the exchange (e.g., "Binance", "Kraken")
   mid_price: number;     // The mid-price from this exchange
+  excluded?: string;     // Why the price was left out of the index, if it was
 }
 
 /**
- * Type definition for the complete price data returned by the API.
- * This represents the structure of the /global-price endpoint response.
+ * Type definition for the complete price data used by the UI.
  */
 interface PriceData {
   price: number;                     // The global weighted average price
@@ -17,6 +17,36 @@ interface PriceData {
   exchange_prices: ExchangePrice[];  // Array of individual exchange prices
 }
 
+/**
+ * Type definition for the /global-price endpoint response.
+ * Prices are exact decimals sent as strings so no precision is lost in JSON.
+ */
+interface ApiPriceData {
+  symbol: string;
+  price: string;
+  timestamp: string;
+  exchange_prices: { exchange: string; mid_price: string }[];
+  excluded?: { exchange: string; mid_price: string; reason: string }[];
+}
+
+/**
+ * Converts the API response to numbers for display.
+ * data - Raw response from the /global-price endpoint
+ * returns price data with numeric prices
+ */
+function parsePriceData(data: ApiPriceData): PriceData {
+  return {
+    price: Number(data.price),
+    timestamp: data.timestamp,
+    exchange_prices: data.exchange_prices.map((exchange) => ({
+      exchange: exchange.exchange,
+      mid_price: Number(exchange.mid_price),
+      excluded: data.excluded?.find((excluded) => excluded.exchange === exchange.exchange)
+        ?.reason,
+    })),
+  };
+}
+
 /**
  * PriceDisplay class handles all UI updates and price formatting.
  * It's responsible for fetching price data and updating the DOM.
@@ -33,6 +63,12 @@ class PriceDisplay {
    */
   private readonly apiBaseUrl = "http://127.0.0.1:8080";
 
+  /**
+   * The symbol on display, taken from the first index received.
+   * The stream carries every indexed symbol, other symbols are ignored.
+   */
+  private symbol?: string;
+
   /**
    * Formats a number as a price with 2 decimal places.
    * price - The price to format
@@ -107,7 +143,11 @@ class PriceDisplay {
         // Return HTML for this exchange price
         return `
                 <div class="flex justify-between items-center">
-                    <span class="text-gray-600">${exchange.exchange}</span>
+                    <span class="text-gray-600">${exchange.exchange}${
+          exchange.excluded
+            ? ` <span class="text-xs text-gray-400">(excluded: ${exchange.excluded.replace(/_/g, " ")})</span>`
+            : ""
+        }</span>
                     <span class="font-semibold ${priceClass}">$${this.formatPrice(
           exchange.mid_price
         )}</span>
@@ -127,6 +167,23 @@ class PriceDisplay {
     }
   }
 
+  /**
+   * Updates every section of the UI with new price data.
+   * data - Raw index received from the API
+   */
+  private render(data: ApiPriceData): void {
+    if (this.symbol === undefined) {
+      this.symbol = data.symbol;
+    } else if (data.symbol !== this.symbol) {
+      return;
+    }
+
+    const priceData = parsePriceData(data);
+    this.updateGlobalPrice(priceData);
+    this.updateExchangePrices(priceData);
+    this.updateLastUpdateTime(priceData.timestamp);
+  }
+
   /**
    * Main method to fetch the latest price data and update the UI.
    * Calls the /global-price API endpoint and handles the response.
@@ -136,16 +193,42 @@ class PriceDisplay {
     try {
       // Fetch the latest price data from the API
       const response = await fetch(`${this.apiBaseUrl}/global-price`);
-      const data: PriceData = await response.json();
-
-      // Update all sections of the UI with the new data
-      this.updateGlobalPrice(data);
-      this.updateExchangePrices(data);
-      this.updateLastUpdateTime(data.timestamp);
+      this.render((await response.json()) as ApiPriceData);
     } catch (error) {
       console.error("Error fetching prices:", error);
     }
   }
+
+  /**
+   * Subscribes to live index updates pushed by the /stream endpoint.
+   * Falls back to polling /global-price every 5 seconds if the browser
+   * does not support Server-Sent Events or the stream cannot be opened.
+   */
+  public subscribe(): void {
+    const startPolling = () => {
+      this.updatePrices();
+      setInterval(() => this.updatePrices(), 5000);
+    };
+
+    if (typeof EventSource === "undefined") {
+      startPolling();
+      return;
+    }
+
+    const source = new EventSource(`${this.apiBaseUrl}/stream`);
+    let received = false;
+    source.addEventListener("index", (event) => {
+      received = true;
+      this.render(JSON.parse((event as MessageEvent).data) as ApiPriceData);
+    });
+    source.onerror = () => {
+      // EventSource reconnects by itself once the stream has worked
+      if (!received) {
+        source.close();
+        startPolling();
+      }
+    };
+  }
 }
 
 // Initialize the price display
@@ -154,5 +237,5 @@ const priceDisplay = new PriceDisplay();
 // Initial update to show prices immediately when page loads
 priceDisplay.updatePrices();
 
-// Set up automatic refresh every 5 seconds to keep prices current
-setInterval(() => priceDisplay.updatePrices(), 5000);
+// Keep prices current with live updates pushed by the server
+priceDisplay.subscribe();
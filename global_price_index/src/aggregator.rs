@@ -0,0 +1,426 @@
+// Background aggregation of exchange prices into the global index
+
+use crate::config::{
+    get_mid_price_calculator, get_min_venues, get_outlier_filter, get_price_weighting,
+    MidPriceCalculator, OutlierFilter, PriceWeighting, Settings, WeightingMethod,
+};
+use crate::error::Result;
+use crate::exchanges::Exchange;
+use crate::history::{HistoryStore, HistoryWriter};
+use crate::metrics::METRICS;
+use crate::models::{ExchangePrice, ExclusionReason, GlobalPriceIndex};
+use crate::outlier::is_stale;
+use crate::stream::IndexPublisher;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, info_span, warn, Instrument};
+
+/// Settings the venue prices are taken and combined into an index with
+#[derive(Debug, Clone)]
+pub struct IndexSettings {
+    /// Weighting strategy, weight cap and maximum age of the venue prices
+    pub price_weighting: PriceWeighting,
+    /// Rejection of the venue prices too far from the others
+    pub outlier_filter: OutlierFilter,
+    /// Calculation of the venue mid prices from their books
+    pub mid_price: MidPriceCalculator,
+    /// Venue quorum of a valid index, the `[health] min_venues` of `/ready`
+    pub min_venues: usize,
+}
+
+impl IndexSettings {
+    /// Returns the index settings of the global configuration
+    pub fn configured() -> Self {
+        Self {
+            price_weighting: get_price_weighting(),
+            outlier_filter: get_outlier_filter(),
+            mid_price: get_mid_price_calculator(),
+            min_venues: get_min_venues(),
+        }
+    }
+}
+
+impl From<&Settings> for IndexSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            price_weighting: settings.price_weighting.clone(),
+            outlier_filter: settings.outlier_filter.clone(),
+            mid_price: settings.mid_price.clone(),
+            min_venues: settings.health.min_venues,
+        }
+    }
+}
+
+/// Aggregator keeps the latest price of every venue and the resulting index
+///
+/// Each exchange is read by its own background task on its own schedule,
+/// so HTTP handlers never wait on exchange round-trips and the upstream
+/// load does not depend on the number of clients. Whenever a venue's mid
+/// price changes, or its liquidity when the index is weighted by liquidity,
+/// the index of its symbol is recomputed, cached and published to
+/// streaming clients, then stored in the index history if enabled and
+/// exported to the metrics when it meets its venue quorum.
+///
+/// Indices are computed with the settings given by `with_settings` and
+/// replaced by `set_settings`, e.g. on reload, or with the global settings,
+/// read on every use, when none were given. Clones share the settings.
+#[derive(Clone)]
+pub struct Aggregator {
+    prices: Arc<RwLock<HashMap<String, HashMap<String, ExchangePrice>>>>,
+    publisher: IndexPublisher,
+    history: Option<HistoryWriter>,
+    settings: Arc<RwLock<Option<Arc<IndexSettings>>>>,
+}
+
+impl Aggregator {
+    /// Creates an aggregator publishing to a channel of the given capacity
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            prices: Arc::new(RwLock::new(HashMap::new())),
+            publisher: IndexPublisher::new(capacity),
+            history: None,
+            settings: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Computes every index from now on with explicit settings
+    pub fn with_settings(self, settings: IndexSettings) -> Self {
+        self.set_settings(settings);
+        self
+    }
+
+    /// Replaces the settings every index is computed with from now on
+    pub fn set_settings(&self, settings: IndexSettings) {
+        *self.settings.write().unwrap() = Some(Arc::new(settings));
+    }
+
+    /// Returns the settings the indices are computed with
+    pub fn settings(&self) -> Arc<IndexSettings> {
+        self.settings
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| Arc::new(IndexSettings::configured()))
+    }
+
+    /// Stores every index computed from now on in a history store
+    ///
+    /// The indices are written by a dedicated thread, see `HistoryWriter`.
+    pub fn with_history(mut self, history: HistoryStore) -> Self {
+        self.history = Some(HistoryWriter::spawn(history));
+        self
+    }
+
+    /// Returns the history store of the computed indices, if enabled
+    pub fn history(&self) -> Option<&HistoryStore> {
+        self.history.as_ref().map(HistoryWriter::store)
+    }
+
+    /// Writes the stored indices to disk before the process exits
+    ///
+    /// Called once the exchanges are stopped. Waits for the queued indices
+    /// to be stored, see `HistoryWriter::flush`. Does nothing when the
+    /// history is disabled.
+    pub fn flush(&self) -> Result<()> {
+        match &self.history {
+            Some(history) => history.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the publisher of index updates
+    pub fn publisher(&self) -> &IndexPublisher {
+        &self.publisher
+    }
+
+    /// Returns the latest index computed for a symbol
+    pub fn latest(&self, symbol: &str) -> Option<GlobalPriceIndex> {
+        self.publisher.latest(symbol)
+    }
+
+    /// Returns the latest price of every venue quoting a symbol
+    pub fn exchange_prices(&self, symbol: &str) -> Vec<ExchangePrice> {
+        self.prices
+            .read()
+            .unwrap()
+            .get(symbol)
+            .map(|venues| venues.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Records the latest price of a venue for a symbol
+    ///
+    /// This function:
+    /// 1. Stores the price as the venue's latest
+    /// 2. Recomputes the symbol's index if the venue is new, its mid price changed,
+    ///    its liquidity changed and the weighting strategy uses liquidity, any
+    ///    venue of the symbol became stale or fresh again since the latest
+    ///    index, or the symbol has no index
+    /// 3. Publishes the recomputed index
+    /// 4. Queues the recomputed index to the history, which logs failed writes,
+    ///    and records it and the venue deviations in the metrics, if it
+    ///    meets its venue quorum
+    ///
+    /// When no venue price is left to compute the index from, e.g. when all
+    /// the venues are stale, the symbol's index is withdrawn instead, see
+    /// `publish`.
+    ///
+    /// Returns:
+    ///   true if the index was recomputed
+    pub fn record_price(&self, symbol: &str, price: ExchangePrice) -> bool {
+        let settings = self.settings();
+        let mut prices = self.prices.write().unwrap();
+        let venues = prices.entry(symbol.to_string()).or_default();
+        let weighting = &settings.price_weighting;
+        let uses_liquidity = weighting.method.uses_liquidity();
+        let price_changed = venues.get(&price.exchange).is_none_or(|previous| {
+            previous.mid_price != price.mid_price
+                || (uses_liquidity && previous.liquidity != price.liquidity)
+        });
+        venues.insert(price.exchange.clone(), price);
+
+        if !price_changed && !self.staleness_changed(symbol, venues, weighting.max_age()) {
+            return false;
+        }
+        let index = compute_index(symbol, venues, &settings);
+        self.publish(symbol, index);
+        true
+    }
+
+    /// Re-evaluates the staleness of every venue of a symbol
+    ///
+    /// Called when a venue read fails, so that venues whose books stopped
+    /// updating leave the index even when no venue price is recorded anymore,
+    /// e.g. when the exchanges are unreachable. The index is recomputed and
+    /// published as by `record_price` if a venue became stale since the
+    /// latest index, which may withdraw it or make it lose its venue quorum.
+    ///
+    /// Returns:
+    ///   true if the index was recomputed
+    pub fn refresh(&self, symbol: &str) -> bool {
+        let settings = self.settings();
+        let mut prices = self.prices.write().unwrap();
+        let Some(venues) = prices.get_mut(symbol) else {
+            return false;
+        };
+        if self.latest(symbol).is_none()
+            || !self.staleness_changed(symbol, venues, settings.price_weighting.max_age())
+        {
+            return false;
+        }
+        let index = compute_index(symbol, venues, &settings);
+        self.publish(symbol, index);
+        true
+    }
+
+    /// Returns whether the venues of a symbol stale as of now differ from
+    /// those the latest index excluded as stale, or the symbol has no index
+    ///
+    /// A frozen book keeps its mid price, so staleness is compared to the
+    /// latest index rather than to the previous prices.
+    fn staleness_changed(
+        &self,
+        symbol: &str,
+        venues: &HashMap<String, ExchangePrice>,
+        max_age: Duration,
+    ) -> bool {
+        let Some(latest) = self.latest(symbol) else {
+            return true;
+        };
+        let now = SystemTime::now();
+        venues.values().any(|price| {
+            let was_stale = latest.excluded.iter().any(|excluded| {
+                excluded.exchange == price.exchange && excluded.reason == ExclusionReason::Stale
+            });
+            is_stale(price, max_age, now) != was_stale
+        })
+    }
+
+    /// Removes a venue from the index of a symbol
+    ///
+    /// Used when the venue's market is stopped. The index is recomputed
+    /// without the venue and published as by `record_price`, or forgotten
+    /// if no other venue quotes the symbol.
+    ///
+    /// Returns:
+    ///   true if the venue had a price for the symbol
+    pub fn remove_venue(&self, symbol: &str, exchange: &str) -> bool {
+        let settings = self.settings();
+        let mut prices = self.prices.write().unwrap();
+        let Some(venues) = prices.get_mut(symbol) else {
+            return false;
+        };
+        if venues.remove(exchange).is_none() {
+            return false;
+        }
+        if venues.is_empty() {
+            prices.remove(symbol);
+            self.publisher.clear(symbol);
+            return true;
+        }
+        let index = compute_index(symbol, venues, &settings);
+        self.publish(symbol, index);
+        true
+    }
+
+    /// Publishes, stores and exports a recomputed index
+    ///
+    /// A symbol without an index is neither stored nor published: its latest
+    /// index and its metric are cleared, so the API answers that no price is
+    /// available rather than serving a price of 0. An index short of its
+    /// venue quorum is published, flagged as such, but neither stored nor
+    /// exported.
+    ///
+    /// Called with the venue prices locked, so that the indices of a symbol
+    /// recomputed by concurrent venue tasks are published, stored and
+    /// exported in the order they were computed.
+    fn publish(&self, symbol: &str, index: Option<GlobalPriceIndex>) {
+        let previous = self.latest(symbol);
+        let Some(index) = index else {
+            if previous.is_some() {
+                warn!(symbol, "No venue price left to compute the index from");
+            }
+            self.publisher.clear(symbol);
+            METRICS.clear_index(symbol);
+            return;
+        };
+        if !index.quorum_met {
+            if previous.is_some_and(|previous| previous.quorum_met) {
+                warn!(
+                    symbol,
+                    venues = index.venues,
+                    min_venues = index.min_venues,
+                    "Index lost its venue quorum"
+                );
+            }
+            METRICS.clear_index(symbol);
+            self.publisher.publish(index);
+            return;
+        }
+        if let Some(history) = &self.history {
+            history.record(index.clone());
+        }
+        METRICS.record_index(&index);
+        self.publisher.publish(index);
+    }
+
+    /// Starts reading an exchange in the background
+    ///
+    /// The exchange's mid price is fetched every `interval` and recorded
+    /// for the symbol it tracks. The latency of every read is recorded in
+    /// the metrics. Failed reads are logged and counted, and the venue's
+    /// last known price is kept until it is stale, see `refresh`. When the
+    /// index is weighted by volume, the venue's trailing volume is refreshed
+    /// every volume refresh interval and attached to its prices. Events are
+    /// logged in a span carrying the exchange and symbol.
+    ///
+    /// Returns:
+    ///   The handle of the background task
+    pub fn spawn(&self, exchange: Arc<dyn Exchange>, interval: Duration) -> JoinHandle<()> {
+        self.spawn_polling(exchange, move || interval)
+    }
+
+    /// Starts reading an exchange in the background at a changing interval
+    ///
+    /// Reads the exchange as `spawn` does, the interval being given by
+    /// `interval` after every read, e.g. from reloaded settings. A changed
+    /// interval applies from the next read on.
+    ///
+    /// Returns:
+    ///   The handle of the background task
+    pub fn spawn_polling<F>(&self, exchange: Arc<dyn Exchange>, interval: F) -> JoinHandle<()>
+    where
+        F: Fn() -> Duration + Send + 'static,
+    {
+        let aggregator = self.clone();
+        let span = info_span!(
+            "aggregate",
+            exchange = exchange.name(),
+            symbol = exchange.symbol()
+        );
+        tokio::spawn(
+            async move {
+                let mut period = interval();
+                let mut ticker = tokio::time::interval(period);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                let mut volume = None;
+                let mut volume_refreshed_at: Option<Instant> = None;
+
+                loop {
+                    ticker.tick().await;
+                    if interval() != period {
+                        period = interval();
+                        debug!(
+                            poll_interval_ms = period.as_millis() as u64,
+                            "Poll interval changed"
+                        );
+                        ticker = tokio::time::interval_at(Instant::now() + period, period);
+                        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    }
+                    let settings = aggregator.settings();
+                    let weighting = &settings.price_weighting;
+                    if weighting.method == WeightingMethod::Volume
+                        && volume_refreshed_at
+                            .is_none_or(|at| at.elapsed() >= weighting.volume_refresh_interval())
+                    {
+                        volume_refreshed_at = Some(Instant::now());
+                        match exchange.fetch_volume().await {
+                            Ok(fetched) => volume = fetched,
+                            Err(e) => {
+                                METRICS.record_error(exchange.name(), exchange.symbol(), &e);
+                                warn!(error = %e, "Error fetching volume");
+                            }
+                        }
+                    }
+
+                    let started_at = Instant::now();
+                    let result = exchange
+                        .get_mid_price_with(&settings.mid_price, weighting.depth_bps)
+                        .await;
+                    METRICS.record_fetch(exchange.name(), exchange.symbol(), started_at.elapsed());
+                    match result {
+                        Ok(mut price) => {
+                            price.liquidity.volume = volume;
+                            if aggregator.record_price(exchange.symbol(), price) {
+                                debug!("Index recomputed");
+                            }
+                        }
+                        Err(e) => {
+                            METRICS.record_error(exchange.name(), exchange.symbol(), &e);
+                            warn!(error = %e, "Error fetching price");
+                            if aggregator.refresh(exchange.symbol()) {
+                                debug!("Index recomputed without the stale venues");
+                            }
+                        }
+                    }
+                }
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Computes the index of a symbol from the latest price of each venue
+///
+/// Returns:
+///   The index, or None if no venue price is left to compute it from, see
+///   `GlobalPriceIndex::new`
+fn compute_index(
+    symbol: &str,
+    venues: &HashMap<String, ExchangePrice>,
+    settings: &IndexSettings,
+) -> Option<GlobalPriceIndex> {
+    // Sort by venue so the published index lists exchanges in a stable order
+    let mut exchange_prices: Vec<ExchangePrice> = venues.values().cloned().collect();
+    exchange_prices.sort_by(|a, b| a.exchange.cmp(&b.exchange));
+    GlobalPriceIndex::new(
+        symbol,
+        exchange_prices,
+        &settings.price_weighting,
+        &settings.outlier_filter,
+        settings.min_venues,
+    )
+}
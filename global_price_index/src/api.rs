@@ -1,159 +1,347 @@
 // Exchange trait, factory
 
-use crate::config::{get_api_server_addr, get_frontend_server_url};
-use crate::exchanges::{
-    binance::BinanceExchange, huobi::HuobiExchange, kraken::KrakenExchange, Exchange,
-};
-use crate::models::GlobalPriceIndex;
+use crate::aggregator::{Aggregator, IndexSettings};
+use crate::config::{get_settings, ConfigSources, Settings};
+use crate::exchanges::registry::ExchangeRegistry;
+use crate::health::{get_health, get_ready};
+use crate::history::{get_candles, get_history, HistoryStore};
+use crate::metrics::get_metrics;
+use crate::reload::{post_reload, ConfigReloader};
+use crate::stream::stream_global_price;
 use actix_cors::Cors;
+use actix_web::dev::Server;
 use actix_web::{http::header, middleware, web, App, HttpResponse, HttpServer, Responder};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, error, info, instrument, warn};
 
-/// AppState holds references to all exchange instances
+/// AppState holds the indexed symbols, the exchange registry and the
+/// background aggregator
 ///
-/// This struct is shared across HTTP requests and contains
-/// thread-safe references to each exchange implementation.
-/// It allows the API handlers to access exchange data without
-/// creating new exchange instances for each request.
+/// This struct is shared across HTTP requests. The registry holds every
+/// connected exchange instance built from the configuration. The
+/// aggregator reads each of them on its own schedule and caches the
+/// latest index of each symbol, so the API handlers only read cached
+/// values and never wait on exchange round-trips.
+///
+/// The handlers read the settings given by `with_settings`, or the global
+/// settings when none were given.
 #[derive(Clone)]
 pub struct AppState {
-    pub binance: Arc<BinanceExchange>,
-    pub kraken: Arc<KrakenExchange>,
-    pub huobi: Arc<HuobiExchange>,
+    pub symbols: Vec<String>,
+    pub registry: ExchangeRegistry,
+    pub aggregator: Aggregator,
+    settings: Arc<RwLock<Option<Arc<Settings>>>>,
 }
 
 impl AppState {
-    /// Creates a new AppState with the provided exchange instances
+    /// Creates a new AppState
     ///
     /// Args:
-    ///   binance: Arc-wrapped BinanceExchange
-    ///   kraken: Arc-wrapped KrakenExchange
-    ///   huobi: Arc-wrapped HuobiExchange
+    ///   symbols: Indexed symbols, the first one being served by default
+    ///   registry: Registry of the exchanges quoting these symbols
+    ///   aggregator: Aggregator fed by the registered exchanges
     ///
     /// Returns:
     ///   A new AppState instance
-    pub fn new(
-        binance: Arc<BinanceExchange>,
-        kraken: Arc<KrakenExchange>,
-        huobi: Arc<HuobiExchange>,
-    ) -> Self {
+    pub fn new(symbols: Vec<String>, registry: ExchangeRegistry, aggregator: Aggregator) -> Self {
         Self {
-            binance,
-            kraken,
-            huobi,
+            symbols,
+            registry,
+            aggregator,
+            settings: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Serves the requests with explicit settings
+    pub fn with_settings(self, settings: Settings) -> Self {
+        self.set_settings(settings);
+        self
+    }
+
+    /// Replaces the settings the requests are served with from now on
+    pub fn set_settings(&self, settings: Settings) {
+        *self.settings.write().unwrap() = Some(Arc::new(settings));
+    }
+
+    /// Returns the settings the requests are served with
+    pub fn settings(&self) -> Arc<Settings> {
+        self.settings
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| Arc::new(get_settings()))
+    }
+
+    /// Stops the exchanges and writes the pending index history
+    ///
+    /// This function:
+    /// 1. Stops every exchange market, waiting for the WebSocket feeds to
+    ///    close their connections, see `ExchangeRegistry::shutdown`
+    /// 2. Flushes the index history to disk, see `Aggregator::flush`
+    ///
+    /// Called once the server has stopped serving requests.
+    pub async fn shutdown(&self) {
+        self.registry.shutdown().await;
+        let aggregator = self.aggregator.clone();
+        if let Ok(Err(e)) = tokio::task::spawn_blocking(move || aggregator.flush()).await {
+            error!(error = %e, "Failed to flush index history");
         }
+        info!("Shutdown complete");
     }
 }
 
+/// ApiServer is the running API server and the state its routes share
+pub struct ApiServer {
+    /// The server, running until it is stopped through its handle
+    pub server: Server,
+    /// Exchanges and aggregator of the server, shut down once it stopped
+    pub state: AppState,
+}
+
+/// Resolves a symbol given in a request path to an indexed symbol
+///
+/// Paths cannot contain the slash of "BTC/USDT", so the base and quote
+/// assets may be separated by a dash or an underscore instead. Matching
+/// is case-insensitive: "btc-usdt", "BTC_USDT" and "BTC/USDT" all
+/// resolve to "BTC/USDT".
+///
+/// Returns:
+///   The matching indexed symbol, or None if the symbol is not indexed
+pub fn resolve_symbol<'a>(symbols: &'a [String], requested: &str) -> Option<&'a str> {
+    let requested = requested.replace(['-', '_'], "/").to_uppercase();
+    symbols
+        .iter()
+        .find(|symbol| symbol.to_uppercase() == requested)
+        .map(String::as_str)
+}
+
 /// HTTP handler for the /global-price endpoint
 ///
-/// This function:
-/// 1. Fetches prices from all exchanges
-/// 2. Gracefully handles individual exchange failures
-/// 3. Creates a GlobalPriceIndex with time-based weighting
-/// 4. Returns the index as JSON response
+/// Serves the index of the default (first configured) symbol. The request
+/// is logged in a `global_price` span.
 ///
 /// Returns:
-///   HTTP 200 with GlobalPriceIndex JSON on success
-///   HTTP 503 if no exchange prices are available
+///   HTTP 200 with the cached GlobalPriceIndex JSON on success
+///   HTTP 503 if no exchange prices are available or the index is short
+///   of its venue quorum
+#[instrument(name = "global_price", skip_all)]
 pub async fn get_global_price(data: web::Data<AppState>) -> impl Responder {
-    // Create a vector to store the prices from all exchanges
-    let mut exchange_prices = Vec::new();
-
-    // Fetch prices from all exchanges
-    match data.binance.get_mid_price().await {
-        Ok(price) => {
-            exchange_prices.push(price);
-        }
-        Err(e) => println!("Error fetching Binance price: {}", e),
+    match data.symbols.first() {
+        Some(symbol) => global_price_response(&data, symbol),
+        None => HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": "No symbols are configured",
+        })),
     }
+}
 
-    // Fetch prices from Kraken
-    match data.kraken.get_mid_price().await {
-        Ok(price) => {
-            exchange_prices.push(price);
+/// HTTP handler for the /global-price/{symbol} endpoint
+///
+/// The symbol is resolved with `resolve_symbol`, e.g. "/global-price/ETH-USDT".
+/// The request is logged in a `global_price` span carrying the requested symbol.
+///
+/// Returns:
+///   HTTP 200 with the cached GlobalPriceIndex JSON on success
+///   HTTP 404 if the symbol is not indexed
+///   HTTP 503 if no exchange prices are available or the index is short
+///   of its venue quorum
+#[instrument(name = "global_price", skip_all, fields(requested = %path.as_str()))]
+pub async fn get_global_price_for_symbol(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    match resolve_symbol(&data.symbols, &path) {
+        Some(symbol) => global_price_response(&data, symbol),
+        None => {
+            debug!("Unknown symbol requested");
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("Unknown symbol: {}", path.as_str()),
+                "symbols": data.symbols,
+            }))
         }
-        Err(e) => println!("Error fetching Kraken price: {}", e),
     }
+}
 
-    // Fetch prices from Huobi
-    match data.huobi.get_mid_price().await {
-        Ok(price) => {
-            exchange_prices.push(price);
-        }
-        Err(e) => println!("Error fetching Huobi price: {}", e),
-    }
+/// HTTP handler for the /symbols endpoint
+///
+/// Returns:
+///   HTTP 200 with the JSON list of indexed symbols
+pub async fn get_symbols(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(&data.symbols)
+}
 
-    // Check if there is any price data available
-    if exchange_prices.is_empty() {
-        return HttpResponse::ServiceUnavailable().json(serde_json::json!({
-            "error": "No price data available from any exchange",
-        }));
+/// Builds the global price index response of a symbol
+///
+/// Returns:
+///   HTTP 200 with the cached GlobalPriceIndex JSON on success
+///   HTTP 503 if no exchange price has been received yet, or every venue
+///   price is left out of the index, e.g. because all the venues are stale
+///   HTTP 503 with the number of venues and the quorum if fewer than
+///   `min_venues` venues priced the index
+fn global_price_response(data: &AppState, symbol: &str) -> HttpResponse {
+    match data.aggregator.latest(symbol) {
+        Some(global_index) if !global_index.quorum_met => {
+            warn!(
+                symbol,
+                venues = global_index.venues,
+                min_venues = global_index.min_venues,
+                "Index short of its venue quorum"
+            );
+            HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "error": format!(
+                    "Only {} of the {} venues required priced the index of {}",
+                    global_index.venues, global_index.min_venues, symbol
+                ),
+                "venues": global_index.venues,
+                "min_venues": global_index.min_venues,
+                "quorum_met": false,
+            }))
+        }
+        Some(global_index) => {
+            debug!(symbol, price = %global_index.price, "Serving global price index");
+            HttpResponse::Ok().json(global_index)
+        }
+        None => {
+            warn!(symbol, "No price data available");
+            HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "error": format!("No valid price available from any exchange for {}", symbol),
+            }))
+        }
     }
-
-    // Create the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
-
-    HttpResponse::Ok().json(global_index)
 }
 
 /// Configures the API routes and state
 ///
 /// This function:
-/// 1. Initializes exchange connections
-/// 2. Sets up the AppState
-/// 3. Configures the /global-price route
-pub async fn initialize_app_state() -> AppState {
-    // Initialize exchanges
-    let binance = Arc::new(
-        BinanceExchange::new()
-            .await
-            .expect("Failed to create Binance exchange"),
-    );
-    let kraken = Arc::new(
-        KrakenExchange::new()
-            .await
-            .expect("Failed to create Kraken exchange"),
-    );
-    let huobi = Arc::new(
-        HuobiExchange::new()
-            .await
-            .expect("Failed to create Huobi exchange"),
-    );
-
-    // Create and return the app state
-    AppState::new(binance, kraken, huobi)
+/// 1. Connects every enabled (exchange, symbol) market of the settings
+///    in the background, retrying the ones that fail
+/// 2. Opens the index history database if enabled
+/// 3. Sets up the AppState serving the requests with the settings given,
+///    whose aggregator computes the indices with their weighting, outlier
+///    filter and mid price settings
+///
+/// Exchanges join the registry and the aggregator as they connect, so the
+/// server starts even if some venues are unavailable. If the history
+/// database cannot be opened, the server starts without history.
+pub fn initialize_app_state(settings: &Settings) -> AppState {
+    let registry = ExchangeRegistry::with_settings(settings.clone());
+    let mut aggregator = Aggregator::new(settings.stream.channel_capacity)
+        .with_settings(IndexSettings::from(settings));
+    if settings.history.enabled {
+        let path = &settings.history.path;
+        match HistoryStore::open(path) {
+            Ok(history) => aggregator = aggregator.with_history(history),
+            Err(e) => error!(path, error = %e, "Failed to open index history"),
+        }
+    }
+    registry.start_configured(&aggregator);
+
+    AppState::new(settings.index.symbols.clone(), registry, aggregator)
+        .with_settings(settings.clone())
 }
 
 /// Starts the HTTP server with API routes and exchange instances
 ///
 /// This function:
-/// 1. Initializes all exchange connections
-/// 2. Sets up the /global-price API route with CORS support
-/// 3. Starts the server
-pub async fn start_server() -> std::io::Result<actix_web::dev::Server> {
-    // Get server address from config
-    let addr = get_api_server_addr();
-    let frontend_url = get_frontend_server_url();
+/// 1. Starts connecting the exchanges of the settings and the background aggregator
+/// 2. Watches the configuration file for changes if enabled
+/// 3. Sets up the /global-price, /global-price/{symbol}, /symbols, /stream,
+///    /history/{symbol}, /history/{symbol}/candles, /metrics, /health and
+///    /ready API routes and the POST /admin/reload route with CORS support
+/// 4. Starts the server
+///
+/// The server does not handle signals itself. It is stopped through its
+/// handle, giving the requests in flight up to `server.drain_timeout_secs`
+/// to complete, after which `AppState::shutdown` stops the exchanges, see
+/// `shutdown_signal`.
+///
+/// Args:
+///   settings: Settings the server is started with, e.g. `config::get_settings()`
+///   for the global configuration
+///   sources: Sources the settings are loaded from again on reload, e.g.
+///   `config::get_config_sources()`
+pub async fn start_server(
+    settings: Settings,
+    sources: ConfigSources,
+) -> std::io::Result<ApiServer> {
+    // Get server address from the settings
+    let addr = settings.server.api_addr();
+    let frontend_url = settings.server.frontend_url();
 
     // Initialize exchanges
-    let app_state = web::Data::new(initialize_app_state().await);
+    let state = initialize_app_state(&settings);
+    let app_state = web::Data::new(state.clone());
+    let reloader = web::Data::new(ConfigReloader::new(sources, state.clone()));
+    if settings.reload.watch {
+        if let Err(e) = reloader.watch() {
+            error!(error = %e, "Failed to watch the configuration file");
+        }
+    }
 
     // Create and start the server
-    Ok(HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allowed_origin(&frontend_url)
             .allowed_origin(&frontend_url.replace("127.0.0.1", "localhost"))
             .allowed_methods(vec!["GET"])
-            .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT, header::CONTENT_TYPE])
+            .allowed_headers(vec![
+                header::AUTHORIZATION,
+                header::ACCEPT,
+                header::CONTENT_TYPE,
+            ])
             .max_age(3600);
 
         App::new()
             .wrap(cors)
             .wrap(middleware::Logger::default())
             .app_data(app_state.clone())
+            .app_data(reloader.clone())
             .route("/global-price", web::get().to(get_global_price))
+            .route(
+                "/global-price/{symbol}",
+                web::get().to(get_global_price_for_symbol),
+            )
+            .route("/symbols", web::get().to(get_symbols))
+            .route("/stream", web::get().to(stream_global_price))
+            .route("/history/{symbol}", web::get().to(get_history))
+            .route("/history/{symbol}/candles", web::get().to(get_candles))
+            .route("/metrics", web::get().to(get_metrics))
+            .route("/health", web::get().to(get_health))
+            .route("/ready", web::get().to(get_ready))
+            .route("/admin/reload", web::post().to(post_reload))
     })
+    .shutdown_timeout(settings.server.drain_timeout_secs)
+    .disable_signals()
     .bind(&addr)?
-    .run())
+    .run();
+
+    Ok(ApiServer { server, state })
+}
+
+/// Waits for a request to shut the service down
+///
+/// Returns the name of the signal received: SIGTERM, e.g. from a process
+/// manager, or SIGINT, e.g. Ctrl-C. Only Ctrl-C is handled on platforms
+/// without Unix signals.
+pub async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = terminate.recv() => "SIGTERM",
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+            },
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
 }
@@ -0,0 +1,386 @@
+// Recording of the raw exchange feeds for offline replay
+use crate::config::Capture as CaptureConfig;
+use crate::error::{PriceIndexError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn};
+
+/// A raw message received from an exchange
+///
+/// Ping and pong frames are not captured: they carry no market data and
+/// are answered by the WebSocket clients on both sides of a replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureEvent {
+    /// Body of a REST response and the URL requested, query included
+    Rest { url: String, body: String },
+    /// A WebSocket connection was opened, the frames that follow were received on it
+    Connect { url: String },
+    /// Text WebSocket frame
+    Text { data: String },
+    /// Binary WebSocket frame, base64-encoded
+    Binary { data: String },
+    /// Close frame sent by the exchange
+    Close,
+}
+
+impl CaptureEvent {
+    /// Builds the event of a received WebSocket frame
+    ///
+    /// Returns:
+    ///   The event, None for ping, pong and raw frames
+    pub fn from_message(message: &Message) -> Option<Self> {
+        match message {
+            Message::Text(data) => Some(CaptureEvent::Text { data: data.clone() }),
+            Message::Binary(data) => Some(CaptureEvent::Binary {
+                data: STANDARD.encode(data),
+            }),
+            Message::Close(_) => Some(CaptureEvent::Close),
+            _ => None,
+        }
+    }
+
+    /// Converts a captured frame back into the WebSocket message received
+    ///
+    /// Returns:
+    ///   The message, None for REST responses, connections and invalid base64
+    pub fn to_message(&self) -> Option<Message> {
+        match self {
+            CaptureEvent::Text { data } => Some(Message::Text(data.clone())),
+            CaptureEvent::Binary { data } => STANDARD.decode(data).ok().map(Message::Binary),
+            CaptureEvent::Close => Some(Message::Close(None)),
+            _ => None,
+        }
+    }
+}
+
+/// A captured message and the local time it was received at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    /// Receive time in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: CaptureEvent,
+}
+
+/// Request handled by the thread writing a capture file
+enum CaptureRequest {
+    /// Writes a record
+    Record(Box<CaptureRecord>),
+    /// Flushes the file once the records queued before are written, and
+    /// sends back the result
+    Flush(mpsc::Sender<std::io::Result<()>>),
+}
+
+/// Open capture file shared by the clones of a recorder
+struct CaptureFile {
+    path: PathBuf,
+    requests: mpsc::Sender<CaptureRequest>,
+}
+
+/// Writer of the raw feed of one exchange market
+///
+/// File writes block, so records are queued to a dedicated thread rather
+/// than written by the exchange tasks receiving the frames. The thread
+/// writes each record as one JSON line and flushes the file whenever its
+/// queue is empty, so a capture stays readable if the service is killed.
+/// A disabled recorder drops everything, which lets the exchange clients
+/// record unconditionally. Clones write to the same file, and the thread
+/// stops once every clone is dropped.
+#[derive(Clone, Default)]
+pub struct FeedRecorder {
+    file: Option<Arc<CaptureFile>>,
+}
+
+impl FeedRecorder {
+    /// Creates a recorder that drops everything
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Creates a recorder writing to a file, creating its directory if missing
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                PriceIndexError::CaptureError(format!(
+                    "Failed to create {}: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+        let file = File::create(path).map_err(|e| {
+            PriceIndexError::CaptureError(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+
+        let (requests, queue) = mpsc::channel();
+        let writer_path = path.to_path_buf();
+        thread::Builder::new()
+            .name("capture-writer".to_string())
+            .spawn(move || write_queued(&writer_path, BufWriter::new(file), queue))
+            .map_err(|e| {
+                PriceIndexError::CaptureError(format!(
+                    "Failed to start the writer of {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        Ok(Self {
+            file: Some(Arc::new(CaptureFile {
+                path: path.to_path_buf(),
+                requests,
+            })),
+        })
+    }
+
+    /// Creates the recorder of an exchange market as configured in `[capture]`
+    ///
+    /// The capture file is named after the exchange, the symbol and the
+    /// current time, e.g. `binance-BTC-USDT-20250408T093235.932Z.jsonl`.
+    /// A file that cannot be created is logged and disables the recorder.
+    ///
+    /// Parameters:
+    ///   exchange: Name of the exchange, e.g. "Binance"
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   capture: Whether and where the feeds are captured
+    pub fn for_market(exchange: &str, symbol: &str, capture: &CaptureConfig) -> Self {
+        if !capture.enabled {
+            return Self::disabled();
+        }
+
+        let file_name = format!(
+            "{}-{}-{}.jsonl",
+            exchange.to_lowercase(),
+            symbol.replace('/', "-"),
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+        );
+        let path = Path::new(&capture.dir).join(file_name);
+        match Self::create(&path) {
+            Ok(recorder) => {
+                info!(exchange, symbol, path = %path.display(), "Capturing feed");
+                recorder
+            }
+            Err(e) => {
+                warn!(exchange, symbol, error = %e, "Feed not captured");
+                Self::disabled()
+            }
+        }
+    }
+
+    /// Returns the path of the capture file, None if the recorder is disabled
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    /// Returns whether records are written
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Queues a record of an event received now, without waiting for the write
+    pub fn record(&self, event: CaptureEvent) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let record = CaptureRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+        };
+
+        if file
+            .requests
+            .send(CaptureRequest::Record(Box::new(record)))
+            .is_err()
+        {
+            warn!(path = %file.path.display(), "Capture writer stopped, record dropped");
+        }
+    }
+
+    /// Waits for the queued records to be written, then flushes the capture file
+    ///
+    /// Blocks the calling thread. Does nothing if the recorder is disabled.
+    pub fn flush(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let (result, flushed) = mpsc::channel();
+        file.requests
+            .send(CaptureRequest::Flush(result))
+            .ok()
+            .and_then(|_| flushed.recv().ok())
+            .unwrap_or_else(|| Err(std::io::ErrorKind::BrokenPipe.into()))
+            .map_err(|e| {
+                PriceIndexError::CaptureError(format!(
+                    "Failed to flush {}: {}",
+                    file.path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// Records the body of a REST response
+    pub fn record_rest(&self, url: &str, body: &str) {
+        if self.is_enabled() {
+            self.record(CaptureEvent::Rest {
+                url: url.to_string(),
+                body: body.to_string(),
+            });
+        }
+    }
+
+    /// Records the opening of a WebSocket connection
+    pub fn record_connect(&self, url: &str) {
+        if self.is_enabled() {
+            self.record(CaptureEvent::Connect {
+                url: url.to_string(),
+            });
+        }
+    }
+
+    /// Records a received WebSocket frame, ignoring ping and pong frames
+    pub fn record_frame(&self, message: &Message) {
+        if self.is_enabled() {
+            if let Some(event) = CaptureEvent::from_message(message) {
+                self.record(event);
+            }
+        }
+    }
+
+    /// Sends a GET request and parses its JSON response, recording the raw body
+    ///
+    /// Parameters:
+    ///   request: The request to send
+    ///
+    /// Returns:
+    ///   Result<T>: The parsed response or an error
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let response = request.send().await?;
+        let url = response.url().to_string();
+        let body = response.text().await?;
+        self.record_rest(&url, &body);
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+/// Writes the records queued to a capture file until every sender is dropped
+///
+/// The records received in a row are written before the file is flushed,
+/// so a busy feed is not flushed once per record.
+fn write_queued(path: &Path, mut writer: BufWriter<File>, queue: mpsc::Receiver<CaptureRequest>) {
+    while let Ok(request) = queue.recv() {
+        for request in std::iter::once(request).chain(queue.try_iter()) {
+            match request {
+                CaptureRequest::Record(record) => {
+                    let written = serde_json::to_string(&record)
+                        .map_err(std::io::Error::from)
+                        .and_then(|line| writeln!(writer, "{}", line));
+                    if let Err(e) = written {
+                        warn!(path = %path.display(), error = %e, "Failed to write capture");
+                    }
+                }
+                CaptureRequest::Flush(result) => {
+                    let _ = result.send(writer.flush());
+                }
+            }
+        }
+        if let Err(e) = writer.flush() {
+            warn!(path = %path.display(), error = %e, "Failed to write capture");
+        }
+    }
+}
+
+/// A WebSocket connection of a capture and the frames received on it
+#[derive(Debug, Clone)]
+pub struct CaptureSession {
+    /// URL the connection was opened to
+    pub url: String,
+    /// Frames received, in order
+    pub frames: Vec<Message>,
+}
+
+/// A capture file read back for replay
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    /// Records in the order they were received
+    pub records: Vec<CaptureRecord>,
+}
+
+impl Capture {
+    /// Reads a capture file, skipping blank lines
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| {
+            PriceIndexError::CaptureError(format!("Failed to open {}: {}", path.display(), e))
+        })?;
+
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| {
+                PriceIndexError::CaptureError(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|e| {
+                PriceIndexError::CaptureError(format!(
+                    "Invalid record at {}:{}: {}",
+                    path.display(),
+                    number + 1,
+                    e
+                ))
+            })?;
+            records.push(record);
+        }
+        Ok(Self { records })
+    }
+
+    /// Returns the REST responses as (url, body) pairs, in order
+    pub fn rest_responses(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.records
+            .iter()
+            .filter_map(|record| match &record.event {
+                CaptureEvent::Rest { url, body } => Some((url.as_str(), body.as_str())),
+                _ => None,
+            })
+    }
+
+    /// Returns the WebSocket connections with the frames received on each, in order
+    ///
+    /// Frames captured before the first connection are dropped.
+    pub fn sessions(&self) -> Vec<CaptureSession> {
+        let mut sessions: Vec<CaptureSession> = Vec::new();
+        for record in &self.records {
+            match &record.event {
+                CaptureEvent::Connect { url } => sessions.push(CaptureSession {
+                    url: url.clone(),
+                    frames: Vec::new(),
+                }),
+                event => {
+                    if let (Some(session), Some(message)) =
+                        (sessions.last_mut(), event.to_message())
+                    {
+                        session.frames.push(message);
+                    }
+                }
+            }
+        }
+        sessions
+    }
+}
@@ -0,0 +1,136 @@
+// Command line interface of the API server
+use crate::config::{ConfigSources, Settings, CONFIG_FILE};
+use crate::error::PriceIndexError;
+use clap::{Parser, Subcommand};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Command line arguments of the API server
+///
+/// Settings given on the command line override the configuration file and
+/// the `GPI_` environment variables, see `ConfigSources`.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    long_about = None,
+    about = "Serves a price index aggregated from exchange order books"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Configuration file, required if given [default: config.toml, if present]
+    #[arg(short, long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Host the API server listens on, overriding server.api_host
+    #[arg(long, global = true, value_name = "HOST")]
+    pub api_host: Option<String>,
+    /// Port the API server listens on, overriding server.api_port
+    #[arg(long, global = true, value_name = "PORT")]
+    pub api_port: Option<u16>,
+    /// Comma-separated symbols to index, overriding index.symbols
+    #[arg(long, global = true, value_name = "SYMBOLS")]
+    pub symbols: Option<String>,
+    /// Sets any configuration key, e.g. --set price_weighting.decay_factor=60
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE", value_parser = parse_override)]
+    pub overrides: Vec<(String, String)>,
+}
+
+/// Commands run instead of the servers
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Prints the effective configuration and exits non-zero if it is invalid
+    CheckConfig,
+}
+
+impl Cli {
+    /// Returns the sources of the settings selected by the arguments
+    ///
+    /// The dedicated options take precedence over `--set` values of the same key.
+    /// A configuration file given with `--config` must exist, while the
+    /// default one is skipped if missing.
+    pub fn config_sources(&self) -> ConfigSources {
+        let mut overrides = self.overrides.clone();
+        let options = [
+            ("server.api_host", self.api_host.clone()),
+            (
+                "server.api_port",
+                self.api_port.map(|port| port.to_string()),
+            ),
+            ("index.symbols", self.symbols.clone()),
+        ];
+        for (key, value) in options {
+            if let Some(value) = value {
+                overrides.push((key.to_string(), value));
+            }
+        }
+        ConfigSources {
+            file: self
+                .config
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(CONFIG_FILE)),
+            required: self.config.is_some(),
+            env: true,
+            overrides,
+        }
+    }
+}
+
+/// Runs the check-config command
+///
+/// This function:
+/// 1. Loads the settings from their sources, see `Settings::from_sources`
+/// 2. Prints them to `out` as TOML, with the values of every layer applied
+/// 3. Validates them and prints every problem found to `err`
+///
+/// Returns:
+///   io::Result<bool>: Whether the configuration is valid, or an error
+///   writing the output
+pub fn check_config(
+    sources: &ConfigSources,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> io::Result<bool> {
+    let settings = match Settings::from_sources(sources) {
+        Ok(settings) => settings,
+        Err(e) => {
+            writeln!(err, "Invalid configuration: {}", e)?;
+            return Ok(false);
+        }
+    };
+    let rendered = toml::to_string_pretty(&settings)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writeln!(
+        out,
+        "# Effective configuration of {}",
+        sources.file.display()
+    )?;
+    write!(out, "{}", rendered)?;
+
+    match settings.validate() {
+        Ok(()) => {
+            writeln!(err, "Configuration is valid")?;
+            Ok(true)
+        }
+        Err(PriceIndexError::ConfigError(problems)) => {
+            writeln!(err, "Invalid configuration, {} problem(s):", problems.len())?;
+            for problem in problems {
+                writeln!(err, "  - {}", problem)?;
+            }
+            Ok(false)
+        }
+        Err(e) => {
+            writeln!(err, "{}", e)?;
+            Ok(false)
+        }
+    }
+}
+
+/// Parses a `--set` argument into its key and value
+fn parse_override(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got {:?}", arg)),
+    }
+}
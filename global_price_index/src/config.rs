@@ -1,30 +1,122 @@
-use config::{Config, ConfigError, File};
+use crate::error::PriceIndexError;
+use crate::models::{Decimal, Precision};
+use config::{Config, ConfigError, Environment, File};
 use lazy_static::lazy_static;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::RwLock;
 use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+
+/// Configuration file loaded at startup and reloaded while running, unless
+/// another one is given with `--config`
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Prefix of the environment variables overriding the configuration file,
+/// e.g. `GPI_SERVER__API_PORT` for `server.api_port`
+pub const ENV_PREFIX: &str = "GPI";
+
+/// Keys whose environment and command line values are comma-separated lists
+const LIST_KEYS: [&str; 1] = ["index.symbols"];
+
+/// Longest default delay between two streamed updates of a symbol, one hour
+const MAX_THROTTLE_MS: u64 = 3_600_000;
 
 // Initialize global configuration
 lazy_static! {
+    /// Sources the global configuration is loaded and reloaded from, set by `init_settings`
+    static ref SOURCES: RwLock<ConfigSources> = RwLock::new(ConfigSources::default());
+
     /// Global configuration instance that is initialized once and can be accessed from anywhere
     ///
     /// Uses lazy_static for one-time initialization and RwLock for thread-safe access.
-    /// The configuration is loaded from config.toml or falls back to default values.
-    pub static ref SETTINGS: RwLock<Settings> =
-        RwLock::new(Settings::new().expect("Failed to load configuration"));
+    /// The configuration is layered from the sources set by `init_settings`, by default
+    /// config.toml, if present, and the `GPI_` environment variables over the default
+    /// values. A configuration that cannot be loaded or is invalid panics on first use
+    /// rather than running with the default values, e.g. with production endpoints;
+    /// call `init_settings` first to handle the error.
+    pub static ref SETTINGS: RwLock<Settings> = RwLock::new(
+        Settings::new()
+            .map_err(PriceIndexError::from)
+            .and_then(|settings| settings.validate().map(|_| settings))
+            .unwrap_or_else(|e| panic!("Failed to load the configuration: {}", e))
+    );
+}
+
+/// Sources the settings are layered from, each overriding the previous one
+///
+/// 1. Default values, see `Settings::default`
+/// 2. The configuration file, skipped if missing unless it is required
+/// 3. Environment variables prefixed with `GPI_`, nested keys being separated
+///    by `__`, e.g. `GPI_PRICE_WEIGHTING__DECAY_FACTOR=60`
+/// 4. Overrides given on the command line
+#[derive(Debug, Clone)]
+pub struct ConfigSources {
+    /// Configuration file, with or without its extension (default: config.toml)
+    pub file: PathBuf,
+    /// Whether a missing configuration file is an error, e.g. when given with
+    /// `--config` (default: false)
+    pub required: bool,
+    /// Whether the `GPI_` environment variables are read (default: true)
+    pub env: bool,
+    /// Values set on the command line, by dotted key such as "server.api_port"
+    pub overrides: Vec<(String, String)>,
+}
+
+impl Default for ConfigSources {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::from(CONFIG_FILE),
+            required: false,
+            env: true,
+            overrides: Vec::new(),
+        }
+    }
 }
 
 /// Server configuration settings
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Server {
     pub api_host: String,
     pub api_port: u16,
     pub frontend_host: String,
     pub frontend_port: u16,
+    /// Time given to the requests in flight to complete on shutdown, in seconds
+    pub drain_timeout_secs: u64,
+}
+
+impl Server {
+    /// Returns the API server address in format "host:port"
+    pub fn api_addr(&self) -> String {
+        format!("{}:{}", self.api_host, self.api_port)
+    }
+
+    /// Returns the frontend server address in format "host:port"
+    pub fn frontend_addr(&self) -> String {
+        format!("{}:{}", self.frontend_host, self.frontend_port)
+    }
+
+    /// Returns the API server URL
+    pub fn api_url(&self) -> String {
+        format!("http://{}", self.api_addr())
+    }
+
+    /// Returns the frontend server URL
+    pub fn frontend_url(&self) -> String {
+        format!("http://{}", self.frontend_addr())
+    }
+
+    /// Returns the time given to the requests in flight to complete on shutdown
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_secs)
+    }
 }
 
 /// Frontend paths and file locations
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Frontend {
     pub dir: String,
     pub static_dir: String,
@@ -32,42 +124,362 @@ pub struct Frontend {
     pub index_html: String,
 }
 
+/// Instruments the service computes an index for
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Index {
+    /// Canonical symbols such as "BTC/USDT"; the first one is the default
+    pub symbols: Vec<String>,
+}
+
+/// Mapping of an indexed symbol to the market listed on an exchange
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MarketConfig {
+    /// Canonical symbol as listed in `[index] symbols`, e.g. "BTC/USDT"
+    pub symbol: String,
+    /// Symbol used by the exchange API, e.g. "XBTUSDT" on Kraken
+    pub exchange_symbol: String,
+    /// Symbol used by the exchange WebSocket API when it differs from
+    /// `exchange_symbol`, e.g. "XBT/USDT" on Kraken
+    #[serde(default)]
+    pub ws_symbol: Option<String>,
+    /// Decimal places of the tick size, at most 28
+    pub price_precision: u32,
+    /// Decimal places of the lot size, at most 28
+    pub quantity_precision: u32,
+    /// Whether the market contributes to the index (default: true)
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl MarketConfig {
+    /// Returns the tick and lot precision of the market's prices and quantities
+    pub fn precision(&self) -> Precision {
+        Precision::new(self.price_precision, self.quantity_precision)
+    }
+}
+
 /// Binance-specific configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct BinanceConfig {
+    /// Whether the exchange contributes to the index (default: true)
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Base WebSocket URL, the `<symbol>@depth` stream name is appended
     pub ws_url: String,
+    /// REST depth endpoint, queried with the market's symbol
     pub rest_url: String,
+    /// REST 24h ticker endpoint, queried for the trailing traded volume
+    pub ticker_url: String,
+    /// How often the aggregator reads the streamed order book, in milliseconds
+    pub poll_interval_ms: u64,
+    pub markets: Vec<MarketConfig>,
+}
+
+impl BinanceConfig {
+    /// Returns the enabled market configured for a symbol, if any
+    pub fn market(&self, symbol: &str) -> Option<MarketConfig> {
+        find_market(&self.markets, symbol)
+    }
 }
 
 /// Kraken-specific configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct KrakenConfig {
+    /// Whether the exchange contributes to the index (default: true)
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// REST depth endpoint, used when `use_websocket` is false
     pub url: String,
+    /// REST ticker endpoint, queried for the trailing traded volume
+    pub ticker_url: String,
+    /// WebSocket endpoint of the public `book` channel
+    pub ws_url: String,
+    /// Whether to stream the book over WebSocket instead of polling the REST API
+    pub use_websocket: bool,
+    /// Depth of the streamed book (10, 25, 100, 500 or 1000)
+    pub book_depth: usize,
+    /// How often the aggregator polls the REST API, in milliseconds
+    pub poll_interval_ms: u64,
+    pub markets: Vec<MarketConfig>,
+}
+
+impl KrakenConfig {
+    /// Returns the enabled market configured for a symbol, if any
+    pub fn market(&self, symbol: &str) -> Option<MarketConfig> {
+        find_market(&self.markets, symbol)
+    }
 }
 
 /// Huobi-specific configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct HuobiConfig {
+    /// Whether the exchange contributes to the index (default: true)
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// REST depth endpoint, used when `use_websocket` is false
     pub url: String,
+    /// REST 24h market detail endpoint, queried for the trailing traded volume
+    pub ticker_url: String,
+    /// WebSocket endpoint of the market data feed
+    pub ws_url: String,
+    /// Whether to stream the book over WebSocket instead of polling the REST API
+    pub use_websocket: bool,
+    /// How often the aggregator polls the REST API, in milliseconds
+    pub poll_interval_ms: u64,
+    pub markets: Vec<MarketConfig>,
+}
+
+impl HuobiConfig {
+    /// Returns the enabled market configured for a symbol, if any
+    pub fn market(&self, symbol: &str) -> Option<MarketConfig> {
+        find_market(&self.markets, symbol)
+    }
 }
 
 /// Common exchange configuration parameters
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct ExchangeConfig {
     pub initial_reconnect_delay: u64,
     pub ping_interval: u64,
     pub max_reconnect_delay: u64,
     pub ping_retry_count: u32,
+    /// Time given to a WebSocket feed to close its connection when stopped, in seconds
+    pub close_timeout: u64,
+}
+
+impl ExchangeConfig {
+    /// Returns the delay before the first reconnection attempt
+    pub fn initial_reconnect_duration(&self) -> Duration {
+        Duration::from_secs(self.initial_reconnect_delay)
+    }
+
+    /// Returns the cap of the exponentially growing reconnection delay
+    pub fn max_reconnect_duration(&self) -> Duration {
+        Duration::from_secs(self.max_reconnect_delay)
+    }
+
+    /// Returns the interval between two WebSocket pings
+    pub fn ping_interval_duration(&self) -> Duration {
+        Duration::from_secs(self.ping_interval)
+    }
+
+    /// Returns the time given to a WebSocket feed to close its connection when stopped
+    pub fn close_timeout_duration(&self) -> Duration {
+        Duration::from_secs(self.close_timeout)
+    }
+}
+
+/// Live index streaming configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Stream {
+    /// Number of index updates buffered for slow clients before they skip ahead
+    pub channel_capacity: usize,
+    /// Minimum delay between two updates of a symbol sent to a client, in milliseconds
+    pub default_throttle_ms: u64,
+}
+
+impl Stream {
+    /// Returns the default delay between two updates of a symbol as a Duration
+    pub fn default_throttle(&self) -> Duration {
+        Duration::from_millis(self.default_throttle_ms)
+    }
+}
+
+/// Persistence of the computed indices (GET /history)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct History {
+    /// Whether every computed index is stored
+    pub enabled: bool,
+    /// Path of the SQLite database file
+    pub path: String,
+    /// Maximum number of indices returned by a history query
+    pub max_results: usize,
+}
+
+/// Recording of the raw exchange feeds for offline replay
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Capture {
+    /// Whether every REST response and WebSocket frame received is written to disk
+    pub enabled: bool,
+    /// Directory of the capture files, one per exchange market and run
+    pub dir: String,
+}
+
+/// Output format of the logs
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable multi-line output
+    #[default]
+    Pretty,
+    /// One JSON object per event, with the fields of its spans
+    Json,
+}
+
+/// Structured logging configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Logging {
+    /// Output format (default: pretty)
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Filter directives such as "info,global_price_index::exchanges=debug",
+    /// overridden by the RUST_LOG environment variable when set
+    pub filter: String,
+}
+
+/// Hot reload of the configuration file (POST /admin/reload)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Reload {
+    /// Whether changes to the configuration file are applied as they are saved
+    pub watch: bool,
+    /// Delay a change must settle for before it is applied, in milliseconds
+    pub debounce_ms: u64,
+}
+
+impl Reload {
+    /// Returns the debounce delay of the file watch as a Duration
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms)
+    }
+}
+
+/// Liveness and readiness reporting (GET /health, GET /ready)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Health {
+    /// Venue quorum of a valid index: minimum number of fresh venues of
+    /// every symbol for its index to be served, and connected ones for the
+    /// service to be ready
+    pub min_venues: usize,
+}
+
+/// Outlier rejection applied to venue prices before weighting
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OutlierFilter {
+    /// Whether outlying venue prices are left out of the index
+    pub enabled: bool,
+    /// Maximum deviation from the median price, in percent (0 disables the check)
+    pub max_deviation_pct: f64,
+    /// Maximum deviation from the median price, in multiples of the median
+    /// absolute deviation (0 disables the check)
+    pub mad_threshold: f64,
+}
+
+/// Strategy weighting the venue prices of a symbol in the index
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightingMethod {
+    /// Exponential decay with the age of the price
+    #[default]
+    TimeDecay,
+    /// Notional quoted at the best bid and best ask
+    TopOfBookDepth,
+    /// Notional quoted within `depth_bps` basis points of the mid price
+    DepthWithinBps,
+    /// Trailing 24h traded volume
+    Volume,
+}
+
+impl WeightingMethod {
+    /// Returns whether the strategy weights venues by the liquidity of their market
+    pub fn uses_liquidity(&self) -> bool {
+        *self != WeightingMethod::TimeDecay
+    }
+}
+
+/// Clock the age of a venue price is measured on
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Clock {
+    /// Local time the venue's book update was received
+    #[default]
+    Local,
+    /// Time the venue stamped the book update with, for venues that send one
+    Exchange,
 }
 
-/// Time-based price weighting configuration
-#[derive(Debug, Deserialize, Clone)]
+/// Price weighting configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct PriceWeighting {
+    /// Weighting strategy (default: time_decay)
+    #[serde(default)]
+    pub method: WeightingMethod,
+    /// Time constant of the `time_decay` strategy, in seconds
     pub decay_factor: f64,
+    /// Clock the `time_decay` strategy measures price ages on (default: local)
+    #[serde(default)]
+    pub clock: Clock,
+    /// Band around the mid price counted by the `depth_within_bps` strategy, in basis points
+    pub depth_bps: u32,
+    /// Maximum share of the total weight a single venue may have, e.g. 0.5 (0 disables the cap)
+    pub max_weight: f64,
+    /// How often the trailing volumes of the `volume` strategy are refreshed, in milliseconds
+    pub volume_refresh_ms: u64,
+    /// Age of a venue's book beyond which its price is left out of the index
+    /// as stale, in milliseconds
+    pub max_age_ms: u64,
+}
+
+impl PriceWeighting {
+    /// Returns the age beyond which a venue's price is stale
+    pub fn max_age(&self) -> Duration {
+        Duration::from_millis(self.max_age_ms)
+    }
+
+    /// Returns how often the trailing volumes of the `volume` strategy are refreshed
+    pub fn volume_refresh_interval(&self) -> Duration {
+        Duration::from_millis(self.volume_refresh_ms)
+    }
+}
+
+/// Calculation of a venue's mid price from its order book
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MidPriceMethod {
+    /// Average of the best bid and best ask
+    #[default]
+    TopOfBook,
+    /// Average of the volume-weighted prices of filling `vwap_notional` on each side
+    Vwap,
+    /// Best bid and best ask weighted by the size quoted on the opposite side
+    Microprice,
+    /// Average of the prices of buying and selling `impact_size` at market
+    Impact,
+}
+
+/// Mid price calculator configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MidPriceCalculator {
+    /// Calculation method (default: top_of_book)
+    #[serde(default)]
+    pub method: MidPriceMethod,
+    /// Quote notional filled on each side by the `vwap` method
+    pub vwap_notional: f64,
+    /// Base quantity traded on each side by the `impact` method
+    pub impact_size: f64,
 }
 
 /// Exchange-specific configurations
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Exchange {
     pub binance: BinanceConfig,
     pub kraken: KrakenConfig,
@@ -76,109 +488,505 @@ pub struct Exchange {
 }
 
 /// Main settings structure that contains all configuration sections
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Settings {
     pub server: Server,
     pub frontend: Frontend,
+    pub index: Index,
     pub exchange: Exchange,
+    pub stream: Stream,
+    pub history: History,
+    pub capture: Capture,
+    pub logging: Logging,
+    pub reload: Reload,
+    pub health: Health,
+    pub outlier_filter: OutlierFilter,
     pub price_weighting: PriceWeighting,
+    pub mid_price: MidPriceCalculator,
+}
+
+impl Default for Settings {
+    /// Default values of the settings, overridden by the configuration file
+    fn default() -> Self {
+        Self {
+            server: Server {
+                api_host: "127.0.0.1".to_string(),
+                api_port: 8080,
+                frontend_host: "127.0.0.1".to_string(),
+                frontend_port: 8081,
+                drain_timeout_secs: 10,
+            },
+            frontend: Frontend {
+                dir: "frontend".to_string(),
+                static_dir: "static".to_string(),
+                templates_dir: "templates".to_string(),
+                index_html: "index.html".to_string(),
+            },
+            index: Index {
+                symbols: vec!["BTC/USDT".to_string()],
+            },
+            exchange: Exchange {
+                binance: BinanceConfig {
+                    enabled: true,
+                    ws_url: "wss://stream.binance.com:9443/ws".to_string(),
+                    rest_url: "https://api.binance.com/api/v3/depth".to_string(),
+                    ticker_url: "https://api.binance.com/api/v3/ticker/24hr".to_string(),
+                    poll_interval_ms: 250,
+                    markets: vec![MarketConfig {
+                        symbol: "BTC/USDT".to_string(),
+                        exchange_symbol: "BTCUSDT".to_string(),
+                        ws_symbol: None,
+                        price_precision: 2,
+                        quantity_precision: 5,
+                        enabled: true,
+                    }],
+                },
+                kraken: KrakenConfig {
+                    enabled: true,
+                    url: "https://api.kraken.com/0/public/Depth".to_string(),
+                    ticker_url: "https://api.kraken.com/0/public/Ticker".to_string(),
+                    ws_url: "wss://ws.kraken.com".to_string(),
+                    use_websocket: true,
+                    book_depth: 10,
+                    poll_interval_ms: 2000,
+                    markets: vec![MarketConfig {
+                        symbol: "BTC/USDT".to_string(),
+                        exchange_symbol: "XBTUSDT".to_string(),
+                        ws_symbol: Some("XBT/USDT".to_string()),
+                        price_precision: 1,
+                        quantity_precision: 8,
+                        enabled: true,
+                    }],
+                },
+                huobi: HuobiConfig {
+                    enabled: true,
+                    url: "https://api.huobi.pro/market/depth".to_string(),
+                    ticker_url: "https://api.huobi.pro/market/detail".to_string(),
+                    ws_url: "wss://api.huobi.pro/ws".to_string(),
+                    use_websocket: true,
+                    poll_interval_ms: 2000,
+                    markets: vec![MarketConfig {
+                        symbol: "BTC/USDT".to_string(),
+                        exchange_symbol: "btcusdt".to_string(),
+                        ws_symbol: None,
+                        price_precision: 2,
+                        quantity_precision: 6,
+                        enabled: true,
+                    }],
+                },
+                config: ExchangeConfig {
+                    initial_reconnect_delay: 1,
+                    ping_interval: 30,
+                    max_reconnect_delay: 300,
+                    ping_retry_count: 3,
+                    close_timeout: 2,
+                },
+            },
+            stream: Stream {
+                channel_capacity: 64,
+                default_throttle_ms: 0,
+            },
+            history: History {
+                enabled: true,
+                path: "data/history.db".to_string(),
+                max_results: 10000,
+            },
+            capture: Capture {
+                enabled: false,
+                dir: "captures".to_string(),
+            },
+            logging: Logging {
+                format: LogFormat::Pretty,
+                filter: "info".to_string(),
+            },
+            reload: Reload {
+                watch: true,
+                debounce_ms: 500,
+            },
+            health: Health { min_venues: 2 },
+            outlier_filter: OutlierFilter {
+                enabled: true,
+                max_deviation_pct: 2.0,
+                mad_threshold: 5.0,
+            },
+            price_weighting: PriceWeighting {
+                method: WeightingMethod::TimeDecay,
+                decay_factor: 300.0, // 5 minutes default
+                clock: Clock::Local,
+                depth_bps: 10,
+                max_weight: 0.0,
+                volume_refresh_ms: 60000,
+                max_age_ms: 30000,
+            },
+            mid_price: MidPriceCalculator {
+                method: MidPriceMethod::TopOfBook,
+                vwap_notional: 100000.0,
+                impact_size: 1.0,
+            },
+        }
+    }
 }
 
 impl Settings {
-    /// Creates a new Settings instance by loading from config.toml or using defaults
-    ///
-    /// This function attempts to load configuration from a file,
-    /// and falls back to default values if the file is not found or has errors.
+    /// Creates the settings from the sources set by `init_settings`
     ///
     /// Returns:
     ///   Result<Self, ConfigError>: The settings or a configuration error
     pub fn new() -> Result<Self, ConfigError> {
-        // Try to load config file
-        let config_builder = Config::builder().add_source(File::with_name("config"));
-
-        // Attempt to build the configuration from file
-        let config_result = config_builder.build();
-
-        match config_result {
-            Ok(config) => {
-                // Successfully loaded config file, deserialize it
-                config.try_deserialize()
-            }
-            Err(err) => {
-                // Config file not found or error loading, use default values
-                eprintln!(
-                    "Warning: Could not load config file: {}, using default values",
-                    err
-                );
+        Self::from_sources(&get_config_sources())
+    }
 
-                Ok(Self {
-                    server: Server {
-                        api_host: "127.0.0.1".to_string(),
-                        api_port: 8080,
-                        frontend_host: "127.0.0.1".to_string(),
-                        frontend_port: 8081,
-                    },
-                    frontend: Frontend {
-                        dir: "frontend".to_string(),
-                        static_dir: "static".to_string(),
-                        templates_dir: "templates".to_string(),
-                        index_html: "index.html".to_string(),
-                    },
-                    exchange: Exchange {
-                        binance: BinanceConfig {
-                            ws_url: "wss://stream.binance.com:9443/ws/btcusdt@depth".to_string(),
-                            rest_url:
-                                "https://api.binance.com/api/v3/depth?symbol=BTCUSDT&limit=1000"
-                                    .to_string(),
-                        },
-                        kraken: KrakenConfig {
-                            url: "https://api.kraken.com/0/public/Depth?pair=XBTUSDT".to_string(),
-                        },
-                        huobi: HuobiConfig {
-                            url: "https://api.huobi.pro/market/depth".to_string(),
-                        },
-                        config: ExchangeConfig {
-                            initial_reconnect_delay: 1,
-                            ping_interval: 30,
-                            max_reconnect_delay: 300,
-                            ping_retry_count: 3,
-                        },
-                    },
-                    price_weighting: PriceWeighting {
-                        decay_factor: 300.0, // 5 minutes default
-                    },
-                })
+    /// Layers the settings from their sources, see `ConfigSources`
+    ///
+    /// This function fails rather than falling back to defaults when the
+    /// configuration file is unreadable, or missing while required, a value
+    /// has the wrong type or a key is unknown, e.g. misspelt.
+    ///
+    /// Returns:
+    ///   Result<Self, ConfigError>: The settings or a configuration error
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let mut builder = Config::builder()
+            .add_source(Config::try_from(&Settings::default())?)
+            .add_source(
+                File::with_name(&sources.file.to_string_lossy()).required(sources.required),
+            );
+        if sources.env {
+            let mut environment = Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("_")
+                .separator("__")
+                .try_parsing(true)
+                .list_separator(",");
+            for key in LIST_KEYS {
+                environment = environment.with_list_parse_key(key);
             }
+            builder = builder.add_source(environment);
         }
+        for (key, value) in &sources.overrides {
+            builder = if LIST_KEYS.contains(&key.as_str()) {
+                builder.set_override(key, value.split(',').collect::<Vec<_>>())?
+            } else {
+                builder.set_override(key, value.as_str())?
+            };
+        }
+        builder.build()?.try_deserialize()
     }
 
-    /// Reloads configuration from the file
+    /// Loads the settings from a configuration file over the default values
     ///
-    /// This function loads the latest configuration from disk
-    /// and updates the global SETTINGS instance.
+    /// Parameters:
+    ///   path: Path of the file, with or without its extension, which must exist
     ///
     /// Returns:
-    ///   Result<(), ConfigError>: Success or a configuration error
-    pub fn reload() -> Result<(), ConfigError> {
-        let settings = Settings::new()?;
-        let mut write_guard = SETTINGS.write().unwrap();
-        *write_guard = settings;
-        Ok(())
+    ///   Result<Self, ConfigError>: The settings or a configuration error
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        Self::from_sources(&ConfigSources {
+            file: PathBuf::from(path),
+            required: true,
+            env: false,
+            overrides: Vec::new(),
+        })
+    }
+
+    /// Checks that the settings can be applied
+    ///
+    /// This function checks every section and collects all the problems
+    /// found, each naming the offending key, such as malformed URLs,
+    /// non-positive intervals or factors, a reconnect delay range whose
+    /// maximum is below its initial delay or API and frontend servers
+    /// listening on the same address.
+    ///
+    /// Returns:
+    ///   Result<(), PriceIndexError>: Success, or a `PriceIndexError::ConfigError`
+    ///   listing every problem found
+    pub fn validate(&self) -> Result<(), PriceIndexError> {
+        let mut problems = Vec::new();
+        let mut check = |valid: bool, problem: String| {
+            if !valid {
+                problems.push(problem);
+            }
+        };
+
+        let server = &self.server;
+        let unspecified = |host: &str| host == "0.0.0.0" || host == "::";
+        check(
+            server.api_port != server.frontend_port
+                || (server.api_host != server.frontend_host
+                    && !unspecified(&server.api_host)
+                    && !unspecified(&server.frontend_host)),
+            format!(
+                "server.api_port and server.frontend_port are both {} on the same host",
+                server.api_port
+            ),
+        );
+
+        check(
+            !self.index.symbols.is_empty(),
+            "index.symbols must list at least one symbol".to_string(),
+        );
+        for (i, symbol) in self.index.symbols.iter().enumerate() {
+            check(
+                symbol.split_once('/').is_some_and(|(base, quote)| {
+                    !base.is_empty() && !quote.is_empty() && !quote.contains('/')
+                }),
+                format!("index.symbols: {:?} is not of the form BASE/QUOTE", symbol),
+            );
+            check(
+                !self.index.symbols[..i].contains(symbol),
+                format!("index.symbols: {:?} is listed twice", symbol),
+            );
+        }
+
+        let binance = &self.exchange.binance;
+        let kraken = &self.exchange.kraken;
+        let huobi = &self.exchange.huobi;
+        let urls = [
+            ("exchange.binance.ws_url", &binance.ws_url, ["ws", "wss"]),
+            (
+                "exchange.binance.rest_url",
+                &binance.rest_url,
+                ["http", "https"],
+            ),
+            (
+                "exchange.binance.ticker_url",
+                &binance.ticker_url,
+                ["http", "https"],
+            ),
+            ("exchange.kraken.url", &kraken.url, ["http", "https"]),
+            (
+                "exchange.kraken.ticker_url",
+                &kraken.ticker_url,
+                ["http", "https"],
+            ),
+            ("exchange.kraken.ws_url", &kraken.ws_url, ["ws", "wss"]),
+            ("exchange.huobi.url", &huobi.url, ["http", "https"]),
+            (
+                "exchange.huobi.ticker_url",
+                &huobi.ticker_url,
+                ["http", "https"],
+            ),
+            ("exchange.huobi.ws_url", &huobi.ws_url, ["ws", "wss"]),
+        ];
+        for (key, value, schemes) in urls {
+            match Url::parse(value) {
+                Ok(url) => check(
+                    schemes.contains(&url.scheme()),
+                    format!(
+                        "{} must be a {} URL, got {:?}",
+                        key,
+                        schemes.join("/"),
+                        value
+                    ),
+                ),
+                Err(e) => check(
+                    false,
+                    format!("{} is not a valid URL ({}): {:?}", key, e, value),
+                ),
+            }
+        }
+
+        let exchanges = [
+            (
+                "exchange.binance",
+                binance.poll_interval_ms,
+                &binance.markets,
+            ),
+            ("exchange.kraken", kraken.poll_interval_ms, &kraken.markets),
+            ("exchange.huobi", huobi.poll_interval_ms, &huobi.markets),
+        ];
+        for (section, poll_interval_ms, markets) in exchanges {
+            check(
+                poll_interval_ms > 0,
+                format!("{}.poll_interval_ms must be positive", section),
+            );
+            for (i, market) in markets.iter().enumerate() {
+                check(
+                    !market.exchange_symbol.is_empty(),
+                    format!(
+                        "{}.markets: {} has no exchange_symbol",
+                        section, market.symbol
+                    ),
+                );
+                check(
+                    !markets[..i]
+                        .iter()
+                        .any(|other| other.symbol == market.symbol),
+                    format!("{}.markets: {} is listed twice", section, market.symbol),
+                );
+                check(
+                    market.price_precision <= Decimal::MAX_SCALE
+                        && market.quantity_precision <= Decimal::MAX_SCALE,
+                    format!(
+                        "{}.markets: {} price_precision and quantity_precision must not exceed {}",
+                        section,
+                        market.symbol,
+                        Decimal::MAX_SCALE
+                    ),
+                );
+            }
+        }
+        check(
+            [10, 25, 100, 500, 1000].contains(&kraken.book_depth),
+            format!(
+                "exchange.kraken.book_depth must be 10, 25, 100, 500 or 1000, got {}",
+                kraken.book_depth
+            ),
+        );
+
+        let connection = &self.exchange.config;
+        check(
+            connection.initial_reconnect_delay > 0,
+            "exchange.config.initial_reconnect_delay must be positive".to_string(),
+        );
+        check(
+            connection.max_reconnect_delay >= connection.initial_reconnect_delay,
+            format!(
+                "exchange.config.max_reconnect_delay ({}) must not be below initial_reconnect_delay ({})",
+                connection.max_reconnect_delay, connection.initial_reconnect_delay
+            ),
+        );
+        check(
+            connection.ping_interval > 0,
+            "exchange.config.ping_interval must be positive".to_string(),
+        );
+        check(
+            connection.ping_retry_count > 0,
+            "exchange.config.ping_retry_count must be positive".to_string(),
+        );
+        check(
+            connection.close_timeout > 0,
+            "exchange.config.close_timeout must be positive".to_string(),
+        );
+
+        check(
+            server.drain_timeout_secs > 0,
+            "server.drain_timeout_secs must be positive".to_string(),
+        );
+        check(
+            self.stream.channel_capacity > 0,
+            "stream.channel_capacity must be positive".to_string(),
+        );
+        check(
+            self.stream.default_throttle_ms <= MAX_THROTTLE_MS,
+            format!(
+                "stream.default_throttle_ms must not exceed {}, got {}",
+                MAX_THROTTLE_MS, self.stream.default_throttle_ms
+            ),
+        );
+        check(
+            self.reload.debounce_ms > 0,
+            "reload.debounce_ms must be positive".to_string(),
+        );
+        check(
+            self.history.max_results > 0,
+            "history.max_results must be positive".to_string(),
+        );
+        check(
+            EnvFilter::try_new(&self.logging.filter).is_ok(),
+            format!(
+                "logging.filter {:?} is not a valid filter",
+                self.logging.filter
+            ),
+        );
+
+        check(
+            self.health.min_venues > 0,
+            "health.min_venues must be positive".to_string(),
+        );
+
+        let outlier_filter = &self.outlier_filter;
+        check(
+            outlier_filter.max_deviation_pct >= 0.0,
+            "outlier_filter.max_deviation_pct must not be negative".to_string(),
+        );
+        check(
+            outlier_filter.mad_threshold >= 0.0,
+            "outlier_filter.mad_threshold must not be negative".to_string(),
+        );
+
+        let weighting = &self.price_weighting;
+        check(
+            weighting.decay_factor > 0.0 && weighting.decay_factor.is_finite(),
+            format!(
+                "price_weighting.decay_factor must be positive, got {}",
+                weighting.decay_factor
+            ),
+        );
+        check(
+            (0.0..=1.0).contains(&weighting.max_weight),
+            format!(
+                "price_weighting.max_weight must be between 0 and 1, got {}",
+                weighting.max_weight
+            ),
+        );
+        check(
+            weighting.method != WeightingMethod::DepthWithinBps || weighting.depth_bps > 0,
+            "price_weighting.depth_bps must be positive with the depth_within_bps method"
+                .to_string(),
+        );
+        check(
+            weighting.volume_refresh_ms > 0,
+            "price_weighting.volume_refresh_ms must be positive".to_string(),
+        );
+        check(
+            weighting.max_age_ms > 0,
+            "price_weighting.max_age_ms must be positive".to_string(),
+        );
+
+        check(
+            self.mid_price.vwap_notional > 0.0 && self.mid_price.vwap_notional.is_finite(),
+            format!(
+                "mid_price.vwap_notional must be positive, got {}",
+                self.mid_price.vwap_notional
+            ),
+        );
+        check(
+            self.mid_price.impact_size > 0.0 && self.mid_price.impact_size.is_finite(),
+            format!(
+                "mid_price.impact_size must be positive, got {}",
+                self.mid_price.impact_size
+            ),
+        );
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(PriceIndexError::ConfigError(problems))
+        }
     }
 }
 
-// Convenience methods to get configuration values
+/// Sets the sources of the global settings and loads them
+///
+/// This function:
+/// 1. Loads the settings from the sources, see `Settings::from_sources`
+/// 2. Validates them
+/// 3. Replaces the global SETTINGS and keeps the sources for reloads
+///
+/// Returns:
+///   Result<(), PriceIndexError>: Success, or a configuration error leaving
+///   SETTINGS unchanged
+pub fn init_settings(sources: ConfigSources) -> Result<(), PriceIndexError> {
+    let settings = Settings::from_sources(&sources)?;
+    settings.validate()?;
+    *SOURCES.write().unwrap() = sources;
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
 
-/// Returns the Binance WebSocket URL
-pub fn get_binance_ws_url() -> String {
-    SETTINGS.read().unwrap().exchange.binance.ws_url.clone()
+/// Returns a copy of the global settings, to be passed to the components
+/// configured explicitly
+pub fn get_settings() -> Settings {
+    SETTINGS.read().unwrap().clone()
 }
 
-/// Returns the Binance REST API URL
-pub fn get_binance_rest_url() -> String {
-    SETTINGS.read().unwrap().exchange.binance.rest_url.clone()
+/// Returns the sources of the global settings
+pub fn get_config_sources() -> ConfigSources {
+    SOURCES.read().unwrap().clone()
 }
 
+// Convenience methods to get configuration values
+
 /// Returns the Kraken API URL
 pub fn get_kraken_url() -> String {
     SETTINGS.read().unwrap().exchange.kraken.url.clone()
@@ -189,87 +997,64 @@ pub fn get_huobi_url() -> String {
     SETTINGS.read().unwrap().exchange.huobi.url.clone()
 }
 
-/// Returns the initial reconnect delay as a Duration
-pub fn get_initial_reconnect_delay() -> Duration {
-    Duration::from_secs(
-        SETTINGS
-            .read()
-            .unwrap()
-            .exchange
-            .config
-            .initial_reconnect_delay,
-    )
-}
-
-/// Returns the WebSocket ping interval as a Duration
-pub fn get_ping_interval() -> Duration {
-    Duration::from_secs(SETTINGS.read().unwrap().exchange.config.ping_interval)
+/// Returns whether Binance is enabled
+pub fn is_binance_enabled() -> bool {
+    SETTINGS.read().unwrap().exchange.binance.enabled
 }
 
-/// Returns the maximum reconnect delay as a Duration
-pub fn get_max_reconnect_delay() -> Duration {
-    Duration::from_secs(SETTINGS.read().unwrap().exchange.config.max_reconnect_delay)
+/// Returns whether Kraken is enabled
+pub fn is_kraken_enabled() -> bool {
+    SETTINGS.read().unwrap().exchange.kraken.enabled
 }
 
-/// Returns the ping retry count
-pub fn get_ping_retry_count() -> u32 {
-    SETTINGS.read().unwrap().exchange.config.ping_retry_count
+/// Returns whether Huobi is enabled
+pub fn is_huobi_enabled() -> bool {
+    SETTINGS.read().unwrap().exchange.huobi.enabled
 }
 
-/// Returns the decay factor for time-based price weighting
-pub fn get_decay_factor() -> f64 {
-    SETTINGS.read().unwrap().price_weighting.decay_factor
+/// Returns the enabled Binance market configured for a symbol, if any
+pub fn get_binance_market(symbol: &str) -> Option<MarketConfig> {
+    SETTINGS.read().unwrap().exchange.binance.market(symbol)
 }
 
-/// Returns the API server address in format "host:port"
-pub fn get_api_server_addr() -> String {
-    let settings = SETTINGS.read().unwrap();
-    format!("{}:{}", settings.server.api_host, settings.server.api_port)
+/// Returns the enabled Kraken market configured for a symbol, if any
+pub fn get_kraken_market(symbol: &str) -> Option<MarketConfig> {
+    SETTINGS.read().unwrap().exchange.kraken.market(symbol)
 }
 
-/// Returns the frontend server address in format "host:port"
-pub fn get_frontend_server_addr() -> String {
-    let settings = SETTINGS.read().unwrap();
-    format!(
-        "{}:{}",
-        settings.server.frontend_host, settings.server.frontend_port
-    )
+/// Returns the enabled Huobi market configured for a symbol, if any
+pub fn get_huobi_market(symbol: &str) -> Option<MarketConfig> {
+    SETTINGS.read().unwrap().exchange.huobi.market(symbol)
 }
 
-/// Returns the API server URL for CORS configuration
-pub fn get_api_server_url() -> String {
-    let settings = SETTINGS.read().unwrap();
-    format!(
-        "http://{}:{}",
-        settings.server.api_host, settings.server.api_port
-    )
+fn find_market(markets: &[MarketConfig], symbol: &str) -> Option<MarketConfig> {
+    markets
+        .iter()
+        .find(|market| market.enabled && market.symbol == symbol)
+        .cloned()
 }
 
-/// Returns the frontend server URL for CORS configuration
-pub fn get_frontend_server_url() -> String {
-    let settings = SETTINGS.read().unwrap();
-    format!(
-        "http://{}:{}",
-        settings.server.frontend_host, settings.server.frontend_port
-    )
+/// Returns the venue quorum of a valid index, see `Health::min_venues`
+pub fn get_min_venues() -> usize {
+    SETTINGS.read().unwrap().health.min_venues
 }
 
-/// Returns the frontend directory path
-pub fn get_frontend_dir() -> String {
-    SETTINGS.read().unwrap().frontend.dir.clone()
+/// Returns the price weighting settings
+pub fn get_price_weighting() -> PriceWeighting {
+    SETTINGS.read().unwrap().price_weighting.clone()
 }
 
-/// Returns the static files directory path
-pub fn get_static_dir() -> String {
-    SETTINGS.read().unwrap().frontend.static_dir.clone()
+/// Returns the outlier rejection settings
+pub fn get_outlier_filter() -> OutlierFilter {
+    SETTINGS.read().unwrap().outlier_filter.clone()
 }
 
-/// Returns the templates directory path
-pub fn get_templates_dir() -> String {
-    SETTINGS.read().unwrap().frontend.templates_dir.clone()
+/// Returns the band around the mid price counted by depth weighting, in basis points
+pub fn get_depth_bps() -> u32 {
+    SETTINGS.read().unwrap().price_weighting.depth_bps
 }
 
-/// Returns the index HTML file name
-pub fn get_index_html() -> String {
-    SETTINGS.read().unwrap().frontend.index_html.clone()
+/// Returns the calculator of venue mid prices
+pub fn get_mid_price_calculator() -> MidPriceCalculator {
+    SETTINGS.read().unwrap().mid_price.clone()
 }
@@ -0,0 +1,248 @@
+// Headless price index engine for embedding the crate in other services
+
+use crate::aggregator::{Aggregator, IndexSettings};
+use crate::config::{MidPriceCalculator, OutlierFilter, PriceWeighting, Settings};
+use crate::error::{PriceIndexError, Result};
+use crate::exchanges::Exchange;
+use crate::models::GlobalPriceIndex;
+use crate::stream::Subscription;
+use futures::future::join_all;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// How often the exchanges are read when no interval is given
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Builder of a PriceIndexEngine
+///
+/// Every setting not given defaults to the default value of the
+/// configuration, see `Settings::default`, so neither the configuration
+/// file nor the global settings are read.
+pub struct PriceIndexEngineBuilder {
+    symbols: Vec<String>,
+    exchanges: Vec<(Arc<dyn Exchange>, Option<Duration>)>,
+    settings: IndexSettings,
+    poll_interval: Duration,
+    channel_capacity: usize,
+}
+
+impl Default for PriceIndexEngineBuilder {
+    fn default() -> Self {
+        let defaults = Settings::default();
+        Self {
+            symbols: Vec::new(),
+            exchanges: Vec::new(),
+            settings: IndexSettings::from(&defaults),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            channel_capacity: defaults.stream.channel_capacity,
+        }
+    }
+}
+
+impl PriceIndexEngineBuilder {
+    /// Sets the canonical symbols, such as "BTC/USDT", the index is computed for
+    pub fn symbols<I, S>(mut self, symbols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.symbols = symbols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds an exchange read every default poll interval
+    ///
+    /// The exchange is created beforehand, e.g. with `BinanceExchange::new`,
+    /// and tracks one of the symbols.
+    pub fn exchange(mut self, exchange: Arc<dyn Exchange>) -> Self {
+        self.exchanges.push((exchange, None));
+        self
+    }
+
+    /// Adds an exchange read every `interval`
+    pub fn exchange_with_interval(
+        mut self,
+        exchange: Arc<dyn Exchange>,
+        interval: Duration,
+    ) -> Self {
+        self.exchanges.push((exchange, Some(interval)));
+        self
+    }
+
+    /// Sets the weighting strategy, weight cap and maximum age of the venue prices
+    pub fn weighting(mut self, weighting: PriceWeighting) -> Self {
+        self.settings.price_weighting = weighting;
+        self
+    }
+
+    /// Sets the rejection of the venue prices too far from the others
+    pub fn outlier_filter(mut self, outlier_filter: OutlierFilter) -> Self {
+        self.settings.outlier_filter = outlier_filter;
+        self
+    }
+
+    /// Sets the venue quorum of a valid index
+    ///
+    /// Indices priced by fewer fresh venues are still published, with
+    /// `quorum_met` false.
+    pub fn min_venues(mut self, min_venues: usize) -> Self {
+        self.settings.min_venues = min_venues;
+        self
+    }
+
+    /// Sets the calculation of the venue mid prices from their books
+    pub fn mid_price(mut self, mid_price: MidPriceCalculator) -> Self {
+        self.settings.mid_price = mid_price;
+        self
+    }
+
+    /// Sets how often the exchanges added without an interval are read
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets the number of index updates buffered for slow subscribers
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// Starts reading the exchanges
+    ///
+    /// This function:
+    /// 1. Checks that symbols are given, that every exchange tracks one of
+    ///    them and that the buffer of index updates is not empty
+    /// 2. Starts reading every exchange in the background, recomputing the
+    ///    index of its symbol whenever its price changes, see `Aggregator::spawn`
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// Returns:
+    ///   Result<PriceIndexEngine>: The handle of the running engine, or a
+    ///   `PriceIndexError::ConfigError` listing every problem found
+    pub fn start(self) -> Result<PriceIndexEngine> {
+        let mut problems = Vec::new();
+        if self.symbols.is_empty() {
+            problems.push("symbols: at least one symbol is required".to_string());
+        }
+        for (exchange, _) in &self.exchanges {
+            if !self
+                .symbols
+                .iter()
+                .any(|symbol| symbol == exchange.symbol())
+            {
+                problems.push(format!(
+                    "exchanges: {} tracks {}, which is not an indexed symbol",
+                    exchange.name(),
+                    exchange.symbol()
+                ));
+            }
+        }
+        if self.channel_capacity == 0 {
+            problems.push("channel_capacity: must be greater than 0".to_string());
+        }
+        if !problems.is_empty() {
+            return Err(PriceIndexError::ConfigError(problems));
+        }
+
+        let aggregator = Aggregator::new(self.channel_capacity).with_settings(self.settings);
+        let mut exchanges = Vec::new();
+        let mut tasks = Vec::new();
+        for (exchange, interval) in self.exchanges {
+            let interval = interval.unwrap_or(self.poll_interval);
+            tasks.push(aggregator.spawn(exchange.clone(), interval));
+            exchanges.push(exchange);
+        }
+        info!(
+            symbols = %self.symbols.join(", "),
+            exchanges = exchanges.len(),
+            "Price index engine started"
+        );
+
+        Ok(PriceIndexEngine {
+            symbols: self.symbols,
+            exchanges,
+            aggregator,
+            tasks,
+        })
+    }
+}
+
+/// PriceIndexEngine computes the price index without serving it over HTTP
+///
+/// It is the handle of the background tasks reading the exchanges, created
+/// with `PriceIndexEngine::builder`. The tasks are cancelled and the
+/// exchange feeds stopped on `shutdown`, or when the handle is dropped.
+pub struct PriceIndexEngine {
+    symbols: Vec<String>,
+    exchanges: Vec<Arc<dyn Exchange>>,
+    aggregator: Aggregator,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl PriceIndexEngine {
+    /// Returns a builder of an engine
+    pub fn builder() -> PriceIndexEngineBuilder {
+        PriceIndexEngineBuilder::default()
+    }
+
+    /// Returns the indexed symbols
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    /// Returns the latest index computed for a symbol
+    pub fn latest(&self, symbol: &str) -> Option<GlobalPriceIndex> {
+        self.aggregator.latest(symbol)
+    }
+
+    /// Subscribes to the index updates of every symbol
+    ///
+    /// The latest index of each symbol, if any, is delivered first. The
+    /// subscription ends once the engine is shut down, see
+    /// `Subscription::into_stream` to read it as a stream.
+    pub fn subscribe(&self) -> Subscription {
+        self.subscribe_to(self.symbols.clone(), Duration::ZERO)
+    }
+
+    /// Subscribes to the index updates of some symbols
+    ///
+    /// Args:
+    ///   symbols: Canonical symbols to receive updates for
+    ///   throttle: Minimum delay between two updates of the same symbol
+    pub fn subscribe_to(&self, symbols: Vec<String>, throttle: Duration) -> Subscription {
+        self.aggregator.publisher().subscribe(symbols, throttle)
+    }
+
+    /// Stops the engine
+    ///
+    /// This function:
+    /// 1. Cancels the tasks reading the exchanges and waits for them to finish
+    /// 2. Shuts the feeds of the exchanges down at once, waiting for the
+    ///    WebSocket feeds to close their connections, see `Exchange::shutdown`
+    ///
+    /// Subscriptions end once the last index update they hold is delivered.
+    pub async fn shutdown(mut self) {
+        for task in std::mem::take(&mut self.tasks) {
+            task.abort();
+            let _ = task.await;
+        }
+        let exchanges = std::mem::take(&mut self.exchanges);
+        join_all(exchanges.iter().map(|exchange| exchange.shutdown())).await;
+        info!("Price index engine stopped");
+    }
+}
+
+impl Drop for PriceIndexEngine {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        for exchange in &self.exchanges {
+            exchange.stop();
+        }
+    }
+}
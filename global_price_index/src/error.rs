@@ -7,6 +7,7 @@ use thiserror::Error;
 /// making error handling more structured and informative. Each variant
 /// includes context information to aid in debugging and error reporting.
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum PriceIndexError {
     /// Errors related to exchange-specific failures
     #[error("Exchange error: {0}")]
@@ -27,6 +28,40 @@ pub enum PriceIndexError {
     /// Errors related to invalid price data from exchanges
     #[error("Invalid price data: {0}")]
     InvalidPriceData(String),
+
+    /// Errors of the index history database
+    #[error("Storage error: {0}")]
+    StorageError(#[from] rusqlite::Error),
+
+    /// Errors writing or reading captured exchange feeds
+    #[error("Capture error: {0}")]
+    CaptureError(String),
+
+    /// Configuration that cannot be loaded or applied, with every problem found
+    #[error("Invalid configuration: {}", .0.join("; "))]
+    ConfigError(Vec<String>),
+}
+
+impl From<config::ConfigError> for PriceIndexError {
+    fn from(error: config::ConfigError) -> Self {
+        PriceIndexError::ConfigError(vec![error.to_string()])
+    }
+}
+
+impl PriceIndexError {
+    /// Returns a short name of the error variant, used as a metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PriceIndexError::ExchangeError(_) => "exchange",
+            PriceIndexError::WebSocketError(_) => "websocket",
+            PriceIndexError::HttpError(_) => "http",
+            PriceIndexError::JsonError(_) => "json",
+            PriceIndexError::InvalidPriceData(_) => "invalid_price_data",
+            PriceIndexError::StorageError(_) => "storage",
+            PriceIndexError::CaptureError(_) => "capture",
+            PriceIndexError::ConfigError(_) => "config",
+        }
+    }
 }
 
 /// A type alias for Result that uses our custom error type
@@ -1,20 +1,25 @@
 // WebSocket client, order book sync
-use crate::config::{
-    get_binance_rest_url, get_binance_ws_url, get_initial_reconnect_delay, get_max_reconnect_delay,
-    get_ping_interval, get_ping_retry_count,
-};
+use crate::capture::FeedRecorder;
+use crate::config::{get_settings, BinanceConfig, Capture, ExchangeConfig, MarketConfig};
 use crate::error::{PriceIndexError, Result};
-use crate::exchanges::Exchange;
-use crate::models::{Order, OrderBook};
+use crate::exchanges::volume::binance_volume;
+use crate::exchanges::{close_websocket, Exchange, FeedTask};
+use crate::metrics::METRICS;
+use crate::models::{timestamp_from_millis, Decimal, Order, OrderBook, Precision, PriceLevelBook};
 use async_trait::async_trait;
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::net::TcpStream;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, info_span, trace, warn, Instrument};
 use url::Url;
 
 // Type aliases for WebSocket types
@@ -22,7 +27,7 @@ type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 type WsSink = futures::stream::SplitSink<WsStream, Message>;
 type WsStreamRead = futures::stream::SplitStream<WsStream>;
 
-/// Binance order book structure that matches the Binance API response format
+/// Binance order book snapshot that matches the REST `depth` response format
 #[derive(Debug, Serialize, Deserialize)]
 struct BinanceOrderBook {
     #[serde(rename = "bids", deserialize_with = "deserialize_binance_orders")]
@@ -31,13 +36,42 @@ struct BinanceOrderBook {
     asks: Vec<Order>,
 
     #[serde(rename = "lastUpdateId")]
-    last_update_id: i64, // Last update ID
+    last_update_id: u64, // Last update ID
+}
+
+/// Diff-depth event pushed on the `<symbol>@depth` WebSocket stream
+///
+/// `U` and `u` are the first and final update ids covered by the event.
+/// They are checked against the local book's update id to keep the book
+/// in sequence with the exchange. `E` is the event time in milliseconds.
+#[derive(Debug, Serialize, Deserialize)]
+struct BinanceDepthUpdate {
+    #[serde(rename = "E", default)]
+    event_time: Option<u64>,
+    #[serde(rename = "U")]
+    first_update_id: u64,
+    #[serde(rename = "u")]
+    final_update_id: u64,
+    #[serde(rename = "b", deserialize_with = "deserialize_binance_orders")]
+    bids: Vec<Order>,
+    #[serde(rename = "a", deserialize_with = "deserialize_binance_orders")]
+    asks: Vec<Order>,
+}
+
+/// Parses a diff-depth event, expressing its levels in the venue's precision
+///
+/// Returns None for frames that are not depth events.
+fn parse_depth_update(text: &str, precision: Precision) -> Option<BinanceDepthUpdate> {
+    let mut update = serde_json::from_str::<BinanceDepthUpdate>(text).ok()?;
+    update.bids = precision.orders(update.bids);
+    update.asks = precision.orders(update.asks);
+    Some(update)
 }
 
 /// Custom deserializer for Binance order book data
 ///
 /// Binance returns orders as [price: String, quantity: String]
-/// This function converts them to our Order struct with f64 values
+/// This function parses them exactly into our Order struct with Decimal values
 fn deserialize_binance_orders<'de, D>(deserializer: D) -> std::result::Result<Vec<Order>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -47,11 +81,11 @@ where
 
     raw.into_iter()
         .map(|[price, quantity]| {
-            let price = price.parse::<f64>().map_err(|_| {
-                D::Error::custom(format!("Failed to parse price as f64: {}", price))
+            let price = price.parse::<Decimal>().map_err(|_| {
+                D::Error::custom(format!("Failed to parse price as decimal: {}", price))
             })?;
-            let quantity = quantity.parse::<f64>().map_err(|_| {
-                D::Error::custom(format!("Failed to parse quantity as f64: {}", quantity))
+            let quantity = quantity.parse::<Decimal>().map_err(|_| {
+                D::Error::custom(format!("Failed to parse quantity as decimal: {}", quantity))
             })?;
 
             Ok(Order { price, quantity })
@@ -59,32 +93,342 @@ where
         .collect()
 }
 
+/// Maximum number of depth events held while waiting for a snapshot
+///
+/// If the buffer overflows the oldest events are dropped, which shows up as a
+/// gap once the snapshot arrives and triggers another snapshot.
+const MAX_BUFFERED_EVENTS: usize = 1000;
+
+/// Where a depth event sits relative to the local book's update id
+#[derive(Debug, PartialEq, Eq)]
+enum Sequence {
+    /// The event only contains updates already reflected in the book (`u <= lastUpdateId`)
+    Stale,
+    /// The event continues the book (`U <= lastUpdateId + 1 <= u`)
+    Continues,
+    /// Updates between the book and the event were missed (`U > lastUpdateId + 1`)
+    Gap,
+}
+
+/// Classifies a depth event against the update id of the local book
+fn check_sequence(last_update_id: u64, update: &BinanceDepthUpdate) -> Sequence {
+    if update.final_update_id <= last_update_id {
+        Sequence::Stale
+    } else if update.first_update_id > last_update_id + 1 {
+        Sequence::Gap
+    } else {
+        Sequence::Continues
+    }
+}
+
+/// Synchronization state of the local order book against the diff-depth stream
+///
+/// Follows the procedure documented by Binance for managing a local order book:
+/// 1. The stream is opened and its events are buffered while no snapshot is
+///    available
+/// 2. Events with `u <= lastUpdateId` are dropped
+/// 3. The next event must satisfy `U <= lastUpdateId + 1 <= u`
+/// 4. A gap (`U > lastUpdateId + 1`) or a crossed book discards the book and
+///    requests a new snapshot
+///
+/// The state outlives individual WebSocket connections, so after a reconnect the
+/// book keeps going if the new stream continues where the old one stopped and
+/// is re-snapshotted otherwise.
+struct DepthSync {
+    /// Update id of the last snapshot or event applied; None while awaiting a snapshot
+    last_update_id: Option<u64>,
+    /// Events received while awaiting a snapshot, in arrival order
+    buffer: VecDeque<BinanceDepthUpdate>,
+    /// Whether the book is in sync, shared with the exchange serving it
+    synced: Arc<AtomicBool>,
+}
+
+impl DepthSync {
+    /// Creates the sync state of a book awaiting its first snapshot
+    fn new(synced: Arc<AtomicBool>) -> Self {
+        synced.store(false, Ordering::Release);
+        Self {
+            last_update_id: None,
+            buffer: VecDeque::new(),
+            synced,
+        }
+    }
+
+    /// Returns true while the book is waiting for a fresh snapshot
+    fn awaiting_snapshot(&self) -> bool {
+        self.last_update_id.is_none()
+    }
+
+    /// Sets the update id of the book, None when it went out of sync
+    ///
+    /// Called with the book locked whenever the book changes, so the book is
+    /// never served while out of sync, see `fetch_order_book`.
+    fn set_last_update_id(&mut self, last_update_id: Option<u64>) {
+        self.last_update_id = last_update_id;
+        self.synced
+            .store(last_update_id.is_some(), Ordering::Release);
+    }
+
+    /// Queues an event until the next snapshot is applied
+    fn buffer_event(&mut self, update: BinanceDepthUpdate) {
+        if self.buffer.len() >= MAX_BUFFERED_EVENTS {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(update);
+    }
+
+    /// Handles a live depth event
+    ///
+    /// Returns false if the book went out of sync and a new snapshot is needed.
+    /// In that case the event is kept in the buffer so it can be replayed on top
+    /// of the snapshot.
+    async fn on_event(
+        &mut self,
+        update: BinanceDepthUpdate,
+        order_book: &RwLock<PriceLevelBook>,
+    ) -> bool {
+        match self.last_update_id {
+            None => {
+                self.buffer_event(update);
+                true
+            }
+            Some(last_update_id) => match check_sequence(last_update_id, &update) {
+                Sequence::Stale => true,
+                Sequence::Continues => {
+                    let mut order_book = order_book.write().await;
+                    self.apply(&mut order_book, &update)
+                }
+                Sequence::Gap => {
+                    warn!(
+                        last_update_id,
+                        first_update_id = update.first_update_id,
+                        final_update_id = update.final_update_id,
+                        "Binance depth gap detected, resynchronizing"
+                    );
+                    self.set_last_update_id(None);
+                    self.buffer_event(update);
+                    false
+                }
+            },
+        }
+    }
+
+    /// Replaces the book with a snapshot and replays the buffered events on top of it
+    ///
+    /// Returns false if the buffered events do not continue from the snapshot,
+    /// meaning another snapshot must be fetched. Events that could not be
+    /// applied yet remain buffered.
+    async fn on_snapshot(
+        &mut self,
+        snapshot: BinanceOrderBook,
+        order_book: &RwLock<PriceLevelBook>,
+    ) -> bool {
+        let snapshot_update_id = snapshot.last_update_id;
+        let mut order_book = order_book.write().await;
+        order_book.replace(&snapshot.bids, &snapshot.asks);
+        self.set_last_update_id(Some(snapshot_update_id));
+
+        while let Some(update) = self.buffer.pop_front() {
+            let last_update_id = self.last_update_id.unwrap_or(snapshot_update_id);
+            match check_sequence(last_update_id, &update) {
+                Sequence::Stale => {}
+                Sequence::Continues => {
+                    if !self.apply(&mut order_book, &update) {
+                        return false;
+                    }
+                }
+                Sequence::Gap => {
+                    // The snapshot is older than the buffered events, fetch another one
+                    self.set_last_update_id(None);
+                    self.buffer.push_front(update);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Applies an in-sequence event to the book
+    ///
+    /// Returns false if the resulting book is crossed, which can only happen if
+    /// it no longer matches the exchange. The book must then be re-snapshotted.
+    fn apply(&mut self, order_book: &mut PriceLevelBook, update: &BinanceDepthUpdate) -> bool {
+        // Get the current best bid and ask prices if available
+        let current_best_bid = order_book.best_bid().map(|b| b.price);
+        let current_best_ask = order_book.best_ask().map(|a| a.price);
+
+        // Merge updates rather than replacing entire book
+        order_book.apply_updates(&update.bids, &update.asks);
+        order_book.exchange_timestamp = update.event_time.map(timestamp_from_millis);
+        self.set_last_update_id(Some(update.final_update_id));
+
+        // Get the new best bid and ask prices
+        let new_best_bid = order_book.best_bid().map(|b| b.price);
+        let new_best_ask = order_book.best_ask().map(|a| a.price);
+
+        // Trace top of book changes, which happen on most events
+        if current_best_bid != new_best_bid || current_best_ask != new_best_ask {
+            trace!(
+                old_best_bid = ?current_best_bid,
+                old_best_ask = ?current_best_ask,
+                best_bid = ?new_best_bid,
+                best_ask = ?new_best_ask,
+                "Order book top levels updated"
+            );
+        }
+
+        if order_book.is_crossed() {
+            warn!(
+                best_bid = ?new_best_bid,
+                best_ask = ?new_best_ask,
+                "Binance order book crossed, resynchronizing"
+            );
+            self.set_last_update_id(None);
+            return false;
+        }
+        true
+    }
+}
+
 /// The BinanceExchange implements the Exchange trait for Binance
 ///
 /// It uses WebSockets for real-time order book updates and maintains
-/// an in-memory order book that is updated incrementally.
+/// an in-memory order book that is updated incrementally and kept in
+/// sequence with the exchange using the update ids of each depth event.
 #[derive(Clone)]
 pub struct BinanceExchange {
-    order_book: Arc<RwLock<OrderBook>>,
+    order_book: Arc<RwLock<PriceLevelBook>>,
+    symbol: String,
+    exchange_symbol: String,
+    rest_url: String,
+    ws_url: String,
+    ticker_url: String,
+    connection: ExchangeConfig,
+    precision: Precision,
+    recorder: FeedRecorder,
+    connected: Arc<AtomicBool>,
+    synced: Arc<AtomicBool>,
+    task: Arc<FeedTask>,
 }
 
 impl BinanceExchange {
-    /// Creates a new BinanceExchange instance
+    /// Creates a new BinanceExchange instance for a symbol of an explicit configuration
     ///
     /// This function:
-    /// 1. Creates an empty order book
-    /// 2. Initializes the exchange by fetching the initial order book snapshot
-    /// 3. Starts a WebSocket connection for real-time updates
+    /// 1. Looks up the Binance market mapped to the symbol
+    /// 2. Builds the snapshot and diff-depth stream URLs of that market
+    /// 3. Initializes the exchange by opening the WebSocket connection for
+    ///    real-time updates and synchronizing it with an order book snapshot
+    ///
+    /// The raw feed is captured to disk when `[capture]` is enabled.
+    ///
+    /// Parameters:
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   config: Endpoints and markets of Binance
+    ///   connection: Reconnection and ping settings of the WebSocket stream
+    ///   capture: Whether and where the raw feed is captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance or an error
+    pub async fn new(
+        symbol: &str,
+        config: &BinanceConfig,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let market = config.market(symbol).ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!("No Binance market configured for {}", symbol))
+        })?;
+        let rest_url = format!(
+            "{}?symbol={}&limit=1000",
+            config.rest_url, market.exchange_symbol
+        );
+        let ws_url = format!(
+            "{}/{}@depth",
+            config.ws_url.trim_end_matches('/'),
+            market.exchange_symbol.to_lowercase()
+        );
+        Self::connect(
+            market,
+            rest_url,
+            ws_url,
+            config.ticker_url.clone(),
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates a new BinanceExchange instance for a symbol of the global configuration
+    pub async fn configured(symbol: &str) -> Result<Self> {
+        let settings = get_settings();
+        Self::new(
+            symbol,
+            &settings.exchange.binance,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    }
+
+    /// Creates a new BinanceExchange instance against explicit endpoints
+    ///
+    /// This allows pointing the exchange at a local stand-in of the Binance
+    /// REST and WebSocket APIs.
+    ///
+    /// Args:
+    ///   market: Market whose symbol and precision the exchange uses
+    ///   rest_url: Full URL of the order book snapshot endpoint
+    ///   ws_url: Full URL of the diff-depth WebSocket stream
+    ///   ticker_url: URL of the 24h ticker endpoint the volume is read from
+    ///   connection: Reconnection and ping settings of the WebSocket stream
+    ///   capture: Whether and where the raw feed is captured
     ///
     /// Returns:
     ///   Result<Self>: The exchange instance or an error
-    pub async fn new() -> Result<Self> {
-        let order_book = Arc::new(RwLock::new(OrderBook {
-            bids: vec![],
-            asks: vec![],
-            timestamp: SystemTime::now(),
-        }));
-        let exchange = Self { order_book };
+    pub async fn with_urls(
+        market: MarketConfig,
+        rest_url: impl Into<String>,
+        ws_url: impl Into<String>,
+        ticker_url: impl Into<String>,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        Self::connect(
+            market,
+            rest_url.into(),
+            ws_url.into(),
+            ticker_url.into(),
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates the exchange instance and initializes it
+    async fn connect(
+        market: MarketConfig,
+        rest_url: String,
+        ws_url: String,
+        ticker_url: String,
+        connection: ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let order_book = Arc::new(RwLock::new(PriceLevelBook::new()));
+        let exchange = Self {
+            order_book,
+            recorder: FeedRecorder::for_market("Binance", &market.symbol, capture),
+            precision: market.precision(),
+            symbol: market.symbol,
+            exchange_symbol: market.exchange_symbol,
+            rest_url,
+            ws_url,
+            ticker_url,
+            connection,
+            connected: Arc::new(AtomicBool::new(false)),
+            synced: Arc::new(AtomicBool::new(false)),
+            task: Arc::new(FeedTask::default()),
+        };
 
         exchange.initialize().await?;
         Ok(exchange)
@@ -93,36 +437,64 @@ impl BinanceExchange {
     /// Initializes the exchange by fetching the initial order book data
     ///
     /// This function:
-    /// 1. Fetches the initial order book snapshot from Binance REST API
-    /// 2. Updates the in-memory order book with the snapshot data
-    /// 3. Starts a WebSocket connection for real-time updates
+    /// 1. Opens the WebSocket connection for real-time updates
+    /// 2. Fetches the initial order book snapshot from Binance REST API,
+    ///    buffering the depth events received meanwhile
+    /// 3. Updates the in-memory order book with the snapshot data and the
+    ///    buffered events continuing it, see `DepthSync::on_snapshot`
+    /// 4. Hands the connection over to the WebSocket task, which fetches
+    ///    another snapshot if the buffered events did not continue this one
     async fn initialize(&self) -> Result<()> {
-        // Fetch initial order book data from Binance REST API
-        let client = reqwest::Client::new();
-        let response: BinanceOrderBook = client
-            .get(&get_binance_rest_url())
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        // Update the order book with the initial data
-        let mut order_book = self.order_book.write().await;
-        order_book.bids = response.bids;
-        order_book.asks = response.asks;
-        order_book.timestamp = SystemTime::now();
-
-        // Start WebSocket connection
-        self.start_websocket().await?;
+        let (write, mut read) = Self::connect_websocket(&self.ws_url).await?;
+        self.recorder.record_connect(&self.ws_url);
+
+        let mut sync = DepthSync::new(self.synced.clone());
+        let snapshot =
+            Self::fetch_snapshot(self.rest_url.clone(), self.precision, self.recorder.clone());
+        tokio::pin!(snapshot);
+        let snapshot = loop {
+            tokio::select! {
+                snapshot = &mut snapshot => break snapshot?,
+                Some(message) = read.next() => {
+                    if let Ok(message) = &message {
+                        self.recorder.record_frame(message);
+                    }
+                    if let Ok(Message::Text(text)) = message {
+                        if let Some(update) = parse_depth_update(&text, self.precision) {
+                            sync.on_event(update, &self.order_book).await;
+                        }
+                    }
+                }
+            }
+        };
+        sync.on_snapshot(snapshot, &self.order_book).await;
+
+        self.task
+            .set(self.start_websocket(sync, Some((write, read))));
         Ok(())
     }
 
+    /// Fetches an order book snapshot from the Binance REST API
+    ///
+    /// Levels are expressed in the venue's tick and lot precision.
+    async fn fetch_snapshot(
+        rest_url: String,
+        precision: Precision,
+        recorder: FeedRecorder,
+    ) -> Result<BinanceOrderBook> {
+        let client = reqwest::Client::new();
+        let mut response: BinanceOrderBook = recorder.get_json(client.get(rest_url)).await?;
+        response.bids = precision.orders(response.bids);
+        response.asks = precision.orders(response.asks);
+        Ok(response)
+    }
+
     /// Establishes a WebSocket connection to Binance
     ///
     /// Returns:
     ///   Result<(WsSink, WsStreamRead)>: The WebSocket write and read streams
-    async fn connect_websocket() -> Result<(WsSink, WsStreamRead)> {
-        let url = Url::parse(&get_binance_ws_url()).map_err(|e| {
+    async fn connect_websocket(ws_url: &str) -> Result<(WsSink, WsStreamRead)> {
+        let url = Url::parse(ws_url).map_err(|e| {
             PriceIndexError::WebSocketError(format!("Failed to parse WebSocket URL: {}", e))
         })?;
 
@@ -133,125 +505,68 @@ impl BinanceExchange {
         Ok(ws_stream.split())
     }
 
-    /// Merges order book updates into the existing order book
-    ///
-    /// This function:
-    /// 1. Applies incremental updates to the order book
-    /// 2. Adds new price levels, updates existing ones, or removes levels with zero quantity
-    /// 3. Sorts the orders appropriately (bids descending, asks ascending)
-    ///
-    /// Args:
-    ///   existing_orders: The current list of orders to update
-    ///   updates: The new orders to apply as updates
-    ///   is_bids: Whether we're updating bids (true) or asks (false)
-    fn merge_order_book_updates(
-        existing_orders: &mut Vec<Order>,
-        updates: &[Order],
-        is_bids: bool,
-    ) {
-        // Create a combined list of all orders
-        let mut all_orders = existing_orders.clone();
-
-        // Apply updates
-        for update in updates {
-            let price = update.price;
-            let quantity = update.quantity;
-
-            // Check if this price level already exists
-            if let Some(existing_idx) = all_orders
-                .iter()
-                .position(|order| (order.price - price).abs() < f64::EPSILON)
-            {
-                if quantity > 0.0 {
-                    // Update existing order
-                    all_orders[existing_idx].quantity = quantity;
-                } else {
-                    // Remove the order (zero quantity indicates deletion)
-                    all_orders.remove(existing_idx);
-                }
-            } else if quantity > 0.0 {
-                // Add new order
-                all_orders.push(Order { price, quantity });
-            }
-        }
-
-        // Sort all orders
-        if is_bids {
-            // Sort bids in descending order (highest bid first)
-            all_orders.sort_by(|a, b| {
-                b.price
-                    .partial_cmp(&a.price)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-        } else {
-            // Sort asks in ascending order (lowest ask first)
-            all_orders.sort_by(|a, b| {
-                a.price
-                    .partial_cmp(&b.price)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-        }
-
-        // Replace the existing orders with the updated ones
-        *existing_orders = all_orders;
-    }
-
     /// Handles WebSocket messages and updates the order book
     ///
     /// This function:
-    /// 1. Processes incoming WebSocket messages
-    /// 2. Updates the order book with incremental changes
+    /// 1. Processes incoming depth events through the sequence checks of DepthSync
+    /// 2. Fetches a new snapshot in the background whenever the book goes out of
+    ///    sync, buffering events until it arrives
     /// 3. Maintains the WebSocket connection with ping/pong messages
     /// 4. Handles connection errors and closures
+    /// 5. Records every received frame to the feed capture
+    /// 6. Closes the connection with a close frame once `cancel` is cancelled
+    #[allow(clippy::too_many_arguments)]
     async fn handle_websocket_messages(
         mut read: WsStreamRead,
         mut write: WsSink,
-        order_book: Arc<RwLock<OrderBook>>,
+        order_book: Arc<RwLock<PriceLevelBook>>,
+        sync: &mut DepthSync,
+        rest_url: &str,
+        precision: Precision,
+        recorder: &FeedRecorder,
+        connection: &ExchangeConfig,
+        cancel: &CancellationToken,
     ) {
         let mut last_pong = SystemTime::now();
-        let mut ping_interval = tokio::time::interval(get_ping_interval());
+        let mut ping_interval = tokio::time::interval(connection.ping_interval_duration());
+        let mut snapshot_task: Option<JoinHandle<Result<BinanceOrderBook>>> = None;
+
+        // A previous connection may have dropped while a snapshot was pending
+        if sync.awaiting_snapshot() {
+            snapshot_task = Some(tokio::spawn(Self::fetch_snapshot(
+                rest_url.to_string(),
+                precision,
+                recorder.clone(),
+            )));
+        }
 
-        println!("WebSocket message handler started");
+        debug!("WebSocket message handler started");
         loop {
             tokio::select! {
+                _ = cancel.cancelled() => {
+                    close_websocket(&mut write, connection.close_timeout_duration()).await;
+                    break;
+                }
                 Some(message) = read.next() => {
+                    if let Ok(message) = &message {
+                        recorder.record_frame(message);
+                    }
                     match message {
                         Ok(Message::Text(text)) => {
-                            if let Ok(update) = serde_json::from_str::<BinanceOrderBook>(&text) {
-                                let mut order_book = order_book.write().await;
-                                // Only update if we have valid data
-                                if !update.bids.is_empty() && !update.asks.is_empty() {
-                                    // Get the current best bid and ask prices if available
-                                    let current_best_bid = order_book.bids.first().map(|b| b.price);
-                                    let current_best_ask = order_book.asks.first().map(|a| a.price);
-
-                                    // Merge updates rather than replacing entire book
-                                    Self::merge_order_book_updates(&mut order_book.bids, &update.bids, true);
-                                    Self::merge_order_book_updates(&mut order_book.asks, &update.asks, false);
-
-                                    // Get the new best bid and ask prices
-                                    let new_best_bid = order_book.bids.first().map(|b| b.price);
-                                    let new_best_ask = order_book.asks.first().map(|a| a.price);
-
-                                    // Log if best prices have changed
-                                    if current_best_bid != new_best_bid || current_best_ask != new_best_ask {
-                                        println!("Order book top levels updated - Old: {:?}/{:?} New: {:?}/{:?}",
-                                            current_best_bid, current_best_ask, new_best_bid, new_best_ask);
-                                    }
-
-                                    // Always update the timestamp when we receive valid data
-                                    order_book.timestamp = SystemTime::now();
+                            if let Some(update) = parse_depth_update(&text, precision) {
+                                if !sync.on_event(update, &order_book).await && snapshot_task.is_none() {
+                                    snapshot_task = Some(tokio::spawn(Self::fetch_snapshot(rest_url.to_string(), precision, recorder.clone())));
                                 }
                             }
                         }
                         Ok(Message::Close(_)) => {
-                            eprintln!("WebSocket connection closed");
+                            warn!("WebSocket connection closed");
                             break;
                         }
                         Ok(Message::Ping(payload)) => {
                             // Respond to ping with pong, with retry logic
                             let mut retry_count = 0;
-                            let max_retries = get_ping_retry_count();
+                            let max_retries = connection.ping_retry_count;
                             while retry_count < max_retries {
                                 match write.send(Message::Pong(payload.clone())).await {
                                     Ok(_) => {
@@ -259,10 +574,9 @@ impl BinanceExchange {
                                     }
                                     Err(e) => {
                                         retry_count += 1;
-                                        eprintln!("Failed to send pong response (attempt {}/{}): {}",
-                                            retry_count, max_retries, e);
+                                        warn!(attempt = retry_count, max_retries, error = %e, "Failed to send pong response");
                                         if retry_count >= max_retries {
-                                            eprintln!("Max pong retry attempts reached, reconnecting...");
+                                            warn!("Max pong retry attempts reached, reconnecting");
                                             break;
                                         }
                                         sleep(Duration::from_millis(100)).await;
@@ -275,25 +589,44 @@ impl BinanceExchange {
                         }
                         Ok(Message::Pong(_)) => {
                             last_pong = SystemTime::now();
-                            println!("Received pong, connection is healthy");
+                            trace!("Received pong, connection is healthy");
                         }
                         Err(e) => {
-                            eprintln!("WebSocket error: {}", e);
+                            warn!(error = %e, "WebSocket error");
                             break;
                         }
                         _ => {}
                     }
                 }
+                result = async { snapshot_task.as_mut().unwrap().await }, if snapshot_task.is_some() => {
+                    snapshot_task = None;
+                    match result {
+                        Ok(Ok(snapshot)) => {
+                            info!(last_update_id = snapshot.last_update_id, "Applying Binance order book snapshot");
+                            if !sync.on_snapshot(snapshot, &order_book).await {
+                                snapshot_task = Some(tokio::spawn(Self::fetch_snapshot(rest_url.to_string(), precision, recorder.clone())));
+                            }
+                        }
+                        Ok(Err(e)) => {
+                            warn!(error = %e, "Failed to fetch Binance order book snapshot");
+                            break;
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Binance snapshot task failed");
+                            break;
+                        }
+                    }
+                }
                 _ = ping_interval.tick() => {
                     // Check if we haven't received a pong for too long
-                    if last_pong.elapsed().unwrap_or(Duration::from_secs(0)) > get_ping_interval() * 2 {
-                        eprintln!("No pong received for too long, reconnecting...");
+                    if last_pong.elapsed().unwrap_or(Duration::from_secs(0)) > connection.ping_interval_duration() * 2 {
+                        warn!("No pong received for too long, reconnecting");
                         break;
                     }
 
                     // Send a ping to keep the connection alive, with retry logic
                     let mut retry_count = 0;
-                    let max_retries = get_ping_retry_count();
+                    let max_retries = connection.ping_retry_count;
                     while retry_count < max_retries {
                         match write.send(Message::Ping(vec![])).await {
                             Ok(_) => {
@@ -301,10 +634,9 @@ impl BinanceExchange {
                             }
                             Err(e) => {
                                 retry_count += 1;
-                                eprintln!("Failed to send ping (attempt {}/{}): {}",
-                                    retry_count, max_retries, e);
+                                warn!(attempt = retry_count, max_retries, error = %e, "Failed to send ping");
                                 if retry_count >= max_retries {
-                                    eprintln!("Max ping retry attempts reached, reconnecting...");
+                                    warn!("Max ping retry attempts reached, reconnecting");
                                     break;
                                 }
                                 sleep(Duration::from_millis(100)).await;
@@ -317,6 +649,10 @@ impl BinanceExchange {
                 }
             }
         }
+
+        if let Some(task) = snapshot_task {
+            task.abort();
+        }
     }
 
     /// Starts the WebSocket connection with automatic reconnection
@@ -325,42 +661,106 @@ impl BinanceExchange {
     /// 1. Establishes a WebSocket connection to Binance
     /// 2. Spawns a task to handle WebSocket messages
     /// 3. Implements exponential backoff for reconnection attempts
-    /// 4. Continues reconnecting indefinitely to maintain data flow
-    async fn start_websocket(&self) -> Result<()> {
+    /// 4. Continues reconnecting to maintain data flow until the feed is
+    ///    stopped, see `FeedTask`
+    /// 5. Counts reconnections and connection errors in the metrics
+    ///
+    /// Events are logged in a span carrying the exchange and symbol, and
+    /// the events of each connection in a nested span carrying its attempt
+    /// number.
+    ///
+    /// The sync state is carried across reconnections so the stream of a new
+    /// connection is checked against the book built from the previous one.
+    /// The first connection is the one opened by `initialize`.
+    ///
+    /// Returns:
+    ///   The handle of the stream task
+    fn start_websocket(
+        &self,
+        mut sync: DepthSync,
+        mut stream: Option<(WsSink, WsStreamRead)>,
+    ) -> JoinHandle<()> {
         let order_book = self.order_book.clone();
-        let mut reconnect_attempt = 0;
-        let mut reconnect_delay = get_initial_reconnect_delay();
-        let max_reconnect_delay = get_max_reconnect_delay();
-
-        tokio::spawn(async move {
-            loop {
-                match Self::connect_websocket().await {
-                    Ok((write, read)) => {
-                        // Reset reconnection parameters on successful connection
-                        reconnect_attempt = 0;
-                        reconnect_delay = get_initial_reconnect_delay();
-                        Self::handle_websocket_messages(read, write, order_book.clone()).await;
+        let rest_url = self.rest_url.clone();
+        let ws_url = self.ws_url.clone();
+        let precision = self.precision;
+        let recorder = self.recorder.clone();
+        let connected = self.connected.clone();
+        let symbol = self.symbol.clone();
+        let span = info_span!("websocket", exchange = "Binance", symbol = %symbol);
+        let connection = self.connection.clone();
+        let mut attempt: u64 = 0;
+        let mut reconnect_delay = connection.initial_reconnect_duration();
+        let max_reconnect_delay = connection.max_reconnect_duration();
+        let cancel = self.task.token();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    attempt += 1;
+                    async {
+                        let connected_stream = match stream.take() {
+                            Some(stream) => Some(Ok(stream)),
+                            None => cancel
+                                .run_until_cancelled(Self::connect_websocket(&ws_url))
+                                .await
+                                .map(|result| result.inspect(|_| recorder.record_connect(&ws_url))),
+                        };
+                        match connected_stream {
+                            Some(Ok((write, read))) => {
+                                // Reset the delay on successful connection
+                                reconnect_delay = connection.initial_reconnect_duration();
+                                info!("Connected to Binance WebSocket");
+                                connected.store(true, Ordering::Relaxed);
+                                Self::handle_websocket_messages(
+                                    read,
+                                    write,
+                                    order_book.clone(),
+                                    &mut sync,
+                                    &rest_url,
+                                    precision,
+                                    &recorder,
+                                    &connection,
+                                    &cancel,
+                                )
+                                .await;
+                                connected.store(false, Ordering::Relaxed);
+                            }
+                            Some(Err(e)) => {
+                                METRICS.record_error("Binance", &symbol, &e);
+                                warn!(error = %e, "Failed to connect to WebSocket");
+                            }
+                            None => {}
+                        }
                     }
-                    Err(e) => {
-                        eprintln!("Failed to connect to WebSocket: {}", e);
+                    .instrument(info_span!("connection", attempt))
+                    .await;
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    METRICS.record_reconnect("Binance", &symbol);
+
+                    // Implement exponential backoff for reconnection with a maximum cap
+                    warn!(
+                        delay_secs = reconnect_delay.as_secs(),
+                        next_attempt = attempt + 1,
+                        "Attempting to reconnect"
+                    );
+                    if cancel
+                        .run_until_cancelled(sleep(reconnect_delay))
+                        .await
+                        .is_none()
+                    {
+                        break;
                     }
-                }
 
-                // Implement exponential backoff for reconnection with a maximum cap
-                eprintln!(
-                    "Attempting to reconnect in {} seconds (attempt {})",
-                    reconnect_delay.as_secs(),
-                    reconnect_attempt + 1
-                );
-                sleep(reconnect_delay).await;
-                reconnect_attempt += 1;
-
-                // Double the delay with a cap at max_reconnect_delay
-                reconnect_delay = std::cmp::min(reconnect_delay * 2, max_reconnect_delay);
+                    // Double the delay with a cap at max_reconnect_delay
+                    reconnect_delay = std::cmp::min(reconnect_delay * 2, max_reconnect_delay);
+                }
+                info!("Binance feed stopped");
             }
-        });
-
-        Ok(())
+            .instrument(span),
+        )
     }
 }
 
@@ -371,11 +771,49 @@ impl Exchange for BinanceExchange {
         "Binance"
     }
 
+    /// Returns the canonical symbol of the tracked market
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns whether the WebSocket stream is connected
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Stops the WebSocket stream
+    fn stop(&self) {
+        self.task.stop();
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    /// Closes the WebSocket stream and waits for it to end
+    async fn shutdown(&self) {
+        self.task
+            .shutdown(self.connection.close_timeout_duration())
+            .await;
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
     /// Fetches the current order book
     ///
-    /// This implementation returns the in-memory order book
-    /// that's continuously updated via WebSocket
+    /// This implementation returns a copy of the in-memory order book
+    /// that's continuously updated via WebSocket, or an error while the
+    /// book is out of sync and awaits a new snapshot
     async fn fetch_order_book(&self) -> Result<OrderBook> {
-        Ok(self.order_book.read().await.clone())
+        let order_book = self.order_book.read().await;
+        if !self.synced.load(Ordering::Acquire) {
+            return Err(PriceIndexError::ExchangeError(
+                "Binance order book is resynchronizing".to_string(),
+            ));
+        }
+        Ok(order_book.to_order_book())
+    }
+
+    /// Fetches the trailing 24h traded volume from the Binance ticker
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        binance_volume(&self.ticker_url, &self.exchange_symbol)
+            .await
+            .map(Some)
     }
 }
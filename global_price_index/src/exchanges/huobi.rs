@@ -1,20 +1,53 @@
 // REST client, polling logic
-use crate::config::get_huobi_url;
+use crate::capture::FeedRecorder;
+use crate::config::{get_settings, Capture, HuobiConfig, MarketConfig};
 use crate::error::{PriceIndexError, Result};
+use crate::exchanges::volume::huobi_volume;
 use crate::exchanges::Exchange;
-use crate::models::{Order, OrderBook};
+use crate::models::{timestamp_from_millis, Decimal, Order, OrderBook, Precision};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
 
 /// Huobi-specific implementation of the order book
 ///
-/// Unlike other exchanges, Huobi returns price and quantity as float values directly,
-/// so no custom deserializer is needed
+/// Unlike other exchanges, Huobi returns price and quantity as JSON numbers
+/// rather than strings. `ts` is the time of the book in milliseconds.
 #[derive(Debug, Serialize, Deserialize)]
-struct HuobiOrderBook {
-    bids: Vec<Order>,
-    asks: Vec<Order>,
+pub(crate) struct HuobiOrderBook {
+    #[serde(deserialize_with = "deserialize_huobi_orders")]
+    pub(crate) bids: Vec<Order>,
+    #[serde(deserialize_with = "deserialize_huobi_orders")]
+    pub(crate) asks: Vec<Order>,
+    #[serde(default)]
+    pub(crate) ts: Option<u64>,
+}
+
+/// Custom deserializer for Huobi order data format
+///
+/// Huobi returns orders as [price: Number, quantity: Number]
+/// The numbers are read as f64, whose shortest round-trip representation is
+/// the decimal text sent by Huobi, so formatting them back yields the exact
+/// decimal value
+fn deserialize_huobi_orders<'de, D>(deserializer: D) -> std::result::Result<Vec<Order>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let raw: Vec<[f64; 2]> = Vec::deserialize(deserializer)?;
+
+    raw.into_iter()
+        .map(|[price, quantity]| {
+            let price = price.to_string().parse::<Decimal>().map_err(|_| {
+                D::Error::custom(format!("Failed to parse price as decimal: {}", price))
+            })?;
+            let quantity = quantity.to_string().parse::<Decimal>().map_err(|_| {
+                D::Error::custom(format!("Failed to parse quantity as decimal: {}", quantity))
+            })?;
+
+            Ok(Order { price, quantity })
+        })
+        .collect()
 }
 
 /// The full response from Huobi API
@@ -28,7 +61,7 @@ struct HuobiResponse {
     err_code: Option<String>,
     #[serde(rename = "err-msg")]
     err_msg: Option<String>,
-    ts: i64,                      // timestamp
+    ts: u64,                      // response time in milliseconds
     tick: Option<HuobiOrderBook>, // order book
 }
 
@@ -36,21 +69,85 @@ struct HuobiResponse {
 ///
 /// This exchange uses REST API polling rather than WebSockets,
 /// making periodic HTTP requests to fetch the current order book.
+/// See `HuobiWsExchange` for the streaming implementation.
 pub struct HuobiExchange {
     client: reqwest::Client,
+    url: String,
+    ticker_url: String,
+    market: MarketConfig,
+    precision: Precision,
+    recorder: FeedRecorder,
 }
 
 impl HuobiExchange {
-    /// Creates a new HuobiExchange instance
+    /// Creates a new HuobiExchange instance for a symbol of an explicit configuration
+    ///
+    /// This function:
+    /// 1. Looks up the Huobi market mapped to the symbol
+    /// 2. Creates the exchange against the API URL of the configuration, see `with_url`
+    ///
+    /// Parameters:
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   config: Endpoints and markets of Huobi
+    ///   capture: Whether and where the raw responses are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance or an error
+    pub async fn new(symbol: &str, config: &HuobiConfig, capture: &Capture) -> Result<Self> {
+        let market = config.market(symbol).ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!("No Huobi market configured for {}", symbol))
+        })?;
+        Self::connect(
+            market,
+            config.url.clone(),
+            config.ticker_url.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates a new HuobiExchange instance for a symbol of the global configuration
+    pub async fn configured(symbol: &str) -> Result<Self> {
+        let settings = get_settings();
+        Self::new(symbol, &settings.exchange.huobi, &settings.capture).await
+    }
+
+    /// Creates a new HuobiExchange instance against an explicit endpoint
+    ///
+    /// This allows pointing the exchange at a local stand-in of the Huobi
+    /// REST API, such as a replayed capture.
     ///
     /// This function:
     /// 1. Creates an HTTP client with a 5-second timeout
     /// 2. Verifies the exchange is accessible by making a test API request
     /// 3. Returns the exchange instance if successful
     ///
+    /// Args:
+    ///   market: Market whose symbol and precision the exchange uses
+    ///   url: URL of the order book endpoint
+    ///   ticker_url: URL of the ticker endpoint the volume is read from
+    ///   capture: Whether and where the raw responses are captured
+    ///
     /// Returns:
     ///   Result<Self>: The exchange instance or an error
-    pub async fn new() -> Result<Self> {
+    pub async fn with_url(
+        market: MarketConfig,
+        url: impl Into<String>,
+        ticker_url: impl Into<String>,
+        capture: &Capture,
+    ) -> Result<Self> {
+        Self::connect(market, url.into(), ticker_url.into(), capture).await
+    }
+
+    /// Creates the exchange instance once the API answers
+    async fn connect(
+        market: MarketConfig,
+        url: String,
+        ticker_url: String,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let recorder = FeedRecorder::for_market("Huobi", &market.symbol, capture);
+
         // Create a new client with custom configuration
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(5))
@@ -61,18 +158,12 @@ impl HuobiExchange {
 
         // Verify the exchange is accessible by making a test request
         let params = [
-            ("symbol", "btcusdt"),
+            ("symbol", market.exchange_symbol.as_str()),
             ("type", "step0"),
             ("depth", "5"), // Valid depth values: 5, 10, 20, 50, 100
         ];
 
-        let response: HuobiResponse = client
-            .get(&get_huobi_url())
-            .query(&params)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let response: HuobiResponse = recorder.get_json(client.get(&url).query(&params)).await?;
 
         if response.status != "ok" {
             return Err(PriceIndexError::ExchangeError(format!(
@@ -81,7 +172,14 @@ impl HuobiExchange {
             )));
         }
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            url,
+            ticker_url,
+            precision: market.precision(),
+            market,
+            recorder,
+        })
     }
 }
 
@@ -92,6 +190,11 @@ impl Exchange for HuobiExchange {
         "Huobi"
     }
 
+    /// Returns the canonical symbol of the tracked market
+    fn symbol(&self) -> &str {
+        &self.market.symbol
+    }
+
     /// Fetches the current order book from Huobi
     ///
     /// This function:
@@ -100,7 +203,7 @@ impl Exchange for HuobiExchange {
     /// 3. Converts the Huobi-specific format to our common OrderBook model
     ///
     /// Parameters:
-    ///   - symbol: Trading pair (btcusdt)
+    ///   - symbol: Huobi symbol of the tracked market (e.g. btcusdt)
     ///   - type: Depth type (step0 for highest precision)
     ///   - depth: Number of price levels (20)
     ///
@@ -109,19 +212,15 @@ impl Exchange for HuobiExchange {
     async fn fetch_order_book(&self) -> Result<OrderBook> {
         // Define the parameters for the request
         let params = [
-            ("symbol", "btcusdt"),
+            ("symbol", self.market.exchange_symbol.as_str()),
             ("type", "step0"),
             ("depth", "20"), // Valid depth values: 5, 10, 20, 50, 100
         ];
 
         // Send the request to Huobi
         let response: HuobiResponse = self
-            .client
-            .get(&get_huobi_url())
-            .query(&params)
-            .send()
-            .await?
-            .json()
+            .recorder
+            .get_json(self.client.get(&self.url).query(&params))
             .await?;
 
         // Check for errors
@@ -137,25 +236,20 @@ impl Exchange for HuobiExchange {
             PriceIndexError::ExchangeError("No order book data received from Huobi".to_string())
         })?;
 
-        // Create the order book
+        // Create the order book in the venue's tick and lot precision,
+        // stamped with the book's time or else the response's
         Ok(OrderBook {
-            bids: tick
-                .bids
-                .into_iter()
-                .map(|order| Order {
-                    price: order.price,
-                    quantity: order.quantity,
-                })
-                .collect(),
-            asks: tick
-                .asks
-                .into_iter()
-                .map(|order| Order {
-                    price: order.price,
-                    quantity: order.quantity,
-                })
-                .collect(),
+            bids: self.precision.orders(tick.bids),
+            asks: self.precision.orders(tick.asks),
             timestamp: SystemTime::now(),
+            exchange_timestamp: Some(timestamp_from_millis(tick.ts.unwrap_or(response.ts))),
         })
     }
+
+    /// Fetches the trailing 24h traded volume from the Huobi ticker
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        huobi_volume(&self.ticker_url, &self.market.exchange_symbol)
+            .await
+            .map(Some)
+    }
 }
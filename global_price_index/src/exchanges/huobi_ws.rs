@@ -0,0 +1,482 @@
+// WebSocket client, gzip frames and heartbeat handling
+use crate::capture::FeedRecorder;
+use crate::config::{get_settings, Capture, ExchangeConfig, HuobiConfig, MarketConfig};
+use crate::error::{PriceIndexError, Result};
+use crate::exchanges::huobi::HuobiOrderBook;
+use crate::exchanges::volume::huobi_volume;
+use crate::exchanges::{close_websocket, Exchange, FeedTask};
+use crate::metrics::METRICS;
+use crate::models::{timestamp_from_millis, Decimal, OrderBook, Precision, PriceLevelBook};
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, warn, Instrument};
+use url::Url;
+
+// Type aliases for WebSocket types
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsSink = futures::stream::SplitSink<WsStream, Message>;
+type WsStreamRead = futures::stream::SplitStream<WsStream>;
+
+/// How long to wait for the first book when connecting
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Messages sent by the Huobi market data feed
+///
+/// Huobi keeps the connection alive with its own JSON heartbeat rather than
+/// WebSocket ping frames: the server sends `{"ping": ts}` every few seconds
+/// and closes the connection if `{"pong": ts}` is not sent back.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum HuobiMessage {
+    /// Application-level heartbeat
+    Ping { ping: u64 },
+    /// Book pushed on a subscribed channel
+    Tick { ch: String, tick: HuobiOrderBook },
+    /// Response to a subscription request
+    Status {
+        status: String,
+        #[serde(rename = "err-msg")]
+        err_msg: Option<String>,
+    },
+}
+
+/// Decodes a Huobi frame into its JSON text
+///
+/// Huobi sends every market data frame as gzip-compressed binary; text
+/// frames are accepted as-is.
+fn decode_frame(message: Message) -> Result<Option<String>> {
+    match message {
+        Message::Binary(data) => {
+            let mut text = String::new();
+            GzDecoder::new(data.as_slice())
+                .read_to_string(&mut text)
+                .map_err(|e| {
+                    PriceIndexError::WebSocketError(format!(
+                        "Failed to decompress Huobi frame: {}",
+                        e
+                    ))
+                })?;
+            Ok(Some(text))
+        }
+        Message::Text(text) => Ok(Some(text)),
+        _ => Ok(None),
+    }
+}
+
+/// HuobiWsExchange implements the Exchange trait for Huobi over WebSocket
+///
+/// It subscribes to the `market.$symbol.depth.step0` channel, which pushes
+/// the top 150 levels of each side of the book, and keeps the latest book
+/// in memory.
+#[derive(Clone)]
+pub struct HuobiWsExchange {
+    order_book: Arc<RwLock<PriceLevelBook>>,
+    symbol: String,
+    exchange_symbol: String,
+    channel: String,
+    ws_url: String,
+    ticker_url: String,
+    connection: ExchangeConfig,
+    precision: Precision,
+    recorder: FeedRecorder,
+    connected: Arc<AtomicBool>,
+    task: Arc<FeedTask>,
+}
+
+impl HuobiWsExchange {
+    /// Creates a new HuobiWsExchange instance for a symbol of an explicit configuration
+    ///
+    /// Parameters:
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   config: Endpoints and markets of Huobi
+    ///   connection: Reconnection and ping settings of the WebSocket connection
+    ///   capture: Whether and where the raw frames are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance once the first book is received, or an error
+    pub async fn new(
+        symbol: &str,
+        config: &HuobiConfig,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let market = config.market(symbol).ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!("No Huobi market configured for {}", symbol))
+        })?;
+        Self::connect(
+            market,
+            config.ws_url.clone(),
+            config.ticker_url.clone(),
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates a new HuobiWsExchange instance for a symbol of the global configuration
+    pub async fn configured(symbol: &str) -> Result<Self> {
+        let settings = get_settings();
+        Self::new(
+            symbol,
+            &settings.exchange.huobi,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    }
+
+    /// Creates a new HuobiWsExchange instance against an explicit endpoint
+    ///
+    /// This allows pointing the exchange at a local stand-in of the Huobi
+    /// WebSocket API, such as a replayed capture.
+    ///
+    /// Args:
+    ///   market: Market whose symbol and precision the exchange uses
+    ///   ws_url: URL of the WebSocket API
+    ///   ticker_url: URL of the ticker endpoint the volume is read from
+    ///   connection: Reconnection and ping settings of the WebSocket connection
+    ///   capture: Whether and where the raw frames are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance once the first book is received, or an error
+    pub async fn with_url(
+        market: MarketConfig,
+        ws_url: impl Into<String>,
+        ticker_url: impl Into<String>,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        Self::connect(
+            market,
+            ws_url.into(),
+            ticker_url.into(),
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates the exchange instance and waits for its first book
+    async fn connect(
+        market: MarketConfig,
+        ws_url: String,
+        ticker_url: String,
+        connection: ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let exchange = Self {
+            order_book: Arc::new(RwLock::new(PriceLevelBook::new())),
+            recorder: FeedRecorder::for_market("Huobi", &market.symbol, capture),
+            channel: format!("market.{}.depth.step0", market.exchange_symbol),
+            precision: market.precision(),
+            symbol: market.symbol,
+            exchange_symbol: market.exchange_symbol,
+            ws_url,
+            ticker_url,
+            connection,
+            connected: Arc::new(AtomicBool::new(false)),
+            task: Arc::new(FeedTask::default()),
+        };
+
+        exchange.initialize().await?;
+        Ok(exchange)
+    }
+
+    /// Starts the WebSocket connection and waits for the first book
+    async fn initialize(&self) -> Result<()> {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        self.task.set(self.start_websocket(ready_tx));
+
+        match timeout(SNAPSHOT_TIMEOUT, ready_rx).await {
+            Ok(Ok(())) => Ok(()),
+            _ => {
+                self.task.stop();
+                Err(PriceIndexError::WebSocketError(format!(
+                    "No Huobi book received on {}",
+                    self.channel
+                )))
+            }
+        }
+    }
+
+    /// Establishes a WebSocket connection to Huobi
+    ///
+    /// Returns:
+    ///   Result<(WsSink, WsStreamRead)>: The WebSocket write and read streams
+    async fn connect_websocket(ws_url: &str) -> Result<(WsSink, WsStreamRead)> {
+        let url = Url::parse(ws_url).map_err(|e| {
+            PriceIndexError::WebSocketError(format!("Failed to parse WebSocket URL: {}", e))
+        })?;
+
+        let (ws_stream, _) = connect_async(url).await.map_err(|e| {
+            PriceIndexError::WebSocketError(format!("Failed to connect to WebSocket: {}", e))
+        })?;
+
+        Ok(ws_stream.split())
+    }
+
+    /// Sends a JSON message to Huobi
+    async fn send_json(write: &mut WsSink, message: serde_json::Value) -> Result<()> {
+        write
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|e| PriceIndexError::WebSocketError(format!("Failed to send to Huobi: {}", e)))
+    }
+
+    /// Handles WebSocket messages and updates the order book
+    ///
+    /// This function:
+    /// 1. Subscribes to the depth channel of the market
+    /// 2. Decompresses every frame
+    /// 3. Answers `{"ping": ts}` heartbeats with `{"pong": ts}`
+    /// 4. Replaces the in-memory book with every pushed book
+    /// 5. Gives up on the connection when nothing is received for two ping intervals
+    /// 6. Records every received frame to the feed capture
+    /// 7. Closes the connection with a close frame once `cancel` is cancelled
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_websocket_messages(
+        mut read: WsStreamRead,
+        mut write: WsSink,
+        order_book: Arc<RwLock<PriceLevelBook>>,
+        channel: &str,
+        ready: &mut Option<oneshot::Sender<()>>,
+        recorder: &FeedRecorder,
+        connection: &ExchangeConfig,
+        cancel: &CancellationToken,
+    ) {
+        let request = serde_json::json!({ "sub": channel, "id": channel });
+        if let Err(e) = Self::send_json(&mut write, request).await {
+            warn!(error = %e, "Failed to subscribe to the Huobi book");
+            return;
+        }
+
+        let ping_interval = connection.ping_interval_duration();
+        loop {
+            let next = tokio::select! {
+                _ = cancel.cancelled() => {
+                    close_websocket(&mut write, connection.close_timeout_duration()).await;
+                    break;
+                }
+                next = timeout(ping_interval * 2, read.next()) => next,
+            };
+            let message = match next {
+                Ok(Some(Ok(message))) => message,
+                Ok(Some(Err(e))) => {
+                    warn!(error = %e, "Huobi WebSocket error");
+                    break;
+                }
+                Ok(None) => {
+                    warn!("Huobi WebSocket connection closed");
+                    break;
+                }
+                Err(_) => {
+                    warn!("No message received from Huobi for too long, reconnecting");
+                    break;
+                }
+            };
+            recorder.record_frame(&message);
+
+            if let Message::Close(_) = message {
+                warn!("Huobi WebSocket connection closed");
+                break;
+            }
+
+            let text = match decode_frame(message) {
+                Ok(Some(text)) => text,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(error = %e, "Failed to decode Huobi frame");
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<HuobiMessage>(&text) {
+                Ok(HuobiMessage::Ping { ping }) => {
+                    let pong = serde_json::json!({ "pong": ping });
+                    if let Err(e) = Self::send_json(&mut write, pong).await {
+                        warn!(error = %e, "Failed to answer Huobi heartbeat");
+                        break;
+                    }
+                }
+                Ok(HuobiMessage::Tick { ch, tick }) if ch == channel => {
+                    {
+                        let mut book = order_book.write().await;
+                        book.replace(&tick.bids, &tick.asks);
+                        book.exchange_timestamp = tick.ts.map(timestamp_from_millis);
+                    }
+                    if let Some(ready) = ready.take() {
+                        let _ = ready.send(());
+                    }
+                }
+                Ok(HuobiMessage::Tick { .. }) => {}
+                Ok(HuobiMessage::Status { status, err_msg }) => {
+                    if status != "ok" {
+                        warn!(channel, error = ?err_msg, "Huobi subscription failed");
+                    }
+                }
+                Err(e) => warn!(error = %e, "Failed to parse Huobi message"),
+            }
+        }
+    }
+
+    /// Starts the WebSocket connection with automatic reconnection
+    ///
+    /// This function:
+    /// 1. Establishes a WebSocket connection to Huobi and subscribes to the book
+    /// 2. Signals `ready` once the first book is received
+    /// 3. Implements exponential backoff for reconnection attempts, using the
+    ///    shared reconnect settings of the exchange configuration
+    /// 4. Counts reconnections and connection errors in the metrics
+    /// 5. Stops reconnecting once the feed is stopped, see `FeedTask`
+    ///
+    /// Events are logged in a span carrying the exchange and symbol, and
+    /// the events of each connection in a nested span carrying its attempt
+    /// number.
+    ///
+    /// Returns:
+    ///   The handle of the connection task
+    fn start_websocket(&self, ready: oneshot::Sender<()>) -> JoinHandle<()> {
+        let order_book = self.order_book.clone();
+        let ws_url = self.ws_url.clone();
+        let channel = self.channel.clone();
+        let recorder = self.recorder.clone();
+        let connected = self.connected.clone();
+        let symbol = self.symbol.clone();
+        let span = info_span!("websocket", exchange = "Huobi", symbol = %symbol);
+        let mut ready = Some(ready);
+        let connection = self.connection.clone();
+        let mut attempt: u64 = 0;
+        let mut reconnect_delay = connection.initial_reconnect_duration();
+        let max_reconnect_delay = connection.max_reconnect_duration();
+        let cancel = self.task.token();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    attempt += 1;
+                    async {
+                        match cancel
+                            .run_until_cancelled(Self::connect_websocket(&ws_url))
+                            .await
+                        {
+                            Some(Ok((write, read))) => {
+                                // Reset the delay on successful connection
+                                reconnect_delay = connection.initial_reconnect_duration();
+                                info!("Connected to Huobi WebSocket");
+                                connected.store(true, Ordering::Relaxed);
+                                recorder.record_connect(&ws_url);
+                                Self::handle_websocket_messages(
+                                    read,
+                                    write,
+                                    order_book.clone(),
+                                    &channel,
+                                    &mut ready,
+                                    &recorder,
+                                    &connection,
+                                    &cancel,
+                                )
+                                .await;
+                                connected.store(false, Ordering::Relaxed);
+                            }
+                            Some(Err(e)) => {
+                                METRICS.record_error("Huobi", &symbol, &e);
+                                warn!(error = %e, "Failed to connect to Huobi WebSocket");
+                            }
+                            None => {}
+                        }
+                    }
+                    .instrument(info_span!("connection", attempt))
+                    .await;
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    METRICS.record_reconnect("Huobi", &symbol);
+
+                    warn!(
+                        delay_secs = reconnect_delay.as_secs(),
+                        next_attempt = attempt + 1,
+                        "Reconnecting to Huobi"
+                    );
+                    if cancel
+                        .run_until_cancelled(sleep(reconnect_delay))
+                        .await
+                        .is_none()
+                    {
+                        break;
+                    }
+
+                    // Double the delay with a cap at max_reconnect_delay
+                    reconnect_delay = std::cmp::min(reconnect_delay * 2, max_reconnect_delay);
+                }
+                info!("Huobi feed stopped");
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[async_trait]
+impl Exchange for HuobiWsExchange {
+    /// Returns the name of the exchange
+    fn name(&self) -> &'static str {
+        "Huobi"
+    }
+
+    /// Returns the canonical symbol of the tracked market
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns whether the WebSocket stream is connected
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Stops the WebSocket stream
+    fn stop(&self) {
+        self.task.stop();
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    /// Closes the WebSocket stream and waits for it to end
+    async fn shutdown(&self) {
+        self.task
+            .shutdown(self.connection.close_timeout_duration())
+            .await;
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    /// Fetches the current order book
+    ///
+    /// This implementation returns a copy of the in-memory order book
+    /// that's continuously updated via WebSocket, expressed in the venue's
+    /// tick and lot precision
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        let order_book = self.order_book.read().await.to_order_book();
+        Ok(OrderBook {
+            bids: self.precision.orders(order_book.bids),
+            asks: self.precision.orders(order_book.asks),
+            timestamp: order_book.timestamp,
+            exchange_timestamp: order_book.exchange_timestamp,
+        })
+    }
+
+    /// Fetches the trailing 24h traded volume from the Huobi ticker
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        huobi_volume(&self.ticker_url, &self.exchange_symbol)
+            .await
+            .map(Some)
+    }
+}
@@ -1,35 +1,65 @@
 // REST client, polling logic
 
-use crate::config::get_kraken_url;
+use crate::capture::FeedRecorder;
+use crate::config::{get_settings, Capture, KrakenConfig, MarketConfig};
 use crate::error::{PriceIndexError, Result};
+use crate::exchanges::volume::kraken_volume;
 use crate::exchanges::Exchange;
-use crate::models::{Order, OrderBook};
+use crate::models::{Decimal, Order, OrderBook, Precision};
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Kraken-specific implementation of the order book
 /// Contains bids and asks in the format returned by Kraken API
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize)]
 struct KrakenOrderBook {
     #[serde(deserialize_with = "deserialize_kraken_orders")]
-    bids: Vec<Order>,
+    bids: KrakenLevels,
     #[serde(deserialize_with = "deserialize_kraken_orders")]
-    asks: Vec<Order>,
+    asks: KrakenLevels,
+}
+
+/// One side of a Kraken order book
+#[derive(Debug)]
+struct KrakenLevels {
+    orders: Vec<Order>,
+    /// Latest update time of the levels
+    updated_at: Option<SystemTime>,
+}
+
+/// Converts a Kraken timestamp, in seconds with a fractional part sent as a
+/// number or a string, to SystemTime
+pub(crate) fn kraken_timestamp(value: &serde_json::Value) -> Option<SystemTime> {
+    let seconds = match value {
+        serde_json::Value::Number(number) => number.as_f64()?,
+        serde_json::Value::String(text) => text.parse().ok()?,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .map(|since_epoch| UNIX_EPOCH + since_epoch)
 }
 
 /// Custom deserializer for Kraken order data format
 ///
 /// Kraken returns orders as [price: String, volume: String, timestamp: Integer (Unix time)]
-/// This function converts them to our Order struct with f64 values for price and quantity
-fn deserialize_kraken_orders<'de, D>(deserializer: D) -> std::result::Result<Vec<Order>, D::Error>
+/// This function parses them exactly into our Order struct with Decimal values for price and quantity,
+/// and keeps the latest level timestamp as the update time of the side
+fn deserialize_kraken_orders<'de, D>(deserializer: D) -> std::result::Result<KrakenLevels, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     use serde::de::Error;
     let raw: Vec<[serde_json::Value; 3]> = Vec::deserialize(deserializer)?;
+    let updated_at = raw
+        .iter()
+        .filter_map(|[_, _, timestamp]| kraken_timestamp(timestamp))
+        .max();
 
-    raw.into_iter()
+    let orders = raw
+        .into_iter()
         .map(|[price, volume, _timestamp]| {
             let price_str = price
                 .as_str()
@@ -39,31 +69,29 @@ where
                 .ok_or_else(|| D::Error::custom("volume must be a string"))?;
 
             let price = price_str
-                .parse::<f64>()
-                .map_err(|_| D::Error::custom("Failed to parse price as f64"))?;
+                .parse::<Decimal>()
+                .map_err(|_| D::Error::custom("Failed to parse price as decimal"))?;
             let quantity = volume_str
-                .parse::<f64>()
-                .map_err(|_| D::Error::custom("Failed to parse volume as f64"))?;
+                .parse::<Decimal>()
+                .map_err(|_| D::Error::custom("Failed to parse volume as decimal"))?;
 
             Ok(Order { price, quantity })
         })
-        .collect()
-}
-
-/// Represents the result field from Kraken API response
-/// Contains the order book data for XBTUSDT trading pair
-#[derive(Debug, Serialize, Deserialize)]
-pub struct KrakenResult {
-    #[serde(rename = "XBTUSDT")]
-    xbtusdt: KrakenOrderBook,
+        .collect::<std::result::Result<_, D::Error>>()?;
+    Ok(KrakenLevels { orders, updated_at })
 }
 
 /// The full response from Kraken API
 /// Contains an error field and the result data
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// The result is keyed by Kraken's internal pair name, which may differ from
+/// the requested one (e.g. "XBTEUR" is answered as "XXBTZEUR"), so it is read
+/// as a map holding the single requested book.
+#[derive(Debug, Deserialize)]
 struct KrakenResponse {
     error: Vec<String>,
-    result: KrakenResult,
+    #[serde(default)]
+    result: HashMap<String, KrakenOrderBook>,
 }
 
 /// KrakenExchange implements the Exchange trait for Kraken
@@ -72,19 +100,82 @@ struct KrakenResponse {
 /// making periodic HTTP requests to fetch the current order book.
 pub struct KrakenExchange {
     client: reqwest::Client,
+    url: String,
+    ticker_url: String,
+    market: MarketConfig,
+    precision: Precision,
+    recorder: FeedRecorder,
 }
 
 impl KrakenExchange {
-    /// Creates a new KrakenExchange instance
+    /// Creates a new KrakenExchange instance for a symbol of an explicit configuration
+    ///
+    /// This function:
+    /// 1. Looks up the Kraken market mapped to the symbol
+    /// 2. Creates the exchange against the API URL of the configuration, see `with_url`
+    ///
+    /// Parameters:
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   config: Endpoints and markets of Kraken
+    ///   capture: Whether and where the raw responses are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance or an error
+    pub async fn new(symbol: &str, config: &KrakenConfig, capture: &Capture) -> Result<Self> {
+        let market = config.market(symbol).ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!("No Kraken market configured for {}", symbol))
+        })?;
+        Self::connect(
+            market,
+            config.url.clone(),
+            config.ticker_url.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates a new KrakenExchange instance for a symbol of the global configuration
+    pub async fn configured(symbol: &str) -> Result<Self> {
+        let settings = get_settings();
+        Self::new(symbol, &settings.exchange.kraken, &settings.capture).await
+    }
+
+    /// Creates a new KrakenExchange instance against an explicit endpoint
+    ///
+    /// This allows pointing the exchange at a local stand-in of the Kraken
+    /// REST API, such as a replayed capture.
     ///
     /// This function:
     /// 1. Creates an HTTP client with a 5-second timeout
     /// 2. Verifies the exchange is accessible by making a test API request
     /// 3. Returns the exchange instance if successful
     ///
+    /// Args:
+    ///   market: Market whose symbol and precision the exchange uses
+    ///   url: URL of the order book endpoint
+    ///   ticker_url: URL of the ticker endpoint the volume is read from
+    ///   capture: Whether and where the raw responses are captured
+    ///
     /// Returns:
     ///   Result<Self>: The exchange instance or an error
-    pub async fn new() -> Result<Self> {
+    pub async fn with_url(
+        market: MarketConfig,
+        url: impl Into<String>,
+        ticker_url: impl Into<String>,
+        capture: &Capture,
+    ) -> Result<Self> {
+        Self::connect(market, url.into(), ticker_url.into(), capture).await
+    }
+
+    /// Creates the exchange instance once the API answers
+    async fn connect(
+        market: MarketConfig,
+        url: String,
+        ticker_url: String,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let recorder = FeedRecorder::for_market("Kraken", &market.symbol, capture);
+
         // Create a new client with custom configuration
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(5))
@@ -94,14 +185,8 @@ impl KrakenExchange {
             })?;
 
         // Verify the exchange is accessible by making a test request
-        let params = [("pair", "XBTUSDT"), ("count", "1")];
-        let response: KrakenResponse = client
-            .get(&get_kraken_url())
-            .query(&params)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let params = [("pair", market.exchange_symbol.as_str()), ("count", "1")];
+        let response: KrakenResponse = recorder.get_json(client.get(&url).query(&params)).await?;
 
         if !response.error.is_empty() {
             return Err(PriceIndexError::ExchangeError(format!(
@@ -110,7 +195,14 @@ impl KrakenExchange {
             )));
         }
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            url,
+            ticker_url,
+            precision: market.precision(),
+            market,
+            recorder,
+        })
     }
 }
 
@@ -121,24 +213,30 @@ impl Exchange for KrakenExchange {
         "Kraken"
     }
 
+    /// Returns the canonical symbol of the tracked market
+    fn symbol(&self) -> &str {
+        &self.market.symbol
+    }
+
     /// Fetches the current order book from Kraken
     ///
     /// This function:
     /// 1. Makes an HTTP GET request to the Kraken API
     /// 2. Parses the JSON response into KrakenResponse
-    /// 3. Converts the Kraken-specific format to our common OrderBook model
+    /// 3. Converts the Kraken-specific format to our common OrderBook model,
+    ///    expressed in the venue's tick and lot precision and stamped with
+    ///    the latest level timestamp
     ///
     /// Returns:
     ///   Result<OrderBook>: The order book on success, or an error on failure
     async fn fetch_order_book(&self) -> Result<OrderBook> {
-        let params = [("pair", "XBTUSDT"), ("count", "100")];
+        let params = [
+            ("pair", self.market.exchange_symbol.as_str()),
+            ("count", "100"),
+        ];
         let response: KrakenResponse = self
-            .client
-            .get(&get_kraken_url())
-            .query(&params)
-            .send()
-            .await?
-            .json()
+            .recorder
+            .get_json(self.client.get(&self.url).query(&params))
             .await?;
 
         if !response.error.is_empty() {
@@ -148,11 +246,24 @@ impl Exchange for KrakenExchange {
             )));
         }
 
-        let order_book = response.result.xbtusdt;
+        let order_book = response.result.into_values().next().ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!(
+                "No order book data received from Kraken for {}",
+                self.market.exchange_symbol
+            ))
+        })?;
         Ok(OrderBook {
-            bids: order_book.bids,
-            asks: order_book.asks,
+            exchange_timestamp: order_book.bids.updated_at.max(order_book.asks.updated_at),
+            bids: self.precision.orders(order_book.bids.orders),
+            asks: self.precision.orders(order_book.asks.orders),
             timestamp: SystemTime::now(),
         })
     }
+
+    /// Fetches the trailing 24h traded volume from the Kraken ticker
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        kraken_volume(&self.ticker_url, &self.market.exchange_symbol)
+            .await
+            .map(Some)
+    }
 }
@@ -0,0 +1,752 @@
+// WebSocket client, checksummed order book sync
+use crate::capture::FeedRecorder;
+use crate::config::{get_settings, Capture, ExchangeConfig, KrakenConfig, MarketConfig};
+use crate::error::{PriceIndexError, Result};
+use crate::exchanges::kraken::kraken_timestamp;
+use crate::exchanges::volume::kraken_volume;
+use crate::exchanges::{close_websocket, Exchange, FeedTask};
+use crate::metrics::METRICS;
+use crate::models::{Decimal, Order, OrderBook, Precision, PriceLevelBook};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, warn, Instrument};
+use url::Url;
+
+// Type aliases for WebSocket types
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type WsSink = futures::stream::SplitSink<WsStream, Message>;
+type WsStreamRead = futures::stream::SplitStream<WsStream>;
+
+/// Number of levels per side covered by Kraken's book checksum
+const CHECKSUM_DEPTH: usize = 10;
+
+/// How long to wait for the first book snapshot when connecting
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event messages sent by Kraken outside of the book channel
+///
+/// Covers heartbeats, system status and subscription status messages.
+#[derive(Debug, Deserialize)]
+struct KrakenEvent {
+    event: String,
+    status: Option<String>,
+    #[serde(rename = "errorMessage")]
+    error_message: Option<String>,
+}
+
+/// A message of the `book` channel
+///
+/// `updated_at` is the latest timestamp of the levels of the message.
+#[derive(Debug)]
+enum BookMessage {
+    /// Full book of the subscribed depth
+    Snapshot {
+        bids: Vec<Order>,
+        asks: Vec<Order>,
+        updated_at: Option<SystemTime>,
+    },
+    /// Level changes and the checksum of the resulting book
+    Update {
+        bids: Vec<Order>,
+        asks: Vec<Order>,
+        checksum: Option<u32>,
+        updated_at: Option<SystemTime>,
+    },
+}
+
+/// Parses a `book` channel message
+///
+/// Book messages are arrays of the form `[channelID, payload, ..., channelName, pair]`.
+/// Each payload object holds snapshot levels (`as`, `bs`), updated levels
+/// (`a`, `b`) and the checksum of the book after the update (`c`). Levels are
+/// `[price, volume, timestamp]` strings, updates may carry a fourth
+/// republish flag.
+///
+/// Returns:
+///   Result<Option<BookMessage>>: The book message, or None for event messages
+fn parse_book_message(text: &str) -> Result<Option<BookMessage>> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let Some(items) = value.as_array() else {
+        return Ok(None);
+    };
+    if items.len() < 4 {
+        return Err(PriceIndexError::InvalidPriceData(format!(
+            "Malformed Kraken book message: {}",
+            text
+        )));
+    }
+
+    let mut snapshot = false;
+    let mut bids = Vec::new();
+    let mut asks = Vec::new();
+    let mut checksum = None;
+    let mut updated_at = None;
+    for payload in items[1..items.len() - 2]
+        .iter()
+        .filter_map(|p| p.as_object())
+    {
+        for side in ["as", "bs", "a", "b"] {
+            if let Some(levels) = payload.get(side) {
+                updated_at = updated_at.max(latest_level_time(levels));
+            }
+        }
+        if let Some(levels) = payload.get("as") {
+            snapshot = true;
+            asks.extend(parse_levels(levels)?);
+        }
+        if let Some(levels) = payload.get("bs") {
+            snapshot = true;
+            bids.extend(parse_levels(levels)?);
+        }
+        if let Some(levels) = payload.get("a") {
+            asks.extend(parse_levels(levels)?);
+        }
+        if let Some(levels) = payload.get("b") {
+            bids.extend(parse_levels(levels)?);
+        }
+        if let Some(value) = payload.get("c") {
+            let parsed = value.as_str().and_then(|c| c.parse::<u32>().ok());
+            checksum = Some(parsed.ok_or_else(|| {
+                PriceIndexError::InvalidPriceData(format!("Invalid Kraken checksum: {}", value))
+            })?);
+        }
+    }
+
+    Ok(Some(if snapshot {
+        BookMessage::Snapshot {
+            bids,
+            asks,
+            updated_at,
+        }
+    } else {
+        BookMessage::Update {
+            bids,
+            asks,
+            checksum,
+            updated_at,
+        }
+    }))
+}
+
+/// Returns the latest timestamp of Kraken levels
+fn latest_level_time(levels: &serde_json::Value) -> Option<SystemTime> {
+    levels
+        .as_array()?
+        .iter()
+        .filter_map(|level| level.get(2).and_then(kraken_timestamp))
+        .max()
+}
+
+/// Parses Kraken levels exactly, keeping the decimal places sent by Kraken
+///
+/// The decimal places must be kept as sent because they are part of the
+/// checksummed text.
+fn parse_levels(levels: &serde_json::Value) -> Result<Vec<Order>> {
+    let invalid =
+        || PriceIndexError::InvalidPriceData(format!("Invalid Kraken levels: {}", levels));
+    levels
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|level| {
+            let field = |index: usize| {
+                level
+                    .get(index)
+                    .and_then(|value| value.as_str())
+                    .and_then(|value| value.parse::<Decimal>().ok())
+                    .ok_or_else(invalid)
+            };
+            Ok(Order {
+                price: field(0)?,
+                quantity: field(1)?,
+            })
+        })
+        .collect()
+}
+
+/// Computes Kraken's CRC32 checksum of a book
+///
+/// The checksum covers the top 10 asks from lowest price followed by the
+/// top 10 bids from highest price. Each level contributes its price and then
+/// its volume, written as sent by Kraken with the decimal point removed and
+/// leading zeros stripped.
+pub fn book_checksum(book: &PriceLevelBook) -> u32 {
+    let mut payload = String::new();
+    for level in book
+        .asks()
+        .take(CHECKSUM_DEPTH)
+        .chain(book.bids().take(CHECKSUM_DEPTH))
+    {
+        payload.push_str(checksum_digits(level.price).as_str());
+        payload.push_str(checksum_digits(level.quantity).as_str());
+    }
+    crc32fast::hash(payload.as_bytes())
+}
+
+/// Formats a value as it appears in the checksummed text
+fn checksum_digits(value: Decimal) -> String {
+    value
+        .to_string()
+        .replace('.', "")
+        .trim_start_matches('0')
+        .to_string()
+}
+
+/// Local copy of a Kraken book subscription
+///
+/// The book is only updated once a snapshot has been received, and every
+/// update is validated against the checksum Kraken sends with it.
+struct BookSync {
+    /// Subscribed depth, levels pushed beyond it are dropped
+    depth: usize,
+    /// False until a snapshot is applied, and again after a checksum mismatch;
+    /// shared with the exchange serving the book
+    synced: Arc<AtomicBool>,
+}
+
+impl BookSync {
+    /// Creates the sync state of a subscription awaiting its snapshot
+    fn new(depth: usize, synced: Arc<AtomicBool>) -> Self {
+        synced.store(false, Ordering::Release);
+        Self { depth, synced }
+    }
+
+    /// Returns whether the book matches Kraken's
+    fn is_synced(&self) -> bool {
+        self.synced.load(Ordering::Acquire)
+    }
+
+    /// Applies a book message to the local book
+    ///
+    /// Returns false if the book no longer matches Kraken's and the
+    /// subscription must be renewed to receive a fresh snapshot.
+    async fn on_message(
+        &mut self,
+        message: BookMessage,
+        order_book: &RwLock<PriceLevelBook>,
+    ) -> bool {
+        match message {
+            BookMessage::Snapshot {
+                bids,
+                asks,
+                updated_at,
+            } => {
+                let mut order_book = order_book.write().await;
+                order_book.replace(&bids, &asks);
+                order_book.exchange_timestamp = updated_at;
+                self.synced.store(true, Ordering::Release);
+                true
+            }
+            BookMessage::Update {
+                bids,
+                asks,
+                checksum,
+                updated_at,
+            } => {
+                // Updates received before the snapshot of a renewed subscription
+                if !self.is_synced() {
+                    return true;
+                }
+
+                let mut order_book = order_book.write().await;
+                order_book.apply_updates(&bids, &asks);
+                order_book.truncate(self.depth);
+                if updated_at.is_some() {
+                    order_book.exchange_timestamp = updated_at;
+                }
+
+                if let Some(expected) = checksum {
+                    let actual = book_checksum(&order_book);
+                    if actual != expected {
+                        warn!(
+                            expected,
+                            actual, "Kraken book checksum mismatch, resubscribing"
+                        );
+                        // Withheld until the snapshot of the renewed subscription, see `fetch_order_book`
+                        self.synced.store(false, Ordering::Release);
+                        return false;
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+/// KrakenWsExchange implements the Exchange trait for Kraken over WebSocket
+///
+/// It subscribes to the public `book` channel of one pair and maintains an
+/// in-memory book that is validated against Kraken's CRC32 checksum after
+/// every update. A mismatch renews the subscription to get a fresh snapshot.
+#[derive(Clone)]
+pub struct KrakenWsExchange {
+    order_book: Arc<RwLock<PriceLevelBook>>,
+    symbol: String,
+    exchange_symbol: String,
+    pair: String,
+    ws_url: String,
+    ticker_url: String,
+    depth: usize,
+    connection: ExchangeConfig,
+    precision: Precision,
+    recorder: FeedRecorder,
+    connected: Arc<AtomicBool>,
+    synced: Arc<AtomicBool>,
+    task: Arc<FeedTask>,
+}
+
+impl KrakenWsExchange {
+    /// Creates a new KrakenWsExchange instance for a symbol of an explicit configuration
+    ///
+    /// Parameters:
+    ///   symbol: Canonical symbol such as "BTC/USDT"
+    ///   config: Endpoints, book depth and markets of Kraken
+    ///   connection: Reconnection and ping settings of the WebSocket connection
+    ///   capture: Whether and where the raw frames are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance once the first snapshot is received, or an error
+    pub async fn new(
+        symbol: &str,
+        config: &KrakenConfig,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let market = config.market(symbol).ok_or_else(|| {
+            PriceIndexError::ExchangeError(format!("No Kraken market configured for {}", symbol))
+        })?;
+        Self::connect(
+            market,
+            config.ws_url.clone(),
+            config.ticker_url.clone(),
+            config.book_depth,
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates a new KrakenWsExchange instance for a symbol of the global configuration
+    pub async fn configured(symbol: &str) -> Result<Self> {
+        let settings = get_settings();
+        Self::new(
+            symbol,
+            &settings.exchange.kraken,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    }
+
+    /// Creates a new KrakenWsExchange instance against an explicit endpoint
+    ///
+    /// This allows pointing the exchange at a local stand-in of the Kraken
+    /// WebSocket API, such as a replayed capture.
+    ///
+    /// Args:
+    ///   market: Market whose pair and precision the exchange uses
+    ///   ws_url: URL of the WebSocket API
+    ///   ticker_url: URL of the ticker endpoint the volume is read from
+    ///   depth: Subscribed book depth
+    ///   connection: Reconnection and ping settings of the WebSocket connection
+    ///   capture: Whether and where the raw frames are captured
+    ///
+    /// Returns:
+    ///   Result<Self>: The exchange instance once the first snapshot is received, or an error
+    pub async fn with_url(
+        market: MarketConfig,
+        ws_url: impl Into<String>,
+        ticker_url: impl Into<String>,
+        depth: usize,
+        connection: &ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        Self::connect(
+            market,
+            ws_url.into(),
+            ticker_url.into(),
+            depth,
+            connection.clone(),
+            capture,
+        )
+        .await
+    }
+
+    /// Creates the exchange instance and waits for its first snapshot
+    async fn connect(
+        market: MarketConfig,
+        ws_url: String,
+        ticker_url: String,
+        depth: usize,
+        connection: ExchangeConfig,
+        capture: &Capture,
+    ) -> Result<Self> {
+        let exchange = Self {
+            order_book: Arc::new(RwLock::new(PriceLevelBook::new())),
+            recorder: FeedRecorder::for_market("Kraken", &market.symbol, capture),
+            pair: market
+                .ws_symbol
+                .clone()
+                .unwrap_or_else(|| market.exchange_symbol.clone()),
+            precision: market.precision(),
+            symbol: market.symbol,
+            exchange_symbol: market.exchange_symbol,
+            ws_url,
+            ticker_url,
+            depth,
+            connection,
+            connected: Arc::new(AtomicBool::new(false)),
+            synced: Arc::new(AtomicBool::new(false)),
+            task: Arc::new(FeedTask::default()),
+        };
+
+        exchange.initialize().await?;
+        Ok(exchange)
+    }
+
+    /// Starts the WebSocket connection and waits for the first book snapshot
+    async fn initialize(&self) -> Result<()> {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        self.task.set(self.start_websocket(ready_tx));
+
+        match timeout(SNAPSHOT_TIMEOUT, ready_rx).await {
+            Ok(Ok(())) => Ok(()),
+            _ => {
+                self.task.stop();
+                Err(PriceIndexError::WebSocketError(format!(
+                    "No Kraken book snapshot received for {}",
+                    self.pair
+                )))
+            }
+        }
+    }
+
+    /// Establishes a WebSocket connection to Kraken
+    ///
+    /// Returns:
+    ///   Result<(WsSink, WsStreamRead)>: The WebSocket write and read streams
+    async fn connect_websocket(ws_url: &str) -> Result<(WsSink, WsStreamRead)> {
+        let url = Url::parse(ws_url).map_err(|e| {
+            PriceIndexError::WebSocketError(format!("Failed to parse WebSocket URL: {}", e))
+        })?;
+
+        let (ws_stream, _) = connect_async(url).await.map_err(|e| {
+            PriceIndexError::WebSocketError(format!("Failed to connect to WebSocket: {}", e))
+        })?;
+
+        Ok(ws_stream.split())
+    }
+
+    /// Sends a `subscribe` or `unsubscribe` request for the book of a pair
+    async fn send_subscription(
+        write: &mut WsSink,
+        event: &str,
+        pair: &str,
+        depth: usize,
+    ) -> Result<()> {
+        let request = serde_json::json!({
+            "event": event,
+            "pair": [pair],
+            "subscription": { "name": "book", "depth": depth },
+        });
+        write
+            .send(Message::Text(request.to_string()))
+            .await
+            .map_err(|e| {
+                PriceIndexError::WebSocketError(format!("Failed to send {}: {}", event, e))
+            })
+    }
+
+    /// Sends a message, retrying up to the ping retry count of the connection
+    ///
+    /// Returns false if every attempt failed.
+    async fn send_with_retry(write: &mut WsSink, message: Message, max_retries: u32) -> bool {
+        for attempt in 1..=max_retries {
+            match write.send(message.clone()).await {
+                Ok(_) => return true,
+                Err(e) => {
+                    warn!(attempt, max_retries, error = %e, "Failed to send Kraken control frame");
+                    sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+        false
+    }
+
+    /// Handles WebSocket messages and updates the order book
+    ///
+    /// This function:
+    /// 1. Subscribes to the book channel of the pair
+    /// 2. Applies snapshots and updates through the checksum checks of BookSync
+    /// 3. Renews the subscription whenever a checksum does not match
+    /// 4. Maintains the WebSocket connection with ping/pong messages
+    /// 5. Handles connection errors and closures
+    /// 6. Records every received frame to the feed capture
+    /// 7. Closes the connection with a close frame once `cancel` is cancelled
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_websocket_messages(
+        mut read: WsStreamRead,
+        mut write: WsSink,
+        order_book: Arc<RwLock<PriceLevelBook>>,
+        synced: Arc<AtomicBool>,
+        pair: &str,
+        depth: usize,
+        ready: &mut Option<oneshot::Sender<()>>,
+        recorder: &FeedRecorder,
+        connection: &ExchangeConfig,
+        cancel: &CancellationToken,
+    ) {
+        let mut sync = BookSync::new(depth, synced);
+        if let Err(e) = Self::send_subscription(&mut write, "subscribe", pair, depth).await {
+            warn!(error = %e, "Failed to subscribe to the Kraken book");
+            return;
+        }
+
+        let mut last_pong = SystemTime::now();
+        let mut ping_interval = tokio::time::interval(connection.ping_interval_duration());
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    close_websocket(&mut write, connection.close_timeout_duration()).await;
+                    break;
+                }
+                Some(message) = read.next() => {
+                    if let Ok(message) = &message {
+                        recorder.record_frame(message);
+                    }
+                    match message {
+                        Ok(Message::Text(text)) => match parse_book_message(&text) {
+                            Ok(Some(book_message)) => {
+                                if !sync.on_message(book_message, &order_book).await {
+                                    let renewed = Self::send_subscription(&mut write, "unsubscribe", pair, depth).await
+                                        .and(Self::send_subscription(&mut write, "subscribe", pair, depth).await);
+                                    if let Err(e) = renewed {
+                                        warn!(error = %e, "Failed to renew the Kraken subscription");
+                                        break;
+                                    }
+                                } else if sync.is_synced() {
+                                    if let Some(ready) = ready.take() {
+                                        let _ = ready.send(());
+                                    }
+                                }
+                            }
+                            Ok(None) => {
+                                if let Ok(event) = serde_json::from_str::<KrakenEvent>(&text) {
+                                    if event.status.as_deref() == Some("error") {
+                                        warn!(
+                                            event = %event.event,
+                                            pair,
+                                            error = ?event.error_message,
+                                            "Kraken subscription error"
+                                        );
+                                    }
+                                }
+                            }
+                            Err(e) => warn!(error = %e, "Failed to parse Kraken message"),
+                        },
+                        Ok(Message::Close(_)) => {
+                            warn!("Kraken WebSocket connection closed");
+                            break;
+                        }
+                        Ok(Message::Ping(payload)) => {
+                            // Respond to ping with pong, reconnecting if it cannot be sent
+                            let sent = Self::send_with_retry(&mut write, Message::Pong(payload), connection.ping_retry_count).await;
+                            if !sent {
+                                break;
+                            }
+                        }
+                        Ok(Message::Pong(_)) => {
+                            last_pong = SystemTime::now();
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Kraken WebSocket error");
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                _ = ping_interval.tick() => {
+                    // Check if we haven't received a pong for too long
+                    if last_pong.elapsed().unwrap_or(Duration::from_secs(0)) > connection.ping_interval_duration() * 2 {
+                        warn!("No pong received from Kraken for too long, reconnecting");
+                        break;
+                    }
+                    if !Self::send_with_retry(&mut write, Message::Ping(vec![]), connection.ping_retry_count).await {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts the WebSocket connection with automatic reconnection
+    ///
+    /// This function:
+    /// 1. Establishes a WebSocket connection to Kraken and subscribes to the book
+    /// 2. Signals `ready` once the first snapshot is applied
+    /// 3. Implements exponential backoff for reconnection attempts, using the
+    ///    shared reconnect settings of the exchange configuration
+    /// 4. Counts reconnections and connection errors in the metrics
+    /// 5. Stops reconnecting once the feed is stopped, see `FeedTask`
+    ///
+    /// Events are logged in a span carrying the exchange and symbol, and
+    /// the events of each connection in a nested span carrying its attempt
+    /// number.
+    ///
+    /// Returns:
+    ///   The handle of the connection task
+    fn start_websocket(&self, ready: oneshot::Sender<()>) -> JoinHandle<()> {
+        let order_book = self.order_book.clone();
+        let ws_url = self.ws_url.clone();
+        let pair = self.pair.clone();
+        let depth = self.depth;
+        let recorder = self.recorder.clone();
+        let connected = self.connected.clone();
+        let synced = self.synced.clone();
+        let symbol = self.symbol.clone();
+        let span = info_span!("websocket", exchange = "Kraken", symbol = %symbol);
+        let mut ready = Some(ready);
+        let connection = self.connection.clone();
+        let mut attempt: u64 = 0;
+        let mut reconnect_delay = connection.initial_reconnect_duration();
+        let max_reconnect_delay = connection.max_reconnect_duration();
+        let cancel = self.task.token();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    attempt += 1;
+                    async {
+                        match cancel
+                            .run_until_cancelled(Self::connect_websocket(&ws_url))
+                            .await
+                        {
+                            Some(Ok((write, read))) => {
+                                // Reset the delay on successful connection
+                                reconnect_delay = connection.initial_reconnect_duration();
+                                info!("Connected to Kraken WebSocket");
+                                connected.store(true, Ordering::Relaxed);
+                                recorder.record_connect(&ws_url);
+                                Self::handle_websocket_messages(
+                                    read,
+                                    write,
+                                    order_book.clone(),
+                                    synced.clone(),
+                                    &pair,
+                                    depth,
+                                    &mut ready,
+                                    &recorder,
+                                    &connection,
+                                    &cancel,
+                                )
+                                .await;
+                                connected.store(false, Ordering::Relaxed);
+                            }
+                            Some(Err(e)) => {
+                                METRICS.record_error("Kraken", &symbol, &e);
+                                warn!(error = %e, "Failed to connect to Kraken WebSocket");
+                            }
+                            None => {}
+                        }
+                    }
+                    .instrument(info_span!("connection", attempt))
+                    .await;
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    METRICS.record_reconnect("Kraken", &symbol);
+
+                    warn!(
+                        delay_secs = reconnect_delay.as_secs(),
+                        next_attempt = attempt + 1,
+                        "Reconnecting to Kraken"
+                    );
+                    if cancel
+                        .run_until_cancelled(sleep(reconnect_delay))
+                        .await
+                        .is_none()
+                    {
+                        break;
+                    }
+
+                    // Double the delay with a cap at max_reconnect_delay
+                    reconnect_delay = std::cmp::min(reconnect_delay * 2, max_reconnect_delay);
+                }
+                info!("Kraken feed stopped");
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[async_trait]
+impl Exchange for KrakenWsExchange {
+    /// Returns the name of the exchange
+    fn name(&self) -> &'static str {
+        "Kraken"
+    }
+
+    /// Returns the canonical symbol of the tracked market
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns whether the WebSocket stream is connected
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Stops the WebSocket stream
+    fn stop(&self) {
+        self.task.stop();
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    /// Closes the WebSocket stream and waits for it to end
+    async fn shutdown(&self) {
+        self.task
+            .shutdown(self.connection.close_timeout_duration())
+            .await;
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    /// Fetches the current order book
+    ///
+    /// This implementation returns a copy of the in-memory order book
+    /// that's continuously updated via WebSocket, expressed in the venue's
+    /// tick and lot precision, or an error while the book is out of sync and
+    /// awaits the snapshot of a renewed subscription
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        let order_book = self.order_book.read().await;
+        if !self.synced.load(Ordering::Acquire) {
+            return Err(PriceIndexError::ExchangeError(
+                "Kraken order book is resynchronizing".to_string(),
+            ));
+        }
+        let order_book = order_book.to_order_book();
+        Ok(OrderBook {
+            bids: self.precision.orders(order_book.bids),
+            asks: self.precision.orders(order_book.asks),
+            timestamp: order_book.timestamp,
+            exchange_timestamp: order_book.exchange_timestamp,
+        })
+    }
+
+    /// Fetches the trailing 24h traded volume from the Kraken ticker
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        kraken_volume(&self.ticker_url, &self.exchange_symbol)
+            .await
+            .map(Some)
+    }
+}
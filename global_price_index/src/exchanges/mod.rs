@@ -1,12 +1,32 @@
 // Exchange trait, factory
+use crate::config::{
+    get_binance_market, get_depth_bps, get_huobi_market, get_kraken_market,
+    get_mid_price_calculator, get_settings, is_binance_enabled, is_huobi_enabled,
+    is_kraken_enabled, BinanceConfig, Capture, ExchangeConfig, HuobiConfig, KrakenConfig,
+    MarketConfig, MidPriceCalculator, Settings,
+};
 use crate::error::{PriceIndexError, Result};
-use crate::models::{ExchangePrice, OrderBook};
+use crate::metrics::METRICS;
+use crate::models::{Decimal, ExchangePrice, OrderBook};
 use async_trait::async_trait;
-use std::time::SystemTime;
+use futures::{Sink, SinkExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 pub mod binance;
 pub mod huobi;
+pub mod huobi_ws;
 pub mod kraken;
+pub mod kraken_ws;
+pub mod registry;
+pub mod volume;
 
 /// The Exchange trait defines the interface for cryptocurrency exchanges.
 ///
@@ -18,6 +38,35 @@ pub trait Exchange: Send + Sync {
     /// Returns the name of the exchange as a static string
     fn name(&self) -> &'static str;
 
+    /// Returns the canonical symbol (e.g. "BTC/USDT") of the market this instance tracks
+    fn symbol(&self) -> &str;
+
+    /// Returns whether the exchange feed is currently connected
+    ///
+    /// Streaming exchanges report whether their WebSocket is up, so a book
+    /// that stopped updating can be told apart from a quiet market. The
+    /// default implementation, used by the REST exchanges, which connect on
+    /// every request, reports a connected feed.
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    /// Stops the exchange's background feed, if any
+    ///
+    /// Called when the exchange is taken out of the registry, e.g. when a
+    /// reloaded configuration changes its market. The default implementation,
+    /// used by the REST exchanges, has nothing to stop.
+    fn stop(&self) {}
+
+    /// Stops the exchange's background feed and waits for it to end
+    ///
+    /// Called when the service shuts down, so the streaming exchanges close
+    /// their WebSocket connections cleanly before the process exits. The
+    /// default implementation stops the feed, see `stop`.
+    async fn shutdown(&self) {
+        self.stop();
+    }
+
     /// Fetches the current order book from the exchange
     ///
     /// This method must be implemented by each exchange to handle the
@@ -27,31 +76,365 @@ pub trait Exchange: Send + Sync {
     ///   Result<OrderBook>: The order book on success, or an error on failure
     async fn fetch_order_book(&self) -> Result<OrderBook>;
 
-    /// Calculates the mid-price from the exchange's order book
+    /// Calculates the mid-price from the exchange's order book with the
+    /// configured calculator, see `get_mid_price_with`
+    async fn get_mid_price(&self) -> Result<ExchangePrice> {
+        self.get_mid_price_with(&get_mid_price_calculator(), get_depth_bps())
+            .await
+    }
+
+    /// Calculates the mid-price from the exchange's order book with an explicit calculator
     ///
     /// This is a default implementation that:
     /// 1. Fetches the order book using fetch_order_book()
-    /// 2. Calculates the mid-price using OrderBook::calculate_mid_price()
-    /// 3. Returns an ExchangePrice with the exchange name, mid-price, and current timestamp
+    /// 2. Calculates the mid-price with the calculator, see
+    ///    OrderBook::calculate_mid_price_with()
+    /// 3. Measures the book's liquidity for the depth weighting strategies
+    /// 4. Records the time of the book's last update and its feed latency in the metrics
+    /// 5. Returns an ExchangePrice with the exchange name, mid-price, liquidity,
+    ///    the local and exchange times of the book's last update and its feed
+    ///    latency, so a book that stopped updating ages and is eventually
+    ///    excluded as stale
     ///
     /// This method can be overridden by exchanges if they have a more efficient
     /// way to get mid-prices directly.
     ///
+    /// Args:
+    ///   calculator: Calculation of the mid price from the book
+    ///   depth_bps: Band around the mid price the book's depth is measured in
+    ///
     /// Returns:
     ///   Result<ExchangePrice>: The exchange price on success, or an error on failure
-    async fn get_mid_price(&self) -> Result<ExchangePrice> {
+    async fn get_mid_price_with(
+        &self,
+        calculator: &MidPriceCalculator,
+        depth_bps: u32,
+    ) -> Result<ExchangePrice> {
         let order_book = self.fetch_order_book().await?;
-        let mid_price = order_book.calculate_mid_price().ok_or_else(|| {
-            PriceIndexError::InvalidPriceData(format!(
-                "Failed to calculate mid price for {}",
-                self.name()
-            ))
-        })?;
+        let mid_price = order_book
+            .calculate_mid_price_with(calculator)
+            .ok_or_else(|| {
+                PriceIndexError::InvalidPriceData(format!(
+                    "Failed to calculate mid price for {}",
+                    self.name()
+                ))
+            })?;
+
+        METRICS.record_book_update(self.name(), self.symbol(), order_book.timestamp);
+        let latency = order_book.latency();
+        if let Some(latency) = latency {
+            METRICS.record_feed_latency(self.name(), self.symbol(), latency);
+        }
 
         Ok(ExchangePrice {
             exchange: self.name().to_string(),
             mid_price,
-            timestamp: SystemTime::now(),
+            timestamp: order_book.timestamp,
+            exchange_timestamp: order_book.exchange_timestamp,
+            latency_ms: latency.map(|latency| latency.as_millis() as u64),
+            liquidity: order_book.liquidity(depth_bps),
         })
     }
+
+    /// Fetches the trailing 24h traded volume of the market, in the base currency
+    ///
+    /// Used by the volume weighting strategy. The default implementation
+    /// reports no volume.
+    ///
+    /// Returns:
+    ///   Result<Option<Decimal>>: The volume if the exchange provides it, or an error
+    async fn fetch_volume(&self) -> Result<Option<Decimal>> {
+        Ok(None)
+    }
+}
+
+/// Background task streaming an exchange's order book
+///
+/// Shared by the clones of an exchange instance. The task watches the
+/// cancellation token of the feed and, once it is cancelled, closes its
+/// WebSocket connection with a close frame and ends. The token is cancelled
+/// when the feed is stopped, or once the last clone is dropped, e.g. when
+/// the instance is dropped while still connecting.
+#[derive(Default)]
+pub(crate) struct FeedTask {
+    cancel: CancellationToken,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl FeedTask {
+    /// Returns the token the feed's task stops on
+    pub(crate) fn token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Keeps the feed's task, aborting the previous one if any
+    pub(crate) fn set(&self, task: JoinHandle<()>) {
+        if let Some(previous) = self.task.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Asks the feed's task to close its connection and end
+    pub(crate) fn stop(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Stops the feed's task and waits for it to end
+    ///
+    /// The task is aborted if it has not ended within `grace`, e.g. when
+    /// the exchange does not acknowledge the close frame.
+    pub(crate) async fn shutdown(&self, grace: Duration) {
+        self.stop();
+        let task = self.task.lock().unwrap().take();
+        if let Some(mut task) = task {
+            if timeout(grace, &mut task).await.is_err() {
+                warn!("Feed did not close in time, aborting it");
+                task.abort();
+            }
+        }
+    }
+}
+
+impl Drop for FeedTask {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Closes a WebSocket connection with a normal close frame
+///
+/// Gives up after `grace`, so a connection that is already broken does not
+/// hold up the shutdown of its feed.
+pub(crate) async fn close_websocket<S>(write: &mut S, grace: Duration)
+where
+    S: Sink<Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    let frame = CloseFrame {
+        code: CloseCode::Normal,
+        reason: "Shutting down".into(),
+    };
+    match timeout(grace, write.send(Message::Close(Some(frame)))).await {
+        Ok(Ok(())) => info!("WebSocket connection closed"),
+        Ok(Err(e)) => warn!(error = %e, "Failed to close the WebSocket connection"),
+        Err(_) => warn!("Timed out closing the WebSocket connection"),
+    }
+}
+
+/// The exchanges the index can be built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExchangeKind {
+    Binance,
+    Kraken,
+    Huobi,
+}
+
+impl ExchangeKind {
+    /// Every supported exchange
+    pub const ALL: [ExchangeKind; 3] = [
+        ExchangeKind::Binance,
+        ExchangeKind::Kraken,
+        ExchangeKind::Huobi,
+    ];
+
+    /// Returns the name of the exchange
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExchangeKind::Binance => "Binance",
+            ExchangeKind::Kraken => "Kraken",
+            ExchangeKind::Huobi => "Huobi",
+        }
+    }
+
+    /// Returns whether the exchange is enabled in the configuration
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            ExchangeKind::Binance => is_binance_enabled(),
+            ExchangeKind::Kraken => is_kraken_enabled(),
+            ExchangeKind::Huobi => is_huobi_enabled(),
+        }
+    }
+
+    /// Returns whether the exchange is enabled and has an enabled market for a symbol
+    pub fn lists(&self, symbol: &str) -> bool {
+        self.is_enabled()
+            && match self {
+                ExchangeKind::Binance => get_binance_market(symbol).is_some(),
+                ExchangeKind::Kraken => get_kraken_market(symbol).is_some(),
+                ExchangeKind::Huobi => get_huobi_market(symbol).is_some(),
+            }
+    }
+
+    /// Returns how often the aggregator reads the exchange
+    pub fn poll_interval(&self, settings: &Settings) -> Duration {
+        let exchange = &settings.exchange;
+        Duration::from_millis(match self {
+            ExchangeKind::Binance => exchange.binance.poll_interval_ms,
+            ExchangeKind::Kraken => exchange.kraken.poll_interval_ms,
+            ExchangeKind::Huobi => exchange.huobi.poll_interval_ms,
+        })
+    }
+
+    /// Creates the exchange instance tracking a symbol of the global configuration
+    ///
+    /// Returns:
+    ///   Result<Arc<dyn Exchange>>: The connected exchange or an error
+    pub async fn connect(&self, symbol: &str) -> Result<Arc<dyn Exchange>> {
+        self.connect_with(&get_settings(), symbol).await
+    }
+
+    /// Creates the exchange instance tracking a symbol of explicit settings
+    ///
+    /// The REST or WebSocket implementation of Kraken and Huobi is chosen
+    /// by their `use_websocket` setting.
+    ///
+    /// Returns:
+    ///   Result<Arc<dyn Exchange>>: The connected exchange or an error
+    pub async fn connect_with(
+        &self,
+        settings: &Settings,
+        symbol: &str,
+    ) -> Result<Arc<dyn Exchange>> {
+        let exchange = &settings.exchange;
+        let connection = &exchange.config;
+        let capture = &settings.capture;
+        Ok(match self {
+            ExchangeKind::Binance => Arc::new(
+                binance::BinanceExchange::new(symbol, &exchange.binance, connection, capture)
+                    .await?,
+            ),
+            ExchangeKind::Kraken if exchange.kraken.use_websocket => Arc::new(
+                kraken_ws::KrakenWsExchange::new(symbol, &exchange.kraken, connection, capture)
+                    .await?,
+            ),
+            ExchangeKind::Kraken => {
+                Arc::new(kraken::KrakenExchange::new(symbol, &exchange.kraken, capture).await?)
+            }
+            ExchangeKind::Huobi if exchange.huobi.use_websocket => Arc::new(
+                huobi_ws::HuobiWsExchange::new(symbol, &exchange.huobi, connection, capture)
+                    .await?,
+            ),
+            ExchangeKind::Huobi => {
+                Arc::new(huobi::HuobiExchange::new(symbol, &exchange.huobi, capture).await?)
+            }
+        })
+    }
+
+    /// Returns the settings a market of the exchange is connected with
+    ///
+    /// Returns:
+    ///   The settings, or None if the symbol is not indexed, the exchange
+    ///   is disabled or it has no enabled market for the symbol
+    pub fn market_settings(&self, settings: &Settings, symbol: &str) -> Option<MarketSettings> {
+        if !settings
+            .index
+            .symbols
+            .iter()
+            .any(|indexed| indexed == symbol)
+        {
+            return None;
+        }
+        let market_of = |markets: &[MarketConfig]| -> Option<Vec<MarketConfig>> {
+            markets
+                .iter()
+                .find(|market| market.enabled && market.symbol == symbol)
+                .map(|market| vec![market.clone()])
+        };
+        let exchange = &settings.exchange;
+        let section = match self {
+            ExchangeKind::Binance if exchange.binance.enabled => {
+                market_of(&exchange.binance.markets).map(|markets| {
+                    ExchangeSection::Binance(BinanceConfig {
+                        markets,
+                        ..exchange.binance.clone()
+                    })
+                })
+            }
+            ExchangeKind::Kraken if exchange.kraken.enabled => market_of(&exchange.kraken.markets)
+                .map(|markets| {
+                    ExchangeSection::Kraken(KrakenConfig {
+                        markets,
+                        ..exchange.kraken.clone()
+                    })
+                }),
+            ExchangeKind::Huobi if exchange.huobi.enabled => market_of(&exchange.huobi.markets)
+                .map(|markets| {
+                    ExchangeSection::Huobi(HuobiConfig {
+                        markets,
+                        ..exchange.huobi.clone()
+                    })
+                }),
+            _ => None,
+        }?;
+        Some(MarketSettings {
+            section,
+            connection: exchange.config.clone(),
+            capture: settings.capture.clone(),
+        })
+    }
+}
+
+/// Settings an (exchange, symbol) market is connected with
+///
+/// Holds everything the market's exchange instance and its connection task
+/// are created with, so two configurations connect the market the same way
+/// exactly when they give equal settings. The poll interval is left out of
+/// the comparison, as the aggregation task reads it on every poll, see
+/// `ExchangeRegistry::poll_interval`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketSettings {
+    /// The exchange's section with only the market of the symbol
+    pub section: ExchangeSection,
+    /// Reconnection, ping and close settings shared by the exchanges
+    pub connection: ExchangeConfig,
+    /// Capture of the raw feed
+    pub capture: Capture,
+}
+
+/// Configuration section of an exchange
+#[derive(Debug, Clone)]
+pub enum ExchangeSection {
+    Binance(BinanceConfig),
+    Kraken(KrakenConfig),
+    Huobi(HuobiConfig),
+}
+
+impl PartialEq for ExchangeSection {
+    /// Compares the sections regardless of their poll interval
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ExchangeSection::Binance(a), ExchangeSection::Binance(b)) => {
+                BinanceConfig {
+                    poll_interval_ms: b.poll_interval_ms,
+                    ..a.clone()
+                } == *b
+            }
+            (ExchangeSection::Kraken(a), ExchangeSection::Kraken(b)) => {
+                KrakenConfig {
+                    poll_interval_ms: b.poll_interval_ms,
+                    ..a.clone()
+                } == *b
+            }
+            (ExchangeSection::Huobi(a), ExchangeSection::Huobi(b)) => {
+                HuobiConfig {
+                    poll_interval_ms: b.poll_interval_ms,
+                    ..a.clone()
+                } == *b
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns every enabled (exchange, symbol) market of the indexed symbols of the settings
+pub fn enabled_markets(settings: &Settings) -> Vec<(ExchangeKind, String)> {
+    let mut markets = Vec::new();
+    for symbol in &settings.index.symbols {
+        for kind in ExchangeKind::ALL {
+            if kind.market_settings(settings, symbol).is_some() {
+                markets.push((kind, symbol.clone()));
+            }
+        }
+    }
+    markets
 }
@@ -0,0 +1,324 @@
+// Registry of connected exchange instances
+use crate::aggregator::Aggregator;
+use crate::config::{get_settings, Settings};
+use crate::error::Result;
+use crate::exchanges::{enabled_markets, Exchange, ExchangeKind};
+use crate::metrics::METRICS;
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{info, info_span, warn, Instrument};
+
+/// Tasks of each started (exchange, symbol) market
+type MarketTasks = HashMap<(&'static str, String), Vec<JoinHandle<()>>>;
+
+/// ExchangeRegistry holds every connected (exchange, symbol) instance
+///
+/// Instances are registered once they connect, so a venue that is down
+/// at startup simply joins the registry later instead of preventing the
+/// server from starting. The markets started in the background are kept
+/// so the ones still connecting can be reported, together with their
+/// connection and aggregation tasks so they can be stopped.
+///
+/// Markets are connected with the settings the registry holds: those given
+/// by `with_settings`, or a copy of the global settings taken by `new`,
+/// until they are replaced by `set_settings`, e.g. on reload.
+#[derive(Clone)]
+pub struct ExchangeRegistry {
+    exchanges: Arc<RwLock<Vec<Arc<dyn Exchange>>>>,
+    markets: Arc<RwLock<Vec<(&'static str, String)>>>,
+    tasks: Arc<Mutex<MarketTasks>>,
+    settings: Arc<RwLock<Arc<Settings>>>,
+}
+
+impl Default for ExchangeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExchangeRegistry {
+    /// Creates an empty registry connecting markets with the global settings
+    /// as of its creation
+    pub fn new() -> Self {
+        Self::with_settings(get_settings())
+    }
+
+    /// Creates an empty registry connecting markets with explicit settings
+    pub fn with_settings(settings: Settings) -> Self {
+        Self {
+            exchanges: Arc::default(),
+            markets: Arc::default(),
+            tasks: Arc::default(),
+            settings: Arc::new(RwLock::new(Arc::new(settings))),
+        }
+    }
+
+    /// Replaces the settings markets started from now on are connected with
+    pub fn set_settings(&self, settings: Settings) {
+        *self.settings.write().unwrap() = Arc::new(settings);
+    }
+
+    /// Returns the settings markets are connected with
+    pub fn settings(&self) -> Arc<Settings> {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Returns how often the markets of an exchange are read, see
+    /// `ExchangeKind::poll_interval`
+    ///
+    /// Read by the aggregation tasks of the started markets on every read,
+    /// so a reloaded poll interval applies without restarting them.
+    pub fn poll_interval(&self, kind: ExchangeKind) -> Duration {
+        kind.poll_interval(&self.settings.read().unwrap())
+    }
+
+    /// Adds a connected exchange instance
+    pub fn register(&self, exchange: Arc<dyn Exchange>) {
+        self.exchanges.write().unwrap().push(exchange);
+    }
+
+    /// Returns every registered exchange instance
+    pub fn exchanges(&self) -> Vec<Arc<dyn Exchange>> {
+        self.exchanges.read().unwrap().clone()
+    }
+
+    /// Returns the registered exchange instances tracking a symbol
+    pub fn for_symbol(&self, symbol: &str) -> Vec<Arc<dyn Exchange>> {
+        self.exchanges
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|exchange| exchange.symbol() == symbol)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the (exchange, symbol) markets started with `start`, connected or not
+    pub fn markets(&self) -> Vec<(&'static str, String)> {
+        self.markets.read().unwrap().clone()
+    }
+
+    /// Connects every enabled market of the settings in the background
+    pub fn start_configured(&self, aggregator: &Aggregator) {
+        for (kind, symbol) in enabled_markets(&self.settings()) {
+            self.start(kind, symbol, aggregator);
+        }
+    }
+
+    /// Connects an exchange market in the background
+    ///
+    /// The market is listed in `markets` right away. See
+    /// `connect_in_background` for the retry behavior. Failed attempts are
+    /// counted in the metrics and logged with the symbol.
+    pub fn start(&self, kind: ExchangeKind, symbol: String, aggregator: &Aggregator) {
+        let market = (kind.name(), symbol.clone());
+        self.markets.write().unwrap().push(market.clone());
+        // The connection task's span is created as a child of this one
+        let span = info_span!("market", symbol = %symbol);
+        let settings = self.settings();
+        let registry = self.clone();
+        let poll_interval = move || registry.poll_interval(kind);
+        let connect = move || {
+            let symbol = symbol.clone();
+            let settings = settings.clone();
+            async move {
+                kind.connect_with(&settings, &symbol)
+                    .await
+                    .inspect_err(|e| METRICS.record_error(kind.name(), &symbol, e))
+            }
+        };
+        let task = span.in_scope(|| {
+            self.connect_polling_in_background(kind.name(), connect, poll_interval, aggregator)
+        });
+        self.track(market, task);
+    }
+
+    /// Stops an exchange market started with `start`
+    ///
+    /// This function:
+    /// 1. Removes the market from `markets`
+    /// 2. Cancels its connection task, waiting for it to finish so an
+    ///    exchange connecting meanwhile is registered before it is removed
+    /// 3. Cancels its aggregation task, unregisters its exchange instances
+    ///    and stops their feeds
+    /// 4. Removes its venue from the index of the symbol
+    pub async fn stop(&self, kind: ExchangeKind, symbol: &str, aggregator: &Aggregator) {
+        let market = (kind.name(), symbol.to_string());
+        self.markets
+            .write()
+            .unwrap()
+            .retain(|started| *started != market);
+        let tasks = self.tasks.lock().unwrap().remove(&market);
+        for task in tasks.unwrap_or_default() {
+            task.abort();
+            let _ = task.await;
+        }
+        // The connection task may have started aggregating before it was cancelled
+        for task in self
+            .tasks
+            .lock()
+            .unwrap()
+            .remove(&market)
+            .unwrap_or_default()
+        {
+            task.abort();
+        }
+
+        let stopped: Vec<Arc<dyn Exchange>> = {
+            let mut exchanges = self.exchanges.write().unwrap();
+            let (stopped, kept) = exchanges
+                .drain(..)
+                .partition(|exchange| exchange.name() == market.0 && exchange.symbol() == symbol);
+            *exchanges = kept;
+            stopped
+        };
+        for exchange in stopped {
+            exchange.stop();
+        }
+        aggregator.remove_venue(symbol, kind.name());
+        info!(exchange = kind.name(), symbol, "Stopped");
+    }
+
+    /// Stops every market, closing the exchange feeds
+    ///
+    /// This function:
+    /// 1. Clears `markets`
+    /// 2. Cancels the connection and aggregation tasks of every market,
+    ///    waiting for them to finish
+    /// 3. Unregisters every exchange instance and shuts their feeds down
+    ///    at once, waiting for the WebSocket feeds to close their
+    ///    connections, see `Exchange::shutdown`
+    pub async fn shutdown(&self) {
+        self.markets.write().unwrap().clear();
+        let tasks: Vec<JoinHandle<()>> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .drain()
+            .flat_map(|(_, tasks)| tasks)
+            .collect();
+        for task in tasks {
+            task.abort();
+            let _ = task.await;
+        }
+        // The connection tasks may have started aggregating before they were cancelled
+        for task in self
+            .tasks
+            .lock()
+            .unwrap()
+            .drain()
+            .flat_map(|(_, tasks)| tasks)
+        {
+            task.abort();
+        }
+
+        let exchanges = std::mem::take(&mut *self.exchanges.write().unwrap());
+        join_all(exchanges.iter().map(|exchange| exchange.shutdown())).await;
+        info!(exchanges = exchanges.len(), "Exchange feeds stopped");
+    }
+
+    /// Stops an exchange market and connects it again with the current settings
+    pub async fn restart(&self, kind: ExchangeKind, symbol: &str, aggregator: &Aggregator) {
+        self.stop(kind, symbol, aggregator).await;
+        self.start(kind, symbol.to_string(), aggregator);
+    }
+
+    /// Keeps a task of a market so it is cancelled when the market is stopped
+    fn track(&self, market: (&'static str, String), task: JoinHandle<()>) {
+        self.tasks
+            .lock()
+            .unwrap()
+            .entry(market)
+            .or_default()
+            .push(task);
+    }
+
+    /// Connects an exchange in the background, retrying until it succeeds
+    ///
+    /// This function:
+    /// 1. Calls `connect` until it succeeds, waiting between attempts with
+    ///    exponential backoff from the initial to the maximum reconnect delay
+    /// 2. Registers the connected exchange
+    /// 3. Hands it to the aggregator, which reads it every `poll_interval`,
+    ///    and keeps the aggregation task with the exchange's market
+    ///
+    /// Events are logged in a span carrying the exchange, and failed
+    /// attempts with their attempt number.
+    ///
+    /// Returns:
+    ///   The handle of the connection task
+    pub fn connect_in_background<F, Fut>(
+        &self,
+        name: &'static str,
+        connect: F,
+        poll_interval: Duration,
+        aggregator: &Aggregator,
+    ) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Arc<dyn Exchange>>> + Send,
+    {
+        self.connect_polling_in_background(name, connect, move || poll_interval, aggregator)
+    }
+
+    /// Connects an exchange in the background, read at a changing interval
+    ///
+    /// Connects the exchange as `connect_in_background` does, the
+    /// aggregator reading it every `poll_interval()`, see
+    /// `Aggregator::spawn_polling`.
+    ///
+    /// Returns:
+    ///   The handle of the connection task
+    fn connect_polling_in_background<F, Fut, I>(
+        &self,
+        name: &'static str,
+        connect: F,
+        poll_interval: I,
+        aggregator: &Aggregator,
+    ) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Arc<dyn Exchange>>> + Send,
+        I: Fn() -> Duration + Send + 'static,
+    {
+        let registry = self.clone();
+        let aggregator = aggregator.clone();
+        let connection = self.settings().exchange.config.clone();
+        let span = info_span!("connect", exchange = name);
+        tokio::spawn(
+            async move {
+                let mut retry_delay = connection.initial_reconnect_duration();
+                let max_retry_delay = connection.max_reconnect_duration();
+
+                for attempt in 1u64.. {
+                    match connect().await {
+                        Ok(exchange) => {
+                            info!(symbol = exchange.symbol(), attempt, "Connected");
+                            registry.register(exchange.clone());
+                            let market = (exchange.name(), exchange.symbol().to_string());
+                            registry
+                                .track(market, aggregator.spawn_polling(exchange, poll_interval));
+                            return;
+                        }
+                        Err(e) => {
+                            warn!(
+                                attempt,
+                                error = %e,
+                                retry_in_secs = retry_delay.as_secs(),
+                                "Failed to connect"
+                            );
+                            sleep(retry_delay).await;
+                            retry_delay = std::cmp::min(retry_delay * 2, max_retry_delay);
+                        }
+                    }
+                }
+            }
+            .instrument(span),
+        )
+    }
+}
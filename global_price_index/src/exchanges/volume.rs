@@ -0,0 +1,131 @@
+// REST clients of the exchanges' 24h ticker endpoints
+use crate::error::{PriceIndexError, Result};
+use crate::models::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Binance 24h ticker, volumes are decimal strings
+#[derive(Debug, Deserialize)]
+struct BinanceTicker {
+    volume: Decimal,
+}
+
+/// Kraken ticker of a pair
+///
+/// `v` holds the volume of today and of the last 24 hours
+#[derive(Debug, Deserialize)]
+struct KrakenTicker {
+    v: [Decimal; 2],
+}
+
+/// Kraken ticker response, keyed by Kraken's own pair name
+#[derive(Debug, Deserialize)]
+struct KrakenTickerResponse {
+    error: Vec<String>,
+    #[serde(default)]
+    result: HashMap<String, KrakenTicker>,
+}
+
+/// Huobi 24h market detail
+///
+/// `amount` is the base currency volume, sent as a JSON number
+#[derive(Debug, Deserialize)]
+struct HuobiDetail {
+    amount: f64,
+}
+
+/// Huobi market detail response
+#[derive(Debug, Deserialize)]
+struct HuobiDetailResponse {
+    status: String,
+    #[serde(rename = "err-msg")]
+    err_msg: Option<String>,
+    tick: Option<HuobiDetail>,
+}
+
+/// Creates an HTTP client with a 5-second timeout
+fn client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| PriceIndexError::ExchangeError(format!("Failed to create HTTP client: {}", e)))
+}
+
+/// Fetches the trailing 24h base volume of a Binance market
+///
+/// Args:
+///   url: 24h ticker endpoint
+///   exchange_symbol: Binance symbol of the market (e.g. BTCUSDT)
+pub async fn binance_volume(url: &str, exchange_symbol: &str) -> Result<Decimal> {
+    let ticker: BinanceTicker = client()?
+        .get(url)
+        .query(&[("symbol", exchange_symbol)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(ticker.volume)
+}
+
+/// Fetches the trailing 24h base volume of a Kraken market
+///
+/// Args:
+///   url: Ticker endpoint
+///   exchange_symbol: Kraken pair of the market (e.g. XBTUSDT)
+pub async fn kraken_volume(url: &str, exchange_symbol: &str) -> Result<Decimal> {
+    let response: KrakenTickerResponse = client()?
+        .get(url)
+        .query(&[("pair", exchange_symbol)])
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if !response.error.is_empty() {
+        return Err(PriceIndexError::ExchangeError(format!(
+            "Kraken API error: {:?}",
+            response.error
+        )));
+    }
+
+    // The result is keyed by Kraken's own pair name, which may differ from the requested one
+    let ticker = response.result.into_values().next().ok_or_else(|| {
+        PriceIndexError::ExchangeError("No ticker data received from Kraken".to_string())
+    })?;
+    Ok(ticker.v[1])
+}
+
+/// Fetches the trailing 24h base volume of a Huobi market
+///
+/// Args:
+///   url: Market detail endpoint
+///   exchange_symbol: Huobi symbol of the market (e.g. btcusdt)
+pub async fn huobi_volume(url: &str, exchange_symbol: &str) -> Result<Decimal> {
+    let response: HuobiDetailResponse = client()?
+        .get(url)
+        .query(&[("symbol", exchange_symbol)])
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if response.status != "ok" {
+        return Err(PriceIndexError::ExchangeError(format!(
+            "Huobi API error: status = {}, error = {:?}",
+            response.status, response.err_msg
+        )));
+    }
+
+    let detail = response.tick.ok_or_else(|| {
+        PriceIndexError::ExchangeError("No market detail received from Huobi".to_string())
+    })?;
+    // The shortest round-trip representation of the number is the text sent by Huobi
+    detail.amount.to_string().parse::<Decimal>().map_err(|_| {
+        PriceIndexError::InvalidPriceData(format!(
+            "Failed to parse Huobi volume as decimal: {}",
+            detail.amount
+        ))
+    })
+}
@@ -0,0 +1,177 @@
+// Liveness and readiness of the exchange feeds
+use crate::api::AppState;
+use crate::models::optional_timestamp_serde;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// Connection state of an exchange market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// Started but not connected yet, the connection is being retried
+    Connecting,
+    /// Connected and read by the aggregator
+    Connected,
+    /// Connected once, but its WebSocket stream is down and reconnecting
+    Disconnected,
+}
+
+/// Health of an (exchange, symbol) market
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedHealth {
+    pub exchange: String,
+    pub symbol: String,
+    pub state: ConnectionState,
+    /// Time of the venue's last successful price update, if any
+    #[serde(serialize_with = "optional_timestamp_serde::serialize")]
+    pub last_update: Option<SystemTime>,
+    /// Age of the last update in milliseconds, as of the report
+    pub age_ms: Option<u64>,
+    /// Whether the venue was not updated within the maximum age of the venue prices
+    pub stale: bool,
+}
+
+impl FeedHealth {
+    /// Returns whether the venue counts towards its symbol's quorum
+    pub fn is_healthy(&self) -> bool {
+        self.state == ConnectionState::Connected && !self.stale
+    }
+}
+
+/// Venue quorum of an indexed symbol
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolHealth {
+    pub symbol: String,
+    /// Number of connected venues updated within the maximum age of the venue prices
+    pub healthy_venues: usize,
+    pub min_venues: usize,
+    pub quorum_met: bool,
+}
+
+/// Health report served by the /health and /ready endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Whether every indexed symbol meets its venue quorum
+    pub ready: bool,
+    /// Age beyond which a venue is stale, and left out of the index
+    pub max_age_ms: u64,
+    pub symbols: Vec<SymbolHealth>,
+    pub exchanges: Vec<FeedHealth>,
+}
+
+impl HealthReport {
+    /// Builds the health report of the service as of `now`
+    ///
+    /// This function:
+    /// 1. Lists every market started by the registry, and every registered
+    ///    exchange, as connecting until it is registered and then as
+    ///    connected or disconnected depending on its feed
+    /// 2. Takes each venue's last update from the aggregator's latest price
+    ///    and flags it stale if older than `max_age`, the age beyond which
+    ///    the index leaves it out
+    /// 3. Counts the connected, fresh venues of each indexed symbol against
+    ///    the `min_venues` quorum
+    ///
+    /// The service is ready once every indexed symbol meets its quorum.
+    pub fn new(state: &AppState, max_age: Duration, min_venues: usize, now: SystemTime) -> Self {
+        let registered = state.registry.exchanges();
+        let mut markets = state.registry.markets();
+        for exchange in &registered {
+            let market = (exchange.name(), exchange.symbol().to_string());
+            if !markets.contains(&market) {
+                markets.push(market);
+            }
+        }
+
+        let exchanges: Vec<FeedHealth> = markets
+            .into_iter()
+            .map(|(exchange, symbol)| {
+                let state_of = registered
+                    .iter()
+                    .find(|e| e.name() == exchange && e.symbol() == symbol)
+                    .map(|e| {
+                        if e.is_connected() {
+                            ConnectionState::Connected
+                        } else {
+                            ConnectionState::Disconnected
+                        }
+                    })
+                    .unwrap_or(ConnectionState::Connecting);
+                let last_update = state
+                    .aggregator
+                    .exchange_prices(&symbol)
+                    .into_iter()
+                    .find(|price| price.exchange == exchange)
+                    .map(|price| price.timestamp);
+                let age = last_update.map(|at| now.duration_since(at).unwrap_or(Duration::ZERO));
+
+                FeedHealth {
+                    exchange: exchange.to_string(),
+                    symbol,
+                    state: state_of,
+                    last_update,
+                    age_ms: age.map(|age| age.as_millis() as u64),
+                    stale: age.is_none_or(|age| age > max_age),
+                }
+            })
+            .collect();
+
+        let symbols: Vec<SymbolHealth> = state
+            .symbols
+            .iter()
+            .map(|symbol| {
+                let healthy_venues = exchanges
+                    .iter()
+                    .filter(|feed| &feed.symbol == symbol && feed.is_healthy())
+                    .count();
+                SymbolHealth {
+                    symbol: symbol.clone(),
+                    healthy_venues,
+                    min_venues,
+                    quorum_met: healthy_venues >= min_venues,
+                }
+            })
+            .collect();
+
+        Self {
+            ready: symbols.iter().all(|symbol| symbol.quorum_met),
+            max_age_ms: max_age.as_millis() as u64,
+            symbols,
+            exchanges,
+        }
+    }
+
+    /// Builds the health report with the maximum price age and quorum of the state's settings
+    pub fn configured(state: &AppState) -> Self {
+        let settings = state.settings();
+        Self::new(
+            state,
+            settings.price_weighting.max_age(),
+            settings.health.min_venues,
+            SystemTime::now(),
+        )
+    }
+}
+
+/// HTTP handler for the /health endpoint (liveness)
+///
+/// Returns:
+///   HTTP 200 with the HealthReport JSON as long as the service runs
+pub async fn get_health(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(HealthReport::configured(&data))
+}
+
+/// HTTP handler for the /ready endpoint (readiness)
+///
+/// Returns:
+///   HTTP 200 with the HealthReport JSON if every symbol meets its venue quorum
+///   HTTP 503 with the HealthReport JSON otherwise
+pub async fn get_ready(data: web::Data<AppState>) -> impl Responder {
+    let report = HealthReport::configured(&data);
+    if report.ready {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
@@ -0,0 +1,630 @@
+// Persistence of the computed indices and time-range queries over them
+use crate::api::{resolve_symbol, AppState};
+use crate::error::{PriceIndexError, Result};
+use crate::models::{timestamp_serde, Decimal, ExchangePrice, GlobalPriceIndex};
+use actix_web::{web, HttpResponse, Responder};
+use chrono::DateTime;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+/// Time window queried when a history request gives no start time
+const DEFAULT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Tables of the history database
+///
+/// Prices are stored as decimal strings so they are read back exactly,
+/// timestamps as milliseconds since the Unix epoch.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS indices (
+    id INTEGER PRIMARY KEY,
+    symbol TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    price TEXT NOT NULL,
+    excluded TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS indices_symbol_timestamp ON indices (symbol, timestamp_ms);
+CREATE TABLE IF NOT EXISTS exchange_prices (
+    index_id INTEGER NOT NULL REFERENCES indices (id),
+    exchange TEXT NOT NULL,
+    mid_price TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    liquidity TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS exchange_prices_index_id ON exchange_prices (index_id);
+";
+
+/// Columns added to the tables of the history database since they were
+/// created, as (table, column, definition)
+///
+/// Databases created by an earlier version are given the missing columns
+/// when opened, their existing rows taking the column's default.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("indices", "venues", "INTEGER NOT NULL DEFAULT 0"),
+    ("indices", "min_venues", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Period covered by each OHLC candle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl Resolution {
+    /// Returns the length of the period
+    pub fn duration(&self) -> Duration {
+        match self {
+            Resolution::OneMinute => Duration::from_secs(60),
+            Resolution::FiveMinutes => Duration::from_secs(300),
+            Resolution::OneHour => Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Open, high, low and close of the index over one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Start of the period, aligned on a multiple of the resolution
+    #[serde(with = "timestamp_serde")]
+    pub start: SystemTime,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub open: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub high: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub low: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub close: Decimal,
+    /// Number of indices computed during the period
+    pub ticks: usize,
+}
+
+/// Embedded SQLite store of every computed index and its venue prices
+///
+/// The store is shared by the aggregator, which records each index it
+/// computes, and the history endpoints, which query it. Clones share the
+/// same connection.
+#[derive(Clone)]
+pub struct HistoryStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl HistoryStore {
+    /// Opens the history database at a path, creating it and its directory if missing
+    pub fn open(path: &str) -> Result<Self> {
+        if let Some(parent) = Path::new(path).parent() {
+            // A directory that cannot be created is reported by the open below
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let connection = Connection::open(path)?;
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        Self::init(connection)
+    }
+
+    /// Opens a history database held in memory, lost when the store is dropped
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// Creates the tables of a new database and the columns missing from an older one
+    fn init(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        for (table, column, definition) in ADDED_COLUMNS {
+            let exists = connection
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                    table
+                ))?
+                .exists(params![column])?;
+            if !exists {
+                connection.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, definition
+                ))?;
+            }
+        }
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Stores an index, its venue quorum and the venue prices it was computed from
+    pub fn record(&self, index: &GlobalPriceIndex) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO indices (symbol, timestamp_ms, price, excluded, venues, min_venues)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                index.symbol,
+                to_millis(index.timestamp),
+                index.price.to_string(),
+                serde_json::to_string(&index.excluded)?,
+                index.venues as i64,
+                index.min_venues as i64,
+            ],
+        )?;
+        let index_id = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO exchange_prices (index_id, exchange, mid_price, timestamp_ms, liquidity)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for price in &index.exchange_prices {
+                insert.execute(params![
+                    index_id,
+                    price.exchange,
+                    price.mid_price.to_string(),
+                    to_millis(price.timestamp),
+                    serde_json::to_string(&price.liquidity)?,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Writes the indices recorded so far into the database file
+    ///
+    /// Recorded indices are committed to the write-ahead log, which SQLite
+    /// moves into the database file from time to time. This moves all of it
+    /// and empties the log, so the file is complete on its own once the
+    /// service stops.
+    pub fn flush(&self) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Returns the indices of a symbol computed in a time range, oldest first
+    ///
+    /// Args:
+    ///   symbol: Indexed symbol, e.g. "BTC/USDT"
+    ///   from: Start of the range, inclusive
+    ///   to: End of the range, exclusive
+    ///   limit: Maximum number of indices returned
+    ///
+    /// Returns:
+    ///   The indices with the venue prices each was computed from
+    pub fn range(
+        &self,
+        symbol: &str,
+        from: SystemTime,
+        to: SystemTime,
+        limit: usize,
+    ) -> Result<Vec<GlobalPriceIndex>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare_cached(
+            "SELECT id, timestamp_ms, price, excluded, venues, min_venues FROM indices
+             WHERE symbol = ?1 AND timestamp_ms >= ?2 AND timestamp_ms < ?3
+             ORDER BY timestamp_ms, id LIMIT ?4",
+        )?;
+        let rows = statement
+            .query_map(
+                params![symbol, to_millis(from), to_millis(to), limit as i64],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, i64>(4)? as usize,
+                        row.get::<_, i64>(5)? as usize,
+                    ))
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut venues: HashMap<i64, Vec<ExchangePrice>> = HashMap::new();
+        let ids = rows.iter().map(|(id, ..)| *id);
+        if let (Some(first), Some(last)) = (ids.clone().min(), ids.max()) {
+            let mut statement = connection.prepare_cached(
+                "SELECT e.index_id, e.exchange, e.mid_price, e.timestamp_ms, e.liquidity
+                 FROM exchange_prices e JOIN indices i ON i.id = e.index_id
+                 WHERE i.symbol = ?1 AND e.index_id BETWEEN ?2 AND ?3
+                 ORDER BY e.rowid",
+            )?;
+            let prices = statement
+                .query_map(params![symbol, first, last], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (index_id, exchange, mid_price, timestamp_ms, liquidity) in prices {
+                venues.entry(index_id).or_default().push(ExchangePrice {
+                    exchange,
+                    mid_price: parse_decimal(&mid_price)?,
+                    timestamp: from_millis(timestamp_ms),
+                    exchange_timestamp: None,
+                    latency_ms: None,
+                    liquidity: serde_json::from_str(&liquidity)?,
+                });
+            }
+        }
+
+        rows.into_iter()
+            .map(
+                |(id, timestamp_ms, price, excluded, priced_venues, min_venues)| {
+                    Ok(GlobalPriceIndex {
+                        symbol: symbol.to_string(),
+                        price: parse_decimal(&price)?,
+                        timestamp: from_millis(timestamp_ms),
+                        exchange_prices: venues.remove(&id).unwrap_or_default(),
+                        excluded: serde_json::from_str(&excluded)?,
+                        venues: priced_venues,
+                        min_venues,
+                        quorum_met: priced_venues >= min_venues,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Builds OHLC candles of a symbol's index from the indices stored in a time range
+    ///
+    /// Each candle covers one period of the resolution, aligned on the Unix
+    /// epoch. Periods without any computed index have no candle.
+    ///
+    /// The candles are aggregated by the database, so only one row per
+    /// period is read whatever the number of indices in the range. Highs
+    /// and lows are ranked by the numeric value of the stored prices and
+    /// returned exactly as stored.
+    ///
+    /// Args:
+    ///   symbol: Indexed symbol, e.g. "BTC/USDT"
+    ///   from: Start of the range, inclusive
+    ///   to: End of the range, exclusive
+    ///   resolution: Period covered by each candle
+    ///
+    /// Returns:
+    ///   The candles, oldest first
+    pub fn candles(
+        &self,
+        symbol: &str,
+        from: SystemTime,
+        to: SystemTime,
+        resolution: Resolution,
+    ) -> Result<Vec<Candle>> {
+        let period = resolution.duration().as_millis() as i64;
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare_cached(
+            "WITH ticks AS (
+                 SELECT id, timestamp_ms, price, CAST(price AS REAL) AS value,
+                        timestamp_ms - (timestamp_ms % ?4 + ?4) % ?4 AS start_ms
+                 FROM indices
+                 WHERE symbol = ?1 AND timestamp_ms >= ?2 AND timestamp_ms < ?3
+             ),
+             ranked AS (
+                 SELECT start_ms, price,
+                        ROW_NUMBER() OVER (PARTITION BY start_ms
+                            ORDER BY timestamp_ms, id) AS open_rank,
+                        ROW_NUMBER() OVER (PARTITION BY start_ms
+                            ORDER BY value DESC, timestamp_ms, id) AS high_rank,
+                        ROW_NUMBER() OVER (PARTITION BY start_ms
+                            ORDER BY value, timestamp_ms, id) AS low_rank,
+                        ROW_NUMBER() OVER (PARTITION BY start_ms
+                            ORDER BY timestamp_ms DESC, id DESC) AS close_rank
+                 FROM ticks
+             )
+             SELECT start_ms,
+                    MAX(CASE WHEN open_rank = 1 THEN price END),
+                    MAX(CASE WHEN high_rank = 1 THEN price END),
+                    MAX(CASE WHEN low_rank = 1 THEN price END),
+                    MAX(CASE WHEN close_rank = 1 THEN price END),
+                    COUNT(*)
+             FROM ranked
+             GROUP BY start_ms
+             ORDER BY start_ms",
+        )?;
+        let rows = statement
+            .query_map(
+                params![symbol, to_millis(from), to_millis(to), period],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        [
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                            row.get::<_, String>(4)?,
+                        ],
+                        row.get::<_, usize>(5)?,
+                    ))
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(start_ms, [open, high, low, close], ticks)| {
+                Ok(Candle {
+                    start: from_millis(start_ms),
+                    open: parse_decimal(&open)?,
+                    high: parse_decimal(&high)?,
+                    low: parse_decimal(&low)?,
+                    close: parse_decimal(&close)?,
+                    ticks,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Request handled by the thread of a `HistoryWriter`
+enum WriteRequest {
+    /// Stores an index
+    Record(Box<GlobalPriceIndex>),
+    /// Flushes the store once the indices queued before are stored, and
+    /// sends back the result
+    Flush(mpsc::Sender<Result<()>>),
+}
+
+/// Writer storing computed indices in a history store on a dedicated thread
+///
+/// SQLite writes block, so indices are queued to the writer's thread rather
+/// than stored by the tasks computing them, and stored in the order they
+/// were queued. Clones share the same queue, and the thread stops once
+/// every clone is dropped.
+#[derive(Clone)]
+pub struct HistoryWriter {
+    store: HistoryStore,
+    requests: mpsc::Sender<WriteRequest>,
+}
+
+impl HistoryWriter {
+    /// Starts the thread writing to a history store
+    pub fn spawn(store: HistoryStore) -> Self {
+        let (requests, queue) = mpsc::channel();
+        let writer = store.clone();
+        thread::Builder::new()
+            .name("history-writer".to_string())
+            .spawn(move || {
+                for request in queue {
+                    match request {
+                        WriteRequest::Record(index) => {
+                            if let Err(e) = writer.record(&index) {
+                                error!(symbol = %index.symbol, error = %e, "Error storing index history");
+                            }
+                        }
+                        WriteRequest::Flush(result) => {
+                            let _ = result.send(writer.flush());
+                        }
+                    }
+                }
+            })
+            .expect("Failed to start the history writer thread");
+        Self { store, requests }
+    }
+
+    /// Returns the store the indices are written to
+    pub fn store(&self) -> &HistoryStore {
+        &self.store
+    }
+
+    /// Queues an index to be stored, without waiting for the write
+    pub fn record(&self, index: GlobalPriceIndex) {
+        if self
+            .requests
+            .send(WriteRequest::Record(Box::new(index)))
+            .is_err()
+        {
+            error!("History writer stopped, index not stored");
+        }
+    }
+
+    /// Waits for the queued indices to be stored, then flushes the store
+    ///
+    /// Blocks the calling thread, see `HistoryStore::flush`. If the writer's
+    /// thread stopped, the indices stored so far are flushed.
+    pub fn flush(&self) -> Result<()> {
+        let (result, flushed) = mpsc::channel();
+        self.requests
+            .send(WriteRequest::Flush(result))
+            .ok()
+            .and_then(|_| flushed.recv().ok())
+            .unwrap_or_else(|| self.store.flush())
+    }
+}
+
+/// Converts a time to milliseconds since the Unix epoch
+fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Converts milliseconds since the Unix epoch to a time
+fn from_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+/// Parses a decimal stored as a string
+fn parse_decimal(value: &str) -> Result<Decimal> {
+    value.parse::<Decimal>().map_err(|_| {
+        PriceIndexError::InvalidPriceData(format!("Invalid decimal in index history: {}", value))
+    })
+}
+
+/// Query parameters of the /history/{symbol} endpoint
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Start of the range as an RFC 3339 time, one hour before `to` when omitted
+    pub from: Option<String>,
+    /// End of the range as an RFC 3339 time, now when omitted
+    pub to: Option<String>,
+    /// Maximum number of indices returned, at most `[history] max_results`
+    pub limit: Option<usize>,
+}
+
+/// Query parameters of the /history/{symbol}/candles endpoint
+#[derive(Debug, Deserialize)]
+pub struct CandleQuery {
+    /// Period covered by each candle: 1m, 5m or 1h
+    pub resolution: Resolution,
+    /// Start of the range as an RFC 3339 time, one hour before `to` when omitted
+    pub from: Option<String>,
+    /// End of the range as an RFC 3339 time, now when omitted
+    pub to: Option<String>,
+}
+
+/// A history request resolved against the application state
+struct HistoryRequest {
+    store: HistoryStore,
+    symbol: String,
+    from: SystemTime,
+    to: SystemTime,
+}
+
+/// Resolves the symbol, store and time range of a history request
+///
+/// Returns:
+///   The resolved request, or the error response to send:
+///   HTTP 404 if the symbol is not indexed
+///   HTTP 503 if the index history is disabled
+///   HTTP 400 if a time is invalid or the range is empty
+fn resolve_request(
+    data: &AppState,
+    symbol: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> std::result::Result<HistoryRequest, Box<HttpResponse>> {
+    let Some(resolved) = resolve_symbol(&data.symbols, symbol) else {
+        return Err(Box::new(HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("Unknown symbol: {}", symbol),
+            "symbols": data.symbols,
+        }))));
+    };
+    let Some(store) = data.aggregator.history() else {
+        return Err(Box::new(
+            HttpResponse::ServiceUnavailable()
+                .json(serde_json::json!({ "error": "Index history is disabled" })),
+        ));
+    };
+
+    let bad_request = |error: String| {
+        Box::new(HttpResponse::BadRequest().json(serde_json::json!({ "error": error })))
+    };
+    let to = match to {
+        Some(to) => parse_time(to).map_err(bad_request)?,
+        None => SystemTime::now(),
+    };
+    let from = match from {
+        Some(from) => parse_time(from).map_err(bad_request)?,
+        None => to.checked_sub(DEFAULT_WINDOW).unwrap_or(UNIX_EPOCH),
+    };
+    if from >= to {
+        return Err(bad_request("`from` must be before `to`".to_string()));
+    }
+
+    Ok(HistoryRequest {
+        store: store.clone(),
+        symbol: resolved.to_string(),
+        from,
+        to,
+    })
+}
+
+/// Parses an RFC 3339 time such as "2025-04-08T09:32:35Z"
+fn parse_time(value: &str) -> std::result::Result<SystemTime, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(SystemTime::from)
+        .map_err(|e| format!("Invalid time {}: {}", value, e))
+}
+
+/// Builds the response of a history query run on the blocking thread pool
+///
+/// Returns:
+///   HTTP 200 with the JSON result on success
+///   HTTP 500 if the database query failed
+fn query_response<T: Serialize>(
+    result: std::result::Result<Result<T>, actix_web::error::BlockingError>,
+) -> HttpResponse {
+    match result {
+        Ok(Ok(value)) => HttpResponse::Ok().json(value),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to read index history: {}", e),
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to read index history: {}", e),
+        })),
+    }
+}
+
+/// HTTP handler for the /history/{symbol} endpoint
+///
+/// Serves the indices of a symbol computed in a time range with their
+/// venue prices, e.g.
+/// `/history/BTC-USDT?from=2025-04-08T09:00:00Z&to=2025-04-08T10:00:00Z`.
+///
+/// Returns:
+///   HTTP 200 with the JSON list of GlobalPriceIndex, oldest first
+///   HTTP 400 if a time is invalid or the range is empty
+///   HTTP 404 if the symbol is not indexed
+///   HTTP 503 if the index history is disabled
+pub async fn get_history(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> impl Responder {
+    let request = match resolve_request(&data, &path, query.from.as_deref(), query.to.as_deref()) {
+        Ok(request) => request,
+        Err(response) => return *response,
+    };
+    let max_results = data.settings().history.max_results;
+    let limit = query.limit.unwrap_or(max_results).min(max_results);
+
+    query_response(
+        web::block(move || {
+            request
+                .store
+                .range(&request.symbol, request.from, request.to, limit)
+        })
+        .await,
+    )
+}
+
+/// HTTP handler for the /history/{symbol}/candles endpoint
+///
+/// Serves OHLC candles of a symbol's index built from the indices stored
+/// in a time range, e.g. `/history/BTC-USDT/candles?resolution=5m`.
+///
+/// Returns:
+///   HTTP 200 with the JSON list of candles, oldest first
+///   HTTP 400 if the resolution or a time is invalid, or the range is empty
+///   HTTP 404 if the symbol is not indexed
+///   HTTP 503 if the index history is disabled
+pub async fn get_candles(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<CandleQuery>,
+) -> impl Responder {
+    let request = match resolve_request(&data, &path, query.from.as_deref(), query.to.as_deref()) {
+        Ok(request) => request,
+        Err(response) => return *response,
+    };
+    let resolution = query.resolution;
+
+    query_response(
+        web::block(move || {
+            request
+                .store
+                .candles(&request.symbol, request.from, request.to, resolution)
+        })
+        .await,
+    )
+}
@@ -1,17 +1,32 @@
-//! Global BTC/USDT Price Index API
+//! Global Price Index API
 //!
-//! This library provides functionality for aggregating and serving real-time BTC/USDT price data
-//! from multiple cryptocurrency exchanges.
+//! This library provides functionality for aggregating and serving real-time price data
+//! for each configured trading pair (BTC/USDT, ETH/USDT, ...) from multiple cryptocurrency
+//! exchanges.
 
+pub mod aggregator;
 pub mod api;
+pub mod capture;
+pub mod cli;
 pub mod config;
+pub mod engine;
 pub mod error;
 pub mod exchanges;
+pub mod health;
+pub mod history;
+pub mod logging;
+pub mod metrics;
 pub mod models;
+pub mod outlier;
+pub mod reload;
+pub mod replay;
+pub mod stream;
+pub mod weighting;
 
 // Re-export commonly used items
 pub use api::start_server;
 pub use config::SETTINGS;
+pub use engine::PriceIndexEngine;
 pub use error::{PriceIndexError, Result};
 pub use models::{ExchangePrice, GlobalPriceIndex, OrderBook};
 
@@ -0,0 +1,61 @@
+// Structured logging setup
+use crate::config::{LogFormat, Logging};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Builds the event filter of a logging configuration
+///
+/// The RUST_LOG environment variable takes precedence over the configured
+/// filter. Invalid directives fall back to the "info" level.
+pub fn env_filter(logging: &Logging) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&logging.filter))
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "Invalid log filter {:?}: {}, using \"info\"",
+                logging.filter, e
+            );
+            EnvFilter::new("info")
+        })
+}
+
+/// Builds the log subscriber of a logging configuration
+///
+/// Events are filtered with `env_filter` and written to `writer` as pretty
+/// text or JSON lines, with the fields of the spans they occur in, such as
+/// the exchange and symbol of a WebSocket connection or the symbol of a
+/// `/global-price` request.
+pub fn build_subscriber<W>(logging: &Logging, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(env_filter(logging))
+        .with_writer(writer);
+    match logging.format {
+        LogFormat::Pretty => Box::new(builder.pretty().finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .finish(),
+        ),
+    }
+}
+
+/// Installs the global log subscriber as configured in `[logging]`
+///
+/// Logs are written to stdout, see `build_subscriber`. The records of the
+/// `log` crate, e.g. the actix access log, are forwarded to it. Calling it
+/// again once a subscriber is installed has no effect.
+pub fn init_logging(logging: &Logging) {
+    if build_subscriber(logging, std::io::stdout)
+        .try_init()
+        .is_err()
+    {
+        tracing::debug!("Log subscriber already installed");
+    }
+}
@@ -1,35 +1,60 @@
-//! Global BTC/USDT Price Index API Binary
+//! Global Price Index API Binary
 //!
-//! This is the main entry point for the Global BTC/USDT Price Index API server.
+//! This is the main entry point for the Global Price Index API server.
 
 use actix_files as fs;
 use actix_web::{middleware, App, HttpServer};
-use futures::future::try_join;
-use global_price_index::{config, api::start_server};
+use clap::Parser;
+use futures::future::{join, try_join};
+use global_price_index::{
+    api::{shutdown_signal, start_server},
+    cli::{check_config, Cli, Command},
+    config,
+    logging::init_logging,
+};
+use tracing::info;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize config (happens implicitly via lazy_static)
-    // Log configuration values
-    println!("Starting Global BTC/USDT Price Index API ...");
-    println!("API server address: {}", config::get_api_server_addr());
-    println!("Frontend server address: {}", config::get_frontend_server_addr());
-    println!("Binance WebSocket URL: {}", config::get_binance_ws_url());
+    // Load the configuration from the file, environment and command line, then logging from it
+    let cli = Cli::parse();
+    if let Some(Command::CheckConfig) = cli.command {
+        let valid = check_config(
+            &cli.config_sources(),
+            &mut std::io::stdout(),
+            &mut std::io::stderr(),
+        )?;
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+    if let Err(e) = config::init_settings(cli.config_sources()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let settings = config::get_settings();
+    init_logging(&settings.logging);
 
-    // Get frontend paths from config
-    let frontend_dir = config::get_frontend_dir();
-    let templates_dir = config::get_templates_dir();
-    let static_dir = config::get_static_dir();
+    // Log configuration values
+    info!(
+        symbols = %settings.index.symbols.join(", "),
+        api_addr = %settings.server.api_addr(),
+        frontend_addr = %settings.server.frontend_addr(),
+        binance_ws_url = %settings.exchange.binance.ws_url,
+        "Starting Global Price Index API"
+    );
 
     // Set up paths for serving
-    let templates_path = format!("./{}/{}", frontend_dir, templates_dir);
-    let static_path = format!("./{}/{}", frontend_dir, static_dir);
+    let frontend = &settings.frontend;
+    let templates_path = format!("./{}/{}", frontend.dir, frontend.templates_dir);
+    let static_path = format!("./{}/{}", frontend.dir, frontend.static_dir);
+    let frontend_addr = settings.server.frontend_addr();
 
     // Start the API server
-    let api_server = start_server().await?;
+    let drain_timeout_secs = settings.server.drain_timeout_secs;
+    let api = start_server(settings, config::get_config_sources()).await?;
+    let api_handle = api.server.handle();
 
     // Start the static file server
-    println!("Starting static file server...");
+    info!("Starting static file server");
     let static_server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
@@ -37,21 +62,40 @@ async fn main() -> std::io::Result<()> {
             .service(
                 fs::Files::new("/static", &static_path)
                     .show_files_listing()
-                    .use_last_modified(true)
+                    .use_last_modified(true),
             )
             // Serve index.html from templates directory
             .service(
                 fs::Files::new("/", &templates_path)
                     .index_file("index.html")
                     .prefer_utf8(true)
-                    .use_last_modified(true)
+                    .use_last_modified(true),
             )
     })
-    .bind(config::get_frontend_server_addr())?
+    .shutdown_timeout(drain_timeout_secs)
+    .disable_signals()
+    .bind(frontend_addr)?
     .run();
+    let static_handle = static_server.handle();
+
+    // Run both servers until a shutdown signal, then let them drain the requests in flight
+    let servers = try_join(api.server, static_server);
+    tokio::pin!(servers);
+    tokio::select! {
+        result = &mut servers => {
+            result?;
+        }
+        signal = shutdown_signal() => {
+            info!(signal, drain_timeout_secs, "Shutting down");
+            // The servers process the stop commands, so they are polled while stopping
+            let stop = join(api_handle.stop(true), static_handle.stop(true));
+            let (result, _) = join(&mut servers, stop).await;
+            result?;
+        }
+    }
 
-    // Run both servers
-    try_join(api_server, static_server).await?;
+    // Close the exchange feeds and write the pending index history
+    api.state.shutdown().await;
 
     Ok(())
 }
@@ -0,0 +1,217 @@
+// Prometheus metrics of feed health and index quality
+
+use crate::error::PriceIndexError;
+use crate::models::GlobalPriceIndex;
+use actix_web::{http::header, HttpResponse, Responder};
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::error;
+
+/// Buckets of the exchange fetch latency histogram, in seconds
+const FETCH_DURATION_BUCKETS: [f64; 10] =
+    [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+lazy_static! {
+    /// Metrics of the running service, served by the /metrics endpoint
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+/// Metrics collects the feed health and index quality measurements
+///
+/// The metrics are:
+/// - `price_index_exchange_fetch_duration_seconds`: latency of every read of
+///   an exchange price, by exchange and symbol
+/// - `price_index_errors_total`: errors by exchange, symbol and
+///   `PriceIndexError` variant
+/// - `price_index_websocket_reconnects_total`: WebSocket reconnections by
+///   exchange and symbol
+/// - `price_index_book_age_seconds`: time since the last update of the book
+///   of each exchange market, as of the scrape
+/// - `price_index_feed_latency_seconds`: time from the exchange timestamp of
+///   the last book update of each exchange market to its local receipt
+/// - `price_index_venue_deviation_bps`: deviation of each venue's mid price
+///   from the index of its symbol, in basis points, excluded venues included
+/// - `price_index_value`: latest index of each symbol
+pub struct Metrics {
+    registry: Registry,
+    fetch_duration: HistogramVec,
+    errors: IntCounterVec,
+    reconnects: IntCounterVec,
+    book_age: GaugeVec,
+    book_updated: Mutex<HashMap<(String, String), SystemTime>>,
+    feed_latency: GaugeVec,
+    venue_deviation: GaugeVec,
+    index_value: GaugeVec,
+}
+
+impl Metrics {
+    /// Creates and registers every metric
+    fn new() -> Self {
+        let fetch_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "price_index_exchange_fetch_duration_seconds",
+                "Latency of exchange price reads",
+            )
+            .buckets(FETCH_DURATION_BUCKETS.to_vec()),
+            &["exchange", "symbol"],
+        )
+        .unwrap();
+        let errors = IntCounterVec::new(
+            Opts::new("price_index_errors_total", "Errors by exchange and kind"),
+            &["exchange", "symbol", "error"],
+        )
+        .unwrap();
+        let reconnects = IntCounterVec::new(
+            Opts::new(
+                "price_index_websocket_reconnects_total",
+                "WebSocket reconnections by exchange",
+            ),
+            &["exchange", "symbol"],
+        )
+        .unwrap();
+        let book_age = GaugeVec::new(
+            Opts::new(
+                "price_index_book_age_seconds",
+                "Seconds since the last update of an exchange order book",
+            ),
+            &["exchange", "symbol"],
+        )
+        .unwrap();
+        let feed_latency = GaugeVec::new(
+            Opts::new(
+                "price_index_feed_latency_seconds",
+                "Seconds from the exchange timestamp of the last book update to its receipt",
+            ),
+            &["exchange", "symbol"],
+        )
+        .unwrap();
+        let venue_deviation = GaugeVec::new(
+            Opts::new(
+                "price_index_venue_deviation_bps",
+                "Deviation of a venue mid price from the index, in basis points",
+            ),
+            &["exchange", "symbol"],
+        )
+        .unwrap();
+        let index_value = GaugeVec::new(
+            Opts::new("price_index_value", "Latest global price index"),
+            &["symbol"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(fetch_duration.clone())).unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+        registry.register(Box::new(reconnects.clone())).unwrap();
+        registry.register(Box::new(book_age.clone())).unwrap();
+        registry.register(Box::new(feed_latency.clone())).unwrap();
+        registry
+            .register(Box::new(venue_deviation.clone()))
+            .unwrap();
+        registry.register(Box::new(index_value.clone())).unwrap();
+
+        Self {
+            registry,
+            fetch_duration,
+            errors,
+            reconnects,
+            book_age,
+            book_updated: Mutex::new(HashMap::new()),
+            feed_latency,
+            venue_deviation,
+            index_value,
+        }
+    }
+
+    /// Records the latency of a read of an exchange price
+    pub fn record_fetch(&self, exchange: &str, symbol: &str, duration: Duration) {
+        self.fetch_duration
+            .with_label_values(&[exchange, symbol])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Counts an error of an exchange market by variant
+    pub fn record_error(&self, exchange: &str, symbol: &str, error: &PriceIndexError) {
+        self.errors
+            .with_label_values(&[exchange, symbol, error.kind()])
+            .inc();
+    }
+
+    /// Counts a WebSocket reconnection of an exchange market
+    pub fn record_reconnect(&self, exchange: &str, symbol: &str) {
+        self.reconnects.with_label_values(&[exchange, symbol]).inc();
+    }
+
+    /// Records the time of the last update of an exchange market's book
+    pub fn record_book_update(&self, exchange: &str, symbol: &str, updated_at: SystemTime) {
+        self.book_updated
+            .lock()
+            .unwrap()
+            .insert((exchange.to_string(), symbol.to_string()), updated_at);
+    }
+
+    /// Records the feed latency of the last update of an exchange market's book
+    pub fn record_feed_latency(&self, exchange: &str, symbol: &str, latency: Duration) {
+        self.feed_latency
+            .with_label_values(&[exchange, symbol])
+            .set(latency.as_secs_f64());
+    }
+
+    /// Records a computed index and the deviation of each venue from it
+    pub fn record_index(&self, index: &GlobalPriceIndex) {
+        self.index_value
+            .with_label_values(&[&index.symbol])
+            .set(index.price.to_f64().unwrap_or(0.0));
+        if index.price <= Decimal::ZERO {
+            return;
+        }
+
+        for price in &index.exchange_prices {
+            let deviation = (price.mid_price - index.price) / index.price * Decimal::from(10_000);
+            self.venue_deviation
+                .with_label_values(&[&price.exchange, &index.symbol])
+                .set(deviation.to_f64().unwrap_or(0.0));
+        }
+    }
+
+    /// Removes the index of a symbol no longer computed, e.g. because all its venues are stale
+    pub fn clear_index(&self, symbol: &str) {
+        let _ = self.index_value.remove_label_values(&[symbol]);
+    }
+
+    /// Renders every metric in the Prometheus text format
+    ///
+    /// The book ages are computed as of now.
+    pub fn render(&self) -> String {
+        let now = SystemTime::now();
+        for ((exchange, symbol), updated_at) in self.book_updated.lock().unwrap().iter() {
+            let age = now.duration_since(*updated_at).unwrap_or(Duration::ZERO);
+            self.book_age
+                .with_label_values(&[exchange, symbol])
+                .set(age.as_secs_f64());
+        }
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// HTTP handler for the /metrics endpoint
+///
+/// Returns:
+///   HTTP 200 with every metric in the Prometheus text format
+pub async fn get_metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, TextEncoder::new().format_type()))
+        .body(METRICS.render())
+}
@@ -1,43 +1,405 @@
 // OrderBook, BidAsk, MidPrice
+use crate::config::{
+    get_min_venues, get_outlier_filter, get_price_weighting, Clock, MidPriceCalculator,
+    MidPriceMethod, OutlierFilter, PriceWeighting,
+};
+use crate::outlier::{filter_outliers, filter_stale};
+use crate::weighting::{max_weight, strategy, weighted_average};
+use rust_decimal::prelude::FromPrimitive;
+pub use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of decimal places the global price index is rounded to
+///
+/// Venue mid prices are exact, only the weighted average needs rounding.
+pub const INDEX_SCALE: u32 = 8;
+
+/// Decimal precision of the prices and quantities quoted by a venue
+///
+/// The price scale is the number of decimal places of the venue's tick size
+/// (2 for a 0.01 tick), the quantity scale that of its lot size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Precision {
+    pub price_scale: u32,
+    pub quantity_scale: u32,
+}
+
+impl Precision {
+    /// Creates a precision from the number of decimal places of prices and quantities
+    pub fn new(price_scale: u32, quantity_scale: u32) -> Self {
+        Self {
+            price_scale,
+            quantity_scale,
+        }
+    }
+
+    /// Returns the tick size, the smallest price increment
+    pub fn tick_size(&self) -> Decimal {
+        Decimal::new(1, self.price_scale)
+    }
+
+    /// Returns the lot size, the smallest quantity increment
+    pub fn lot_size(&self) -> Decimal {
+        Decimal::new(1, self.quantity_scale)
+    }
+
+    /// Expresses a price with exactly the venue's price scale
+    pub fn price(&self, price: Decimal) -> Decimal {
+        let mut price = price.round_dp(self.price_scale);
+        price.rescale(self.price_scale);
+        price
+    }
+
+    /// Expresses a quantity with exactly the venue's quantity scale
+    pub fn quantity(&self, quantity: Decimal) -> Decimal {
+        let mut quantity = quantity.round_dp(self.quantity_scale);
+        quantity.rescale(self.quantity_scale);
+        quantity
+    }
+
+    /// Expresses an order with the venue's price and quantity scales
+    pub fn order(&self, order: Order) -> Order {
+        Order {
+            price: self.price(order.price),
+            quantity: self.quantity(order.quantity),
+        }
+    }
+
+    /// Expresses a list of orders with the venue's price and quantity scales
+    pub fn orders(&self, orders: Vec<Order>) -> Vec<Order> {
+        orders.into_iter().map(|order| self.order(order)).collect()
+    }
+}
 
 /// Represents a single order in an order book with price and quantity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Prices and quantities are exact decimals and are serialized as strings
+/// so no precision is lost in JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Order {
-    pub price: f64,
-    pub quantity: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub quantity: Decimal,
+}
+
+/// Side of a market order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Buys from the asks
+    Buy,
+    /// Sells into the bids
+    Sell,
 }
 
 /// Represents an order book with bids (buy orders), asks (sell orders), and a timestamp
+///
+/// `timestamp` is the local time the book's last update was received, and
+/// `exchange_timestamp` the time the venue stamped it with, if it sends one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBook {
     pub bids: Vec<Order>, // [price, quantity]
     pub asks: Vec<Order>, // [price, quantity]
     #[serde(with = "timestamp_serde")]
     pub timestamp: SystemTime,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_timestamp_serde"
+    )]
+    pub exchange_timestamp: Option<SystemTime>,
+}
+
+/// Order book that keeps each side sorted by price
+///
+/// Levels are stored in a BTreeMap keyed by decimal price, so adding,
+/// updating or removing a level is O(log n) and levels compare exactly. The best bid and best ask are
+/// cached after each change and read in O(1).
+#[derive(Debug, Clone)]
+pub struct PriceLevelBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    best_bid: Option<Order>,
+    best_ask: Option<Order>,
+    pub timestamp: SystemTime,
+    /// Time the venue stamped the last update with, reset by `replace`
+    pub exchange_timestamp: Option<SystemTime>,
+}
+
+impl Default for PriceLevelBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PriceLevelBook {
+    /// Creates an empty book
+    pub fn new() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            best_bid: None,
+            best_ask: None,
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        }
+    }
+
+    /// Creates a book from snapshot levels
+    ///
+    /// Levels with a non-positive quantity are ignored, and duplicate prices
+    /// keep the last quantity given.
+    pub fn from_levels(bids: &[Order], asks: &[Order]) -> Self {
+        let mut book = Self::new();
+        book.apply_updates(bids, asks);
+        book
+    }
+
+    /// Replaces the whole content of the book with snapshot levels
+    ///
+    /// The exchange timestamp is cleared, callers set the one of the snapshot
+    /// if the venue sends it.
+    pub fn replace(&mut self, bids: &[Order], asks: &[Order]) {
+        self.bids.clear();
+        self.asks.clear();
+        self.exchange_timestamp = None;
+        self.apply_updates(bids, asks);
+    }
+
+    /// Sets the quantity of a bid level; a zero quantity removes the level
+    pub fn update_bid(&mut self, price: Decimal, quantity: Decimal) {
+        Self::update_level(&mut self.bids, price, quantity);
+        self.best_bid = self.bids.last_key_value().map(|(price, quantity)| Order {
+            price: *price,
+            quantity: *quantity,
+        });
+    }
+
+    /// Sets the quantity of an ask level; a zero quantity removes the level
+    pub fn update_ask(&mut self, price: Decimal, quantity: Decimal) {
+        Self::update_level(&mut self.asks, price, quantity);
+        self.best_ask = self.asks.first_key_value().map(|(price, quantity)| Order {
+            price: *price,
+            quantity: *quantity,
+        });
+    }
+
+    /// Applies incremental level updates to both sides of the book
+    ///
+    /// Each update sets the quantity of its price level: new levels are added,
+    /// existing ones are updated, and levels with zero quantity are removed.
+    pub fn apply_updates(&mut self, bids: &[Order], asks: &[Order]) {
+        for bid in bids {
+            self.update_bid(bid.price, bid.quantity);
+        }
+        for ask in asks {
+            self.update_ask(ask.price, ask.quantity);
+        }
+        self.timestamp = SystemTime::now();
+    }
+
+    /// Keeps only the best `depth` levels on each side of the book
+    ///
+    /// Venues streaming a fixed-depth book expect levels pushed out of
+    /// that depth to be dropped by the client.
+    pub fn truncate(&mut self, depth: usize) {
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+        if depth == 0 {
+            self.best_bid = None;
+            self.best_ask = None;
+        }
+    }
+
+    /// Returns the highest bid
+    pub fn best_bid(&self) -> Option<&Order> {
+        self.best_bid.as_ref()
+    }
+
+    /// Returns the lowest ask
+    pub fn best_ask(&self) -> Option<&Order> {
+        self.best_ask.as_ref()
+    }
+
+    /// Returns the number of bid levels
+    pub fn bid_depth(&self) -> usize {
+        self.bids.len()
+    }
+
+    /// Returns the number of ask levels
+    pub fn ask_depth(&self) -> usize {
+        self.asks.len()
+    }
+
+    /// Returns true if the best bid is at or above the best ask
+    pub fn is_crossed(&self) -> bool {
+        match (&self.best_bid, &self.best_ask) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
+    /// Returns the bids from highest to lowest price
+    pub fn bids(&self) -> impl Iterator<Item = Order> + '_ {
+        self.bids.iter().rev().map(|(price, quantity)| Order {
+            price: *price,
+            quantity: *quantity,
+        })
+    }
+
+    /// Returns the asks from lowest to highest price
+    pub fn asks(&self) -> impl Iterator<Item = Order> + '_ {
+        self.asks.iter().map(|(price, quantity)| Order {
+            price: *price,
+            quantity: *quantity,
+        })
+    }
+
+    /// Copies the book into an OrderBook with bids descending and asks ascending
+    pub fn to_order_book(&self) -> OrderBook {
+        OrderBook {
+            bids: self.bids().collect(),
+            asks: self.asks().collect(),
+            timestamp: self.timestamp,
+            exchange_timestamp: self.exchange_timestamp,
+        }
+    }
+
+    /// Sets or removes a single level on one side of the book
+    fn update_level(levels: &mut BTreeMap<Decimal, Decimal>, price: Decimal, quantity: Decimal) {
+        if quantity > Decimal::ZERO {
+            levels.insert(price, quantity);
+        } else {
+            levels.remove(&price);
+        }
+    }
+}
+
+/// Liquidity of a venue's market, used by the liquidity-based weighting strategies
+///
+/// Depths are quote notionals (price × quantity) summed over both sides of
+/// the book, the volume is the trailing 24h traded volume in the base
+/// currency. Values a venue did not provide are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Liquidity {
+    /// Notional quoted at the best bid and best ask
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub top_of_book_depth: Option<Decimal>,
+    /// Notional quoted within the configured band around the mid price
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub depth_within_bps: Option<Decimal>,
+    /// Trailing 24h traded volume
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub volume: Option<Decimal>,
 }
 
 /// Represents a price from a specific exchange at a specific time
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExchangePrice {
     pub exchange: String,
-    pub mid_price: f64,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub mid_price: Decimal,
+    /// Local time the last update of the order book the price was taken from was received
     #[serde(with = "timestamp_serde")]
     pub timestamp: SystemTime,
+    /// Time the venue stamped the book's last update with, if it sends one
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_timestamp_serde"
+    )]
+    pub exchange_timestamp: Option<SystemTime>,
+    /// Feed latency of the book's last update, from the venue's timestamp to
+    /// its local receipt, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Liquidity of the venue's market when the price was taken
+    #[serde(default)]
+    pub liquidity: Liquidity,
+}
+
+impl ExchangePrice {
+    /// Returns the time of the price's book update on a clock
+    ///
+    /// The venue's clock falls back to the local one for venues that do not
+    /// stamp their updates.
+    pub fn time(&self, clock: Clock) -> SystemTime {
+        match clock {
+            Clock::Local => self.timestamp,
+            Clock::Exchange => self.exchange_timestamp.unwrap_or(self.timestamp),
+        }
+    }
+}
+
+/// Why a venue price was left out of the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// The price is zero or negative
+    NonPositivePrice,
+    /// The price is further from the median than the maximum percent deviation
+    MaxDeviation,
+    /// The price is further from the median than the median absolute deviation threshold
+    MedianAbsoluteDeviation,
+    /// The venue's book was not updated within the maximum age
+    Stale,
+}
+
+/// A venue price left out of the index, with the reason it was rejected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExcludedPrice {
+    pub exchange: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub mid_price: Decimal,
+    pub reason: ExclusionReason,
 }
 
 /// Represents the global price index aggregated from multiple exchanges
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalPriceIndex {
-    pub price: f64,
+    /// Canonical symbol of the indexed instrument, e.g. "BTC/USDT"
+    pub symbol: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price: Decimal,
     #[serde(with = "timestamp_serde")]
     pub timestamp: SystemTime,
     pub exchange_prices: Vec<ExchangePrice>,
+    /// Venue prices left out of the weighted average
+    #[serde(default)]
+    pub excluded: Vec<ExcludedPrice>,
+    /// Number of venues the index is weighted from: fresh, positive and
+    /// not rejected as outliers
+    #[serde(default)]
+    pub venues: usize,
+    /// Venue quorum of a valid index
+    #[serde(default)]
+    pub min_venues: usize,
+    /// Whether at least `min_venues` venues priced the index. An index
+    /// short of its quorum is reported but not served as a valid price.
+    #[serde(default)]
+    pub quorum_met: bool,
 }
 
 /// Custom serialization/deserialization module for SystemTime timestamps
-mod timestamp_serde {
+pub(crate) mod timestamp_serde {
     use super::*;
     use chrono::{DateTime, Utc};
     use serde::{Deserializer, Serializer};
@@ -66,25 +428,76 @@ mod timestamp_serde {
     }
 }
 
+/// Custom serialization/deserialization module for optional SystemTime timestamps
+pub(crate) mod optional_timestamp_serde {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    /// Serializes a SystemTime like `timestamp_serde`, or None as null
+    pub fn serialize<S>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(time) => timestamp_serde::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional ISO 8601 string to SystemTime
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "timestamp_serde")] SystemTime);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(time)| time))
+    }
+}
+
+/// Converts a Unix timestamp in milliseconds, as sent by most venues, to SystemTime
+pub fn timestamp_from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
 impl OrderBook {
+    /// Returns the feed latency of the book's last update
+    ///
+    /// The latency is the time from the venue's timestamp to the local
+    /// receipt of the update. Venue clocks running ahead of the local one
+    /// give a zero latency.
+    ///
+    /// Returns:
+    ///   The latency, None if the venue does not stamp its updates
+    pub fn latency(&self) -> Option<Duration> {
+        self.exchange_timestamp.map(|exchange_timestamp| {
+            self.timestamp
+                .duration_since(exchange_timestamp)
+                .unwrap_or(Duration::ZERO)
+        })
+    }
+
     /// Calculates the mid-price from the order book as the average of best bid and best ask
     ///
+    /// The result is exact, no rounding is applied.
+    ///
     /// Returns:
     /// - Some(mid_price): If calculation successful
     /// - None: If the order book is empty, contains invalid prices, or has an invalid spread
-    pub fn calculate_mid_price(&self) -> Option<f64> {
+    pub fn calculate_mid_price(&self) -> Option<Decimal> {
         if self.bids.is_empty() || self.asks.is_empty() {
             return None;
         }
 
         // Get the best bid (highest price) and best ask (lowest price)
         let best_bid = self.bids[0].price;
-        if best_bid <= 0.0 {
+        if best_bid <= Decimal::ZERO {
             return None;
         }
 
         let best_ask = self.asks[0].price;
-        if best_ask <= 0.0 {
+        if best_ask <= Decimal::ZERO {
             return None;
         }
 
@@ -94,99 +507,335 @@ impl OrderBook {
         }
 
         // Calculate mid price as average of best bid and best ask
-        let mid_price = (best_bid + best_ask) / 2.0;
+        // This is exact: the mid has at most one more decimal place than the tick,
+        // and keeps the tick's decimal places when the spread is an even number of ticks
+        let scale = best_bid.scale().max(best_ask.scale());
+        let mut mid_price = (best_bid + best_ask) / Decimal::TWO;
+        if mid_price.round_dp(scale) == mid_price {
+            mid_price.rescale(scale);
+        } else {
+            mid_price.rescale(scale + 1);
+        }
+        Some(mid_price)
+    }
 
-        // Round to 2 decimal places
-        Some((mid_price * 100.0).round() / 100.0)
+    /// Calculates the mid-price with the configured calculator
+    ///
+    /// Args:
+    ///   calculator: Calculation method and its parameters
+    ///
+    /// Returns:
+    /// - Some(mid_price): If calculation successful
+    /// - None: If the book has no valid top of book, or cannot supply the
+    ///   calculator's size
+    pub fn calculate_mid_price_with(&self, calculator: &MidPriceCalculator) -> Option<Decimal> {
+        match calculator.method {
+            MidPriceMethod::TopOfBook => self.calculate_mid_price(),
+            MidPriceMethod::Vwap => {
+                self.calculate_vwap_mid_price(Decimal::from_f64(calculator.vwap_notional)?)
+            }
+            MidPriceMethod::Microprice => self.calculate_microprice(),
+            MidPriceMethod::Impact => {
+                self.calculate_impact_mid_price(Decimal::from_f64(calculator.impact_size)?)
+            }
+        }
+    }
+
+    /// Calculates a volume-weighted mid-price resistant to dust-size top levels
+    ///
+    /// This function:
+    /// 1. Walks each side of the book from the best level until `notional` is filled
+    /// 2. Computes the volume-weighted average price of each side's fill
+    /// 3. Returns the average of the two
+    ///
+    /// A side quoting less than `notional` is averaged over all its levels.
+    /// The result is rounded to INDEX_SCALE decimal places.
+    ///
+    /// Args:
+    ///   notional: Quote notional filled on each side
+    ///
+    /// Returns:
+    /// - Some(mid_price): If calculation successful
+    /// - None: If the book has no valid top of book or `notional` is not positive
+    pub fn calculate_vwap_mid_price(&self, notional: Decimal) -> Option<Decimal> {
+        self.calculate_mid_price()?;
+        if notional <= Decimal::ZERO {
+            return None;
+        }
+
+        let bid = fill_side(&self.bids, Fill::Notional(notional))?;
+        let ask = fill_side(&self.asks, Fill::Notional(notional))?;
+        Some(((bid.average_price() + ask.average_price()) / Decimal::TWO).round_dp(INDEX_SCALE))
+    }
+
+    /// Calculates the microprice, the best bid and ask weighted by the opposite side's size
+    ///
+    /// microprice = (best_bid × ask_size + best_ask × bid_size) / (bid_size + ask_size)
+    ///
+    /// The microprice leans toward the side with less size, where the next
+    /// trade is more likely to move the price. The result is rounded to
+    /// INDEX_SCALE decimal places.
+    ///
+    /// Returns:
+    /// - Some(microprice): If calculation successful
+    /// - None: If the book has no valid top of book or no size at its best levels
+    pub fn calculate_microprice(&self) -> Option<Decimal> {
+        self.calculate_mid_price()?;
+        let (best_bid, best_ask) = (&self.bids[0], &self.asks[0]);
+        let size = best_bid.quantity + best_ask.quantity;
+        if size <= Decimal::ZERO {
+            return None;
+        }
+
+        let microprice =
+            (best_bid.price * best_ask.quantity + best_ask.price * best_bid.quantity) / size;
+        Some(microprice.round_dp(INDEX_SCALE))
+    }
+
+    /// Calculates the average execution price of a market order
+    ///
+    /// A buy order walks the asks and a sell order the bids, from the best
+    /// level until `quantity` is filled. The result is rounded to
+    /// INDEX_SCALE decimal places.
+    ///
+    /// Args:
+    ///   side: Side of the market order
+    ///   quantity: Base quantity of the market order
+    ///
+    /// Returns:
+    /// - Some(price): If calculation successful
+    /// - None: If `quantity` is not positive or exceeds the quantity quoted on that side
+    pub fn calculate_impact_price(&self, side: Side, quantity: Decimal) -> Option<Decimal> {
+        if quantity <= Decimal::ZERO {
+            return None;
+        }
+        let levels = match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        };
+
+        let fill = fill_side(levels, Fill::Quantity(quantity))?;
+        if fill.quantity < quantity {
+            return None;
+        }
+        Some(fill.average_price().round_dp(INDEX_SCALE))
+    }
+
+    /// Calculates the mid-price as the average of the buy and sell impact prices of a size
+    ///
+    /// Args:
+    ///   quantity: Base quantity traded on each side
+    ///
+    /// Returns:
+    /// - Some(mid_price): If calculation successful
+    /// - None: If the book has no valid top of book or either side cannot fill `quantity`
+    pub fn calculate_impact_mid_price(&self, quantity: Decimal) -> Option<Decimal> {
+        self.calculate_mid_price()?;
+        let buy = self.calculate_impact_price(Side::Buy, quantity)?;
+        let sell = self.calculate_impact_price(Side::Sell, quantity)?;
+        Some(((buy + sell) / Decimal::TWO).round_dp(INDEX_SCALE))
+    }
+
+    /// Measures the liquidity quoted in the order book
+    ///
+    /// Args:
+    ///   depth_bps: Band around the mid price counted in `depth_within_bps`, in basis points
+    ///
+    /// Returns:
+    ///   The notional quoted at the best bid and ask and within the band, empty if
+    ///   the book has no valid mid price. The volume is not part of the book and
+    ///   is left empty.
+    pub fn liquidity(&self, depth_bps: u32) -> Liquidity {
+        let Some(mid_price) = self.calculate_mid_price() else {
+            return Liquidity::default();
+        };
+
+        let notional = |order: &Order| order.price * order.quantity;
+        let band = mid_price * Decimal::from(depth_bps) / Decimal::from(10_000);
+        let depth_within_bps = self
+            .bids
+            .iter()
+            .filter(|bid| bid.price >= mid_price - band)
+            .chain(self.asks.iter().filter(|ask| ask.price <= mid_price + band))
+            .map(notional)
+            .sum();
+
+        Liquidity {
+            top_of_book_depth: Some(notional(&self.bids[0]) + notional(&self.asks[0])),
+            depth_within_bps: Some(depth_within_bps),
+            volume: None,
+        }
+    }
+}
+
+/// Amount to fill when walking one side of an order book
+#[derive(Debug, Clone, Copy)]
+enum Fill {
+    /// Quote notional
+    Notional(Decimal),
+    /// Base quantity
+    Quantity(Decimal),
+}
+
+/// Base quantity and quote notional filled on one side of an order book
+#[derive(Debug, Clone, Copy)]
+struct Filled {
+    quantity: Decimal,
+    notional: Decimal,
+}
+
+impl Filled {
+    /// Returns the volume-weighted average price of the fill
+    fn average_price(&self) -> Decimal {
+        self.notional / self.quantity
+    }
+}
+
+/// Walks the levels of one side of a book, best first, until `fill` is reached
+///
+/// The last level used is only partially taken. If the levels run out
+/// first, everything they quote is filled.
+///
+/// Returns:
+///   The filled quantity and notional, None if nothing could be filled
+fn fill_side(levels: &[Order], fill: Fill) -> Option<Filled> {
+    let mut filled = Filled {
+        quantity: Decimal::ZERO,
+        notional: Decimal::ZERO,
+    };
+    for level in levels {
+        if level.price <= Decimal::ZERO {
+            continue;
+        }
+        let quantity = match fill {
+            Fill::Notional(notional) => {
+                let remaining = notional - filled.notional;
+                if remaining <= Decimal::ZERO {
+                    break;
+                }
+                level.quantity.min(remaining / level.price)
+            }
+            Fill::Quantity(quantity) => {
+                let remaining = quantity - filled.quantity;
+                if remaining <= Decimal::ZERO {
+                    break;
+                }
+                level.quantity.min(remaining)
+            }
+        };
+        filled.quantity += quantity;
+        filled.notional += quantity * level.price;
+    }
+
+    if filled.quantity > Decimal::ZERO {
+        Some(filled)
+    } else {
+        None
     }
 }
 
 impl GlobalPriceIndex {
-    /// Creates a new GlobalPriceIndex from a vector of exchange prices
+    /// Creates a new GlobalPriceIndex for a symbol from a vector of exchange prices
     ///
     /// This function:
-    /// 1. Filters out invalid (non-positive) prices
-    /// 2. Applies time-based weighting to give recent prices more influence
-    /// 3. Calculates a weighted average based on price recency
-    /// 4. Falls back to simple average if weighting fails
+    /// 1. Filters out prices older than the maximum age of the weighting
+    ///    settings, see `filter_stale`, then invalid (non-positive) prices
+    ///    and outliers, see `filter_outliers`
+    /// 2. Weights the remaining prices with the strategy of the weighting
+    ///    settings, capping the share of each venue, see `weighted_average`
+    /// 3. Falls back to simple average if weighting fails
+    /// 4. Rounds the result to INDEX_SCALE decimal places
+    ///
+    /// Every venue price is listed in `exchange_prices`; those left out of
+    /// the average are also listed in `excluded` with the reason they were
+    /// rejected. No index is computed when every venue price is left out,
+    /// e.g. when all the venues are stale, rather than an index priced at 0.
+    ///
+    /// The index is valid when at least `min_venues` venues are left to
+    /// weight once stale, invalid and outlying prices are left out, the same
+    /// quorum `/ready` requires. Outliers are only rejected among at least
+    /// `min_venues` fresh, positive prices, as fewer venues cannot tell
+    /// which one is wrong. An index short of its quorum is still computed,
+    /// with `quorum_met` false.
     ///
-    /// The time-based weighting uses an exponential decay formula:
+    /// The default time-based weighting uses an exponential decay formula:
     /// weight = e^(-time_diff/decay_factor)
     ///
     /// Args:
+    ///   symbol: Canonical symbol of the instrument the prices are quoted for
     ///   exchange_prices: Vector of ExchangePrice objects from various exchanges
+    ///   weighting: Price weighting settings
+    ///   outlier_filter: Outlier rejection settings
+    ///   min_venues: Venue quorum of a valid index
     ///
     /// Returns:
-    ///   A new GlobalPriceIndex with the weighted average price
-    pub fn new(exchange_prices: Vec<ExchangePrice>) -> Self {
-        // Filter out invalid prices (keep only positive prices)
-        let valid_exchanges: Vec<&ExchangePrice> = exchange_prices
-            .iter()
-            .filter(|ep| ep.mid_price > 0.0)
-            .collect();
-
-        let average_price = if !valid_exchanges.is_empty() {
-            // Calculate weighted average based on timestamp recency
-            let now = SystemTime::now();
-
-            // -----------------------------------------------------------
-            // Time-based weighting system
-            // -----------------------------------------------------------
-            // Rather than using a simple average where all prices have
-            // equal influence, apply time-based weighting to give
-            // more recent prices higher influence on the final result.
-            // This makes the global price more responsive to recent market changes.
-            let mut weighted_sum = 0.0;
-            let mut total_weight = 0.0;
-
-            // The decay factor (in seconds) controls how quickly older prices lose influence
-            // With a decay factor of 300 seconds (5 minutes):
-            // - A price from right now gets weight = e^(-0/300) = 1.0 (100% influence)
-            // - A price 5 minutes old gets weight ≈ e^(-300/300) ≈ 0.368 (36.8% influence)
-            // - A price 10 minutes old gets weight ≈ e^(-600/300) ≈ 0.135 (13.5% influence)
-            // - A price 20 minutes old gets weight ≈ e^(-1200/300) ≈ 0.018 (1.8% influence)
-            let decay_factor = crate::config::get_decay_factor();
-
-            for exchange_price in &valid_exchanges {
-                // Calculate time difference between now and when the price was recorded
-                // This tells us how "old" or "stale" this particular price data is
-                let time_diff_secs = now
-                    .duration_since(exchange_price.timestamp)
-                    .unwrap_or_else(|_| std::time::Duration::from_secs(0))
-                    .as_secs() as f64;
-
-                // Apply exponential decay formula: weight = e^(-time_diff/decay_factor)
-                // This creates a smooth curve where:
-                // - Recent prices get weights close to 1.0
-                // - Older prices get weights approaching 0
-                let weight = (-time_diff_secs / decay_factor).exp();
-
-                // Add this price to our weighted sum
-                weighted_sum += exchange_price.mid_price * weight;
-                total_weight += weight;
-            }
+    ///   A new GlobalPriceIndex with the weighted average price, or None if
+    ///   no venue price is left to compute it from
+    pub fn new(
+        symbol: impl Into<String>,
+        exchange_prices: Vec<ExchangePrice>,
+        weighting: &PriceWeighting,
+        outlier_filter: &OutlierFilter,
+        min_venues: usize,
+    ) -> Option<Self> {
+        let now = SystemTime::now();
+        // Filter out prices of books that stopped updating, then invalid
+        // prices and prices too far from the other venues'
+        let (fresh, mut excluded) = filter_stale(&exchange_prices, weighting.max_age(), now);
+        let (valid_exchanges, outliers) = filter_outliers(&fresh, outlier_filter, min_venues);
+        excluded.extend(outliers);
+        if valid_exchanges.is_empty() {
+            return None;
+        }
+        let venues = valid_exchanges.len();
 
-            // Calculate the final weighted average
-            if total_weight > 0.0 {
-                weighted_sum / total_weight
-            } else {
-                // Fallback to simple average if weighting fails
-                // This should rarely happen but provides robustness
-                // It could occur in extreme cases like:
-                // 1. Clock skew causing future timestamps (negative time diff)
-                // 2. Extreme time differences causing weights to round to zero
-                // 3. Implementation bugs elsewhere in the codebase
-                valid_exchanges.iter().map(|ep| ep.mid_price).sum::<f64>()
-                    / valid_exchanges.len() as f64
-            }
-        } else {
-            0.0
-        };
+        // Rather than using a simple average where all prices have
+        // equal influence, weight each venue by recency or liquidity
+        let strategy = strategy(weighting);
+        let average_price = weighted_average(
+            &valid_exchanges,
+            strategy.as_ref(),
+            max_weight(weighting),
+            now,
+        )
+        .unwrap_or_else(|| {
+            // Fallback to simple average if weighting fails
+            // This should rarely happen but provides robustness
+            // It could occur in extreme cases like:
+            // 1. Extreme time differences causing weights to round to zero
+            // 2. No venue providing the liquidity data the strategy weights by
+            // 3. Implementation bugs elsewhere in the codebase
+            valid_exchanges
+                .iter()
+                .map(|ep| ep.mid_price)
+                .sum::<Decimal>()
+                / Decimal::from(valid_exchanges.len())
+        });
 
-        Self {
-            price: average_price,
-            timestamp: SystemTime::now(),
+        Some(Self {
+            symbol: symbol.into(),
+            price: average_price.round_dp(INDEX_SCALE).normalize(),
+            timestamp: now,
             exchange_prices,
-        }
+            excluded,
+            venues,
+            min_venues,
+            quorum_met: venues >= min_venues,
+        })
+    }
+
+    /// Creates a new GlobalPriceIndex with the weighting, outlier and
+    /// quorum settings of the global configuration, see `new`
+    pub fn configured(
+        symbol: impl Into<String>,
+        exchange_prices: Vec<ExchangePrice>,
+    ) -> Option<Self> {
+        Self::new(
+            symbol,
+            exchange_prices,
+            &get_price_weighting(),
+            &get_outlier_filter(),
+            get_min_venues(),
+        )
     }
 }
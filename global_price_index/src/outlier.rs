@@ -0,0 +1,138 @@
+// Outlier rejection of venue prices before weighting
+use crate::config::OutlierFilter;
+use crate::models::{Decimal, ExchangePrice, ExcludedPrice, ExclusionReason};
+use rust_decimal::prelude::FromPrimitive;
+use std::time::{Duration, SystemTime};
+
+/// Returns the median of a list of values, None if the list is empty
+///
+/// The median of an even number of values is the average of the two middle ones.
+pub fn median(mut values: Vec<Decimal>) -> Option<Decimal> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[middle - 1] + values[middle]) / Decimal::TWO)
+    } else {
+        Some(values[middle])
+    }
+}
+
+/// Splits venue prices into the prices kept for weighting and the rejected ones
+///
+/// This function:
+/// 1. Rejects non-positive prices
+/// 2. Stops there if the filter is disabled or fewer valid prices than the
+///    venue quorum remain, as the median of too few venues cannot tell which
+///    one is wrong
+/// 3. Rejects prices further from the median than `max_deviation_pct` percent of it
+/// 4. Rejects prices further from the median than `mad_threshold` times the
+///    median absolute deviation (MAD) of all valid prices
+///
+/// The MAD check is skipped when the MAD is zero, i.e. when most venues
+/// quote the exact same price, as any other price would be rejected. If no
+/// price is within the limits, the venues disagree too much for outliers to
+/// be told apart and every valid price is kept.
+///
+/// Args:
+///   prices: Latest price of every venue quoting the symbol
+///   filter: Outlier rejection settings
+///   min_venues: Venue quorum of a valid index
+///
+/// Returns:
+///   The prices kept for weighting, and the rejected prices with the reason
+///   each was rejected
+pub fn filter_outliers<'a>(
+    prices: &'a [ExchangePrice],
+    filter: &OutlierFilter,
+    min_venues: usize,
+) -> (Vec<&'a ExchangePrice>, Vec<ExcludedPrice>) {
+    let mut valid = Vec::new();
+    let mut excluded = Vec::new();
+    for price in prices {
+        if price.mid_price > Decimal::ZERO {
+            valid.push(price);
+        } else {
+            excluded.push(exclude(price, ExclusionReason::NonPositivePrice));
+        }
+    }
+
+    if !filter.enabled || valid.len() < min_venues {
+        return (valid, excluded);
+    }
+    let Some(median_price) = median(valid.iter().map(|price| price.mid_price).collect()) else {
+        return (valid, excluded);
+    };
+
+    let deviations: Vec<Decimal> = valid
+        .iter()
+        .map(|price| (price.mid_price - median_price).abs())
+        .collect();
+    let mad = median(deviations.clone()).unwrap_or(Decimal::ZERO);
+    let max_deviation_pct = Decimal::from_f64(filter.max_deviation_pct).unwrap_or(Decimal::ZERO);
+    let mad_threshold = Decimal::from_f64(filter.mad_threshold).unwrap_or(Decimal::ZERO);
+
+    let mut kept = Vec::new();
+    let mut outliers = Vec::new();
+    for (price, deviation) in valid.iter().zip(deviations) {
+        if max_deviation_pct > Decimal::ZERO
+            && deviation * Decimal::ONE_HUNDRED > max_deviation_pct * median_price
+        {
+            outliers.push(exclude(price, ExclusionReason::MaxDeviation));
+        } else if mad_threshold > Decimal::ZERO
+            && mad > Decimal::ZERO
+            && deviation > mad_threshold * mad
+        {
+            outliers.push(exclude(price, ExclusionReason::MedianAbsoluteDeviation));
+        } else {
+            kept.push(*price);
+        }
+    }
+
+    if kept.is_empty() {
+        return (valid, excluded);
+    }
+    excluded.extend(outliers);
+    (kept, excluded)
+}
+
+/// Returns whether a venue price is older than the maximum age as of `now`
+pub fn is_stale(price: &ExchangePrice, max_age: Duration, now: SystemTime) -> bool {
+    now.duration_since(price.timestamp)
+        .is_ok_and(|age| age > max_age)
+}
+
+/// Splits venue prices into the fresh prices and the stale ones
+///
+/// A price is stale when the book it was taken from was not updated within
+/// `max_age`, e.g. because the venue's WebSocket stream died while its last
+/// book is still in memory, see `is_stale`.
+///
+/// Returns:
+///   The fresh prices, and the stale prices excluded as `Stale`
+pub fn filter_stale(
+    prices: &[ExchangePrice],
+    max_age: Duration,
+    now: SystemTime,
+) -> (Vec<ExchangePrice>, Vec<ExcludedPrice>) {
+    let (stale, fresh): (Vec<ExchangePrice>, Vec<ExchangePrice>) = prices
+        .iter()
+        .cloned()
+        .partition(|price| is_stale(price, max_age, now));
+    let excluded = stale
+        .iter()
+        .map(|price| exclude(price, ExclusionReason::Stale))
+        .collect();
+    (fresh, excluded)
+}
+
+/// Records a venue price as excluded for a reason
+fn exclude(price: &ExchangePrice, reason: ExclusionReason) -> ExcludedPrice {
+    ExcludedPrice {
+        exchange: price.exchange.clone(),
+        mid_price: price.mid_price,
+        reason,
+    }
+}
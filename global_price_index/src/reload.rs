@@ -0,0 +1,259 @@
+// Hot reload of the configuration file
+use crate::aggregator::IndexSettings;
+use crate::api::AppState;
+use crate::config::{ConfigSources, Settings};
+use crate::error::Result;
+use crate::exchanges::ExchangeKind;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// Exchange markets to stop, start and restart to apply a new configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketChanges {
+    /// Markets no longer enabled
+    pub stopped: Vec<(ExchangeKind, String)>,
+    /// Markets newly enabled
+    pub started: Vec<(ExchangeKind, String)>,
+    /// Markets whose endpoints, market mapping, connection or capture
+    /// settings changed, see `MarketSettings`
+    pub restarted: Vec<(ExchangeKind, String)>,
+}
+
+impl MarketChanges {
+    /// Compares the exchange markets of two configurations
+    pub fn between(old: &Settings, new: &Settings) -> Self {
+        let mut changes = Self::default();
+        let mut symbols: Vec<&String> = old.index.symbols.iter().collect();
+        symbols.extend(
+            new.index
+                .symbols
+                .iter()
+                .filter(|symbol| !old.index.symbols.contains(symbol)),
+        );
+
+        for symbol in symbols {
+            for kind in ExchangeKind::ALL {
+                let market = (kind, symbol.clone());
+                match (
+                    kind.market_settings(old, symbol),
+                    kind.market_settings(new, symbol),
+                ) {
+                    (Some(_), None) => changes.stopped.push(market),
+                    (None, Some(_)) => changes.started.push(market),
+                    (Some(old), Some(new)) if old != new => changes.restarted.push(market),
+                    _ => {}
+                }
+            }
+        }
+        changes
+    }
+
+    /// Returns whether no market is affected
+    pub fn is_empty(&self) -> bool {
+        self.stopped.is_empty() && self.started.is_empty() && self.restarted.is_empty()
+    }
+
+    /// Returns the changes as JSON, each market as its exchange and symbol
+    pub fn to_json(&self) -> serde_json::Value {
+        let markets = |markets: &[(ExchangeKind, String)]| -> Vec<serde_json::Value> {
+            markets
+                .iter()
+                .map(
+                    |(kind, symbol)| serde_json::json!({"exchange": kind.name(), "symbol": symbol}),
+                )
+                .collect()
+        };
+        serde_json::json!({
+            "stopped": markets(&self.stopped),
+            "started": markets(&self.started),
+            "restarted": markets(&self.restarted),
+        })
+    }
+}
+
+/// ConfigReloader applies changes of the configuration file to the running service
+///
+/// Reloads triggered by the file watch and by POST /admin/reload run one
+/// at a time.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    sources: ConfigSources,
+    state: AppState,
+    lock: Arc<Mutex<()>>,
+}
+
+impl ConfigReloader {
+    /// Creates a reloader of the settings of a service
+    ///
+    /// Args:
+    ///   sources: Configuration file, environment and overrides the settings
+    ///   are loaded from, the file being required only if it was at startup
+    ///   state: Settings, registry and aggregator of the service
+    pub fn new(sources: ConfigSources, state: AppState) -> Self {
+        Self {
+            sources,
+            state,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Reloads the configuration file
+    ///
+    /// This function:
+    /// 1. Loads and validates the settings from their sources, failing on
+    ///    any error
+    /// 2. Replaces at once the settings the requests are served with, the
+    ///    registry connects markets with and the aggregator computes
+    ///    indices with
+    /// 3. Stops the markets no longer enabled
+    /// 4. Restarts the markets whose exchange section, `[exchange.config]`
+    ///    or `[capture]` settings changed, see `MarketSettings`
+    /// 5. Starts the markets newly enabled
+    ///
+    /// The other settings, such as the decay factor, the exchange poll
+    /// intervals, the health thresholds or the stream throttle, are read from
+    /// the replaced settings and apply from their next use.
+    ///
+    /// The indexed symbols are kept: the API serves the symbols it was
+    /// started with, so a changed `[index] symbols` is applied on restart.
+    ///
+    /// Returns:
+    ///   Result<MarketChanges>: The affected markets, or a configuration
+    ///   error leaving the running configuration unchanged
+    pub async fn reload(&self) -> Result<MarketChanges> {
+        let _guard = self.lock.lock().await;
+        let mut current = Settings::from_sources(&self.sources)?;
+        current.validate()?;
+        let previous = self.state.settings();
+        if current.index.symbols != previous.index.symbols {
+            warn!("[index] symbols changed, the new symbols are indexed after a restart");
+            current.index.symbols = previous.index.symbols.clone();
+        }
+
+        let changes = MarketChanges::between(&previous, &current);
+        let (registry, aggregator) = (&self.state.registry, &self.state.aggregator);
+        aggregator.set_settings(IndexSettings::from(&current));
+        registry.set_settings(current.clone());
+        self.state.set_settings(current);
+
+        for (kind, symbol) in &changes.stopped {
+            registry.stop(*kind, symbol, aggregator).await;
+        }
+        for (kind, symbol) in &changes.restarted {
+            registry.restart(*kind, symbol, aggregator).await;
+        }
+        for (kind, symbol) in &changes.started {
+            registry.start(*kind, symbol.clone(), aggregator);
+        }
+        info!(
+            stopped = changes.stopped.len(),
+            started = changes.started.len(),
+            restarted = changes.restarted.len(),
+            "Configuration reloaded"
+        );
+        Ok(changes)
+    }
+
+    /// Reloads the configuration file whenever it changes
+    ///
+    /// The file's directory is watched, as editors often save by replacing
+    /// the file. A reload waits for the file to stay unchanged for the
+    /// configured debounce delay. Invalid configurations are logged and
+    /// the running one is kept.
+    ///
+    /// Returns:
+    ///   notify::Result<JoinHandle<()>>: The handle of the watch task,
+    ///   which stops watching when aborted, or a watch error
+    pub fn watch(&self) -> notify::Result<JoinHandle<()>> {
+        let (changed_tx, mut changed_rx) = mpsc::unbounded_channel();
+        let path = self.sources.file.clone();
+        let file_name = path.file_name().map(ToOwned::to_owned);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                // Reading the file is not a change, nor are its metadata
+                Ok(event)
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) && !matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)))
+                        && event
+                            .paths
+                            .iter()
+                            .any(|path| path.file_name() == file_name.as_deref()) =>
+                {
+                    let _ = changed_tx.send(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "Configuration watch error"),
+            }
+        })?;
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        let reloader = self.clone();
+        let span = info_span!("config_watch", path = %path.display());
+        Ok(tokio::spawn(
+            async move {
+                // The watcher stops watching once the task ends and drops it
+                let _watcher = watcher;
+                while changed_rx.recv().await.is_some() {
+                    // Wait for the rest of the save, e.g. the writes of an editor
+                    loop {
+                        let debounce = reloader.state.settings().reload.debounce();
+                        match timeout(debounce, changed_rx.recv()).await {
+                            Ok(Some(())) => continue,
+                            Ok(None) => return,
+                            Err(_) => break,
+                        }
+                    }
+                    if let Err(e) = reloader.reload().await {
+                        error!(error = %e, "Invalid configuration, keeping the running one");
+                    }
+                }
+            }
+            .instrument(span),
+        ))
+    }
+}
+
+/// HTTP handler for the POST /admin/reload endpoint
+///
+/// Reloads the configuration file, see `ConfigReloader::reload`. The
+/// endpoint is not authenticated, so only requests from the loopback
+/// interface are accepted, even when the API listens on every interface.
+///
+/// Returns:
+///   HTTP 200 with the stopped, started and restarted markets on success
+///   HTTP 403 if the request does not come from the loopback interface
+///   HTTP 422 with the error if the configuration is invalid, the running one being kept
+pub async fn post_reload(req: HttpRequest, reloader: web::Data<ConfigReloader>) -> impl Responder {
+    let local = req
+        .peer_addr()
+        .is_some_and(|addr| addr.ip().to_canonical().is_loopback());
+    if !local {
+        warn!(peer = ?req.peer_addr(), "Rejected configuration reload from a remote peer");
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Configuration reloads are only accepted from the loopback interface",
+        }));
+    }
+
+    match reloader.reload().await {
+        Ok(changes) => HttpResponse::Ok().json(changes.to_json()),
+        Err(e) => {
+            error!(error = %e, "Invalid configuration, keeping the running one");
+            HttpResponse::UnprocessableEntity().json(serde_json::json!({
+                "error": e.to_string(),
+            }))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+// Offline replay of captured exchange feeds
+use crate::capture::{Capture, CaptureSession};
+use crate::error::{PriceIndexError, Result};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use url::Url;
+
+/// Largest HTTP request head read by the replay server
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+/// Recorded bodies of one request and the next one to serve
+struct Responses {
+    bodies: Vec<String>,
+    next: usize,
+}
+
+impl Responses {
+    /// Returns the next recorded body, repeating the last one once all were served
+    fn next_body(&mut self) -> String {
+        let index = self.next.min(self.bodies.len() - 1);
+        self.next += 1;
+        self.bodies[index].clone()
+    }
+}
+
+/// REST responses of a capture, served in the order they were recorded
+///
+/// A request is matched on its path and query, and on its path alone if
+/// no response was recorded for that exact query.
+struct RestReplay {
+    by_target: HashMap<String, Responses>,
+    by_path: HashMap<String, Responses>,
+}
+
+impl RestReplay {
+    /// Indexes the REST responses of a capture by request target and path
+    fn new(capture: &Capture) -> Self {
+        let mut replay = Self {
+            by_target: HashMap::new(),
+            by_path: HashMap::new(),
+        };
+        for (url, body) in capture.rest_responses() {
+            let target = request_target(url);
+            let path = target.split('?').next().unwrap_or_default().to_string();
+            for (map, key) in [(&mut replay.by_target, target), (&mut replay.by_path, path)] {
+                map.entry(key)
+                    .or_insert_with(|| Responses {
+                        bodies: Vec::new(),
+                        next: 0,
+                    })
+                    .bodies
+                    .push(body.to_string());
+            }
+        }
+        replay
+    }
+
+    /// Returns the body to serve for a request target, None if none was recorded
+    fn respond(&mut self, target: &str) -> Option<String> {
+        if let Some(responses) = self.by_target.get_mut(target) {
+            return Some(responses.next_body());
+        }
+        let path = target.split('?').next().unwrap_or_default();
+        self.by_path.get_mut(path).map(Responses::next_body)
+    }
+}
+
+/// Returns the path and query of a URL, e.g. "/api/v3/depth?symbol=BTCUSDT"
+fn request_target(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Local stand-in of an exchange serving a capture back
+///
+/// The server plays the exchange side of a capture:
+/// 1. An HTTP endpoint answers GET requests with the recorded REST bodies,
+///    in order for each request, repeating the last one once exhausted
+/// 2. A WebSocket endpoint sends the nth connection the frames captured on
+///    the nth recorded connection, as fast as the client reads them
+/// 3. Every connection but the last is closed once its frames are sent, so
+///    the client reconnects as it did during the capture; the last one is
+///    kept open
+///
+/// Exchange clients are pointed at the server with the URLs returned by
+/// `rest_url` and `ws_url`, which makes the whole pipeline run offline with
+/// reproducible results. The server stops when dropped.
+pub struct ReplayServer {
+    http_addr: SocketAddr,
+    ws_addr: SocketAddr,
+    ws_target: String,
+    sessions: usize,
+    replayed: watch::Receiver<usize>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl ReplayServer {
+    /// Starts serving a capture on local ports
+    ///
+    /// Returns:
+    ///   Result<Self>: The running server, or an error if no port could be bound
+    pub async fn start(capture: &Capture) -> Result<Self> {
+        let bind = |e: std::io::Error| {
+            PriceIndexError::CaptureError(format!("Failed to bind replay server: {}", e))
+        };
+        let http_listener = TcpListener::bind("127.0.0.1:0").await.map_err(bind)?;
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.map_err(bind)?;
+        let http_addr = http_listener.local_addr().map_err(bind)?;
+        let ws_addr = ws_listener.local_addr().map_err(bind)?;
+
+        let sessions = capture.sessions();
+        let ws_target = sessions
+            .first()
+            .map(|session| request_target(&session.url))
+            .unwrap_or_else(|| "/".to_string());
+        let (replayed_tx, replayed) = watch::channel(0);
+
+        Ok(Self {
+            http_addr,
+            ws_addr,
+            ws_target,
+            sessions: sessions.len(),
+            replayed,
+            tasks: vec![
+                tokio::spawn(serve_rest(
+                    http_listener,
+                    Arc::new(Mutex::new(RestReplay::new(capture))),
+                )),
+                tokio::spawn(serve_websocket(ws_listener, sessions, replayed_tx)),
+            ],
+        })
+    }
+
+    /// Rewrites a URL of the exchange's REST API to point at the server
+    ///
+    /// e.g. "https://api.kraken.com/0/public/Depth" becomes
+    /// "http://127.0.0.1:<port>/0/public/Depth".
+    pub fn rest_url(&self, url: &str) -> String {
+        format!("http://{}{}", self.http_addr, request_target(url))
+    }
+
+    /// Returns the URL of the WebSocket endpoint, with the path of the first captured connection
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}{}", self.ws_addr, self.ws_target)
+    }
+
+    /// Waits until the frames of every captured connection were sent
+    ///
+    /// The frames are sent but not necessarily processed by the client yet.
+    pub async fn wait_until_replayed(&self) {
+        let mut replayed = self.replayed.clone();
+        let _ = replayed.wait_for(|count| *count >= self.sessions).await;
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Accepts HTTP connections and answers each with a recorded REST body
+async fn serve_rest(listener: TcpListener, responses: Arc<Mutex<RestReplay>>) {
+    let mut connections = JoinSet::new();
+    while let Ok((stream, _)) = listener.accept().await {
+        while connections.try_join_next().is_some() {}
+        connections.spawn(respond(stream, responses.clone()));
+    }
+}
+
+/// Reads one HTTP request and writes the recorded response, closing the connection
+///
+/// Requests without a recorded response get HTTP 404.
+async fn respond(mut stream: TcpStream, responses: Arc<Mutex<RestReplay>>) {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 4096];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+        if request.len() > MAX_REQUEST_SIZE {
+            return;
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    let body = responses.lock().unwrap().respond(target);
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body),
+        None => (
+            "404 Not Found",
+            serde_json::json!({ "error": format!("No captured response for {}", target) })
+                .to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Accepts WebSocket connections and replays the captured connections in order
+///
+/// Connections beyond the captured ones are accepted but receive nothing.
+async fn serve_websocket(
+    listener: TcpListener,
+    sessions: Vec<CaptureSession>,
+    replayed: watch::Sender<usize>,
+) {
+    let mut connections = JoinSet::new();
+    let count = sessions.len();
+    let mut sessions = sessions.into_iter();
+    let mut index = 0;
+    while let Ok((stream, _)) = listener.accept().await {
+        while connections.try_join_next().is_some() {}
+        index += 1;
+        connections.spawn(replay_session(
+            stream,
+            sessions.next(),
+            index < count,
+            replayed.clone(),
+        ));
+    }
+}
+
+/// Sends the frames of a captured connection to a client
+///
+/// Client frames are read throughout so pings are answered and a closing
+/// client is noticed. The connection is closed after the frames if
+/// `close_after` is set and kept open until the client leaves otherwise.
+async fn replay_session(
+    stream: TcpStream,
+    session: Option<CaptureSession>,
+    close_after: bool,
+    replayed: watch::Sender<usize>,
+) {
+    let Ok(ws_stream) = accept_async(stream).await else {
+        return;
+    };
+    let (mut write, mut read) = ws_stream.split();
+
+    let send = async move {
+        let Some(session) = session else {
+            return;
+        };
+        for frame in session.frames {
+            if write.send(frame).await.is_err() {
+                break;
+            }
+        }
+        replayed.send_modify(|count| *count += 1);
+        if close_after {
+            let _ = write.send(Message::Close(None)).await;
+        }
+    };
+    let drain = async move { while let Some(Ok(_)) = read.next().await {} };
+    tokio::join!(send, drain);
+}
@@ -0,0 +1,215 @@
+// Live index updates, Server-Sent Events endpoint
+
+use crate::api::{resolve_symbol, AppState};
+use crate::models::GlobalPriceIndex;
+use actix_web::{http::header, web, HttpResponse, Responder};
+use futures::stream::{self, Stream};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{sleep_until, Instant};
+use tracing::warn;
+
+/// IndexPublisher fans out live index updates to streaming clients
+///
+/// Every published index is kept as the latest value of its symbol and
+/// broadcast to all subscriptions. A subscription that falls more than
+/// the channel capacity behind skips ahead to the most recent updates.
+#[derive(Clone)]
+pub struct IndexPublisher {
+    sender: broadcast::Sender<GlobalPriceIndex>,
+    latest: Arc<RwLock<HashMap<String, GlobalPriceIndex>>>,
+}
+
+impl IndexPublisher {
+    /// Creates a publisher buffering up to `capacity` updates per subscription
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            latest: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Records an index as the latest of its symbol and sends it to all subscriptions
+    pub fn publish(&self, index: GlobalPriceIndex) {
+        self.latest
+            .write()
+            .unwrap()
+            .insert(index.symbol.clone(), index.clone());
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(index);
+    }
+
+    /// Returns the latest index published for a symbol
+    pub fn latest(&self, symbol: &str) -> Option<GlobalPriceIndex> {
+        self.latest.read().unwrap().get(symbol).cloned()
+    }
+
+    /// Forgets the latest index of a symbol, once none of its venues is tracked
+    pub fn clear(&self, symbol: &str) {
+        self.latest.write().unwrap().remove(symbol);
+    }
+
+    /// Subscribes to the updates of some symbols
+    ///
+    /// The latest index of each symbol, if any, is delivered first so that
+    /// clients do not wait for the next change to display a price.
+    ///
+    /// Args:
+    ///   symbols: Canonical symbols to receive updates for
+    ///   throttle: Minimum delay between two updates of the same symbol
+    pub fn subscribe(&self, symbols: Vec<String>, throttle: Duration) -> Subscription {
+        // Subscribe before reading the latest values so no update is missed
+        let receiver = self.sender.subscribe();
+        let pending = symbols
+            .iter()
+            .filter_map(|symbol| Some((symbol.clone(), self.latest(symbol)?)))
+            .collect();
+
+        Subscription {
+            receiver,
+            symbols,
+            throttle,
+            last_sent: HashMap::new(),
+            pending,
+        }
+    }
+}
+
+/// A client's view of the index updates
+///
+/// Updates of symbols the client did not subscribe to are ignored. When
+/// a symbol changes faster than the throttle allows, intermediate updates
+/// are coalesced and only the most recent one is delivered.
+pub struct Subscription {
+    receiver: broadcast::Receiver<GlobalPriceIndex>,
+    symbols: Vec<String>,
+    throttle: Duration,
+    last_sent: HashMap<String, Instant>,
+    pending: HashMap<String, GlobalPriceIndex>,
+}
+
+impl Subscription {
+    /// Waits for the next update to deliver to the client
+    ///
+    /// Returns:
+    ///   The next index, or None once the publisher is gone
+    pub async fn next(&mut self) -> Option<GlobalPriceIndex> {
+        loop {
+            let now = Instant::now();
+
+            // Deliver a pending update whose throttle delay has elapsed
+            let ready = self
+                .pending
+                .keys()
+                .find(|symbol| self.next_allowed(symbol).is_none_or(|at| at <= now))
+                .cloned();
+            if let Some(symbol) = ready {
+                self.last_sent.insert(symbol.clone(), now);
+                return self.pending.remove(&symbol);
+            }
+
+            // Otherwise wait for a new update or for the earliest throttled one
+            let wake_at = self
+                .pending
+                .keys()
+                .filter_map(|symbol| self.next_allowed(symbol))
+                .min();
+
+            tokio::select! {
+                received = self.receiver.recv() => match received {
+                    Ok(index) => {
+                        if self.symbols.contains(&index.symbol) {
+                            self.pending.insert(index.symbol.clone(), index);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "Stream client lagging, skipped index updates");
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = sleep_until(wake_at.unwrap_or(now)), if wake_at.is_some() => {}
+            }
+        }
+    }
+
+    /// Turns the subscription into a stream of the updates, ending once the publisher is gone
+    pub fn into_stream(self) -> impl Stream<Item = GlobalPriceIndex> + Unpin {
+        Box::pin(stream::unfold(self, |mut subscription| async move {
+            let index = subscription.next().await?;
+            Some((index, subscription))
+        }))
+    }
+
+    /// Returns when the next update of a symbol may be sent, if it was sent before
+    fn next_allowed(&self, symbol: &str) -> Option<Instant> {
+        self.last_sent.get(symbol).map(|sent| *sent + self.throttle)
+    }
+}
+
+/// Query parameters of the /stream endpoint
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    /// Comma-separated symbols such as "BTC-USDT,ETH-USDT"; all symbols when omitted
+    pub symbols: Option<String>,
+    /// Minimum delay between two updates of a symbol, in milliseconds
+    pub throttle_ms: Option<u64>,
+}
+
+/// HTTP handler for the /stream endpoint
+///
+/// Streams a Server-Sent Event named `index` carrying the GlobalPriceIndex
+/// JSON each time a subscribed symbol's index changes, e.g.
+/// `/stream?symbols=BTC-USDT,ETH-USDT&throttle_ms=1000`.
+///
+/// Returns:
+///   HTTP 200 with a `text/event-stream` body
+///   HTTP 404 if a requested symbol is not indexed
+pub async fn stream_global_price(
+    data: web::Data<AppState>,
+    query: web::Query<StreamQuery>,
+) -> impl Responder {
+    let symbols = match &query.symbols {
+        None => data.symbols.clone(),
+        Some(requested) => {
+            let mut symbols = Vec::new();
+            for name in requested.split(',').map(str::trim) {
+                match resolve_symbol(&data.symbols, name) {
+                    Some(symbol) => symbols.push(symbol.to_string()),
+                    None => {
+                        return HttpResponse::NotFound().json(serde_json::json!({
+                            "error": format!("Unknown symbol: {}", name),
+                            "symbols": data.symbols,
+                        }))
+                    }
+                }
+            }
+            symbols
+        }
+    };
+    let throttle = query
+        .throttle_ms
+        .map(Duration::from_millis)
+        .unwrap_or_else(|| data.settings().stream.default_throttle());
+
+    let subscription = data.aggregator.publisher().subscribe(symbols, throttle);
+    let events = futures::stream::unfold(subscription, |mut subscription| async move {
+        let index = subscription.next().await?;
+        let event = format!(
+            "event: index\ndata: {}\n\n",
+            serde_json::to_string(&index).ok()?
+        );
+        Some((
+            Ok::<_, actix_web::Error>(web::Bytes::from(event)),
+            subscription,
+        ))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
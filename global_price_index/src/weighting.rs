@@ -0,0 +1,210 @@
+// Weighting strategies of venue prices in the index
+use crate::config::{Clock, PriceWeighting, WeightingMethod};
+use crate::models::{Decimal, ExchangePrice};
+use rust_decimal::prelude::FromPrimitive;
+use std::time::{Duration, SystemTime};
+
+/// Computes the weight of a venue price in the index
+///
+/// Weights are relative: only their ratios matter, the index divides the
+/// weighted sum of prices by the sum of weights.
+pub trait WeightingStrategy: Send + Sync {
+    /// Returns the weight of a venue price at time `now`
+    ///
+    /// Returns:
+    ///   The weight, None if the price lacks the data the strategy weights by
+    fn weight(&self, price: &ExchangePrice, now: SystemTime) -> Option<Decimal>;
+}
+
+/// Weights prices by their recency with an exponential decay
+///
+/// weight = e^(-time_diff/decay_factor)
+///
+/// The age of a price is measured on the local clock or on the venue's,
+/// see `ExchangePrice::time`.
+pub struct TimeDecay {
+    /// Time constant of the decay, in seconds
+    pub decay_factor: f64,
+    /// Clock the age of a price is measured on
+    pub clock: Clock,
+}
+
+impl WeightingStrategy for TimeDecay {
+    fn weight(&self, price: &ExchangePrice, now: SystemTime) -> Option<Decimal> {
+        // Calculate time difference between now and when the price was recorded
+        // This tells us how "old" or "stale" this particular price data is
+        let time_diff_secs = now
+            .duration_since(price.time(self.clock))
+            .unwrap_or_else(|_| Duration::from_secs(0))
+            .as_secs() as f64;
+
+        // Apply exponential decay formula: weight = e^(-time_diff/decay_factor)
+        // With a decay factor of 300 seconds (5 minutes):
+        // - A price from right now gets weight = e^(-0/300) = 1.0 (100% influence)
+        // - A price 5 minutes old gets weight ≈ e^(-300/300) ≈ 0.368 (36.8% influence)
+        // - A price 10 minutes old gets weight ≈ e^(-600/300) ≈ 0.135 (13.5% influence)
+        // - A price 20 minutes old gets weight ≈ e^(-1200/300) ≈ 0.018 (1.8% influence)
+        // Weights too small to be represented as a decimal count as zero
+        Some(
+            Decimal::from_f64((-time_diff_secs / self.decay_factor).exp()).unwrap_or(Decimal::ZERO),
+        )
+    }
+}
+
+/// Weights prices by the notional quoted at the venue's best bid and ask
+pub struct TopOfBookDepth;
+
+impl WeightingStrategy for TopOfBookDepth {
+    fn weight(&self, price: &ExchangePrice, _now: SystemTime) -> Option<Decimal> {
+        price.liquidity.top_of_book_depth
+    }
+}
+
+/// Weights prices by the notional quoted close to the venue's mid price
+///
+/// The band around the mid price is set by `depth_bps` when the price is taken.
+pub struct DepthWithinBps;
+
+impl WeightingStrategy for DepthWithinBps {
+    fn weight(&self, price: &ExchangePrice, _now: SystemTime) -> Option<Decimal> {
+        price.liquidity.depth_within_bps
+    }
+}
+
+/// Weights prices by the venue's trailing traded volume
+pub struct TradedVolume;
+
+impl WeightingStrategy for TradedVolume {
+    fn weight(&self, price: &ExchangePrice, _now: SystemTime) -> Option<Decimal> {
+        price.liquidity.volume
+    }
+}
+
+/// Returns the weighting strategy selected in price weighting settings
+pub fn strategy(weighting: &PriceWeighting) -> Box<dyn WeightingStrategy> {
+    match weighting.method {
+        WeightingMethod::TimeDecay => Box::new(TimeDecay {
+            decay_factor: weighting.decay_factor,
+            clock: weighting.clock,
+        }),
+        WeightingMethod::TopOfBookDepth => Box::new(TopOfBookDepth),
+        WeightingMethod::DepthWithinBps => Box::new(DepthWithinBps),
+        WeightingMethod::Volume => Box::new(TradedVolume),
+    }
+}
+
+/// Returns the maximum share of the total weight of a venue of price
+/// weighting settings, 0 if uncapped
+pub fn max_weight(weighting: &PriceWeighting) -> Decimal {
+    Decimal::from_f64(weighting.max_weight).unwrap_or(Decimal::ZERO)
+}
+
+/// Normalizes weights into shares of the total weight, capping each share
+///
+/// This function:
+/// 1. Divides each weight by the total weight
+/// 2. Lowers every share above `max_share` to `max_share`
+/// 3. Redistributes the removed weight to the other venues in proportion to their shares
+/// 4. Repeats until no share exceeds `max_share`
+///
+/// Venues without weight receive no redistributed weight. If the cap
+/// cannot be met, i.e. `max_share` times the number of weighted venues is
+/// at most 1, the weighted venues get equal shares.
+///
+/// Args:
+///   weights: Non-negative weight of every venue
+///   max_share: Maximum share of a venue, 0 or at least 1 disables the cap
+///
+/// Returns:
+///   The share of every venue, None if the total weight is not positive
+pub fn cap_weights(weights: &[Decimal], max_share: Decimal) -> Option<Vec<Decimal>> {
+    let total: Decimal = weights.iter().sum();
+    if total <= Decimal::ZERO {
+        return None;
+    }
+    let mut shares: Vec<Decimal> = weights.iter().map(|weight| weight / total).collect();
+    if max_share <= Decimal::ZERO || max_share >= Decimal::ONE {
+        return Some(shares);
+    }
+
+    let weighted = shares
+        .iter()
+        .filter(|share| **share > Decimal::ZERO)
+        .count();
+    if max_share * Decimal::from(weighted) <= Decimal::ONE {
+        let equal = Decimal::ONE / Decimal::from(weighted);
+        return Some(
+            shares
+                .iter()
+                .map(|share| {
+                    if *share > Decimal::ZERO {
+                        equal
+                    } else {
+                        Decimal::ZERO
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    loop {
+        let excess: Decimal = shares
+            .iter()
+            .filter(|share| **share > max_share)
+            .map(|share| share - max_share)
+            .sum();
+        if excess <= Decimal::ZERO {
+            return Some(shares);
+        }
+
+        let uncapped: Decimal = shares.iter().filter(|share| **share < max_share).sum();
+        if uncapped <= Decimal::ZERO {
+            return Some(shares);
+        }
+        for share in shares.iter_mut() {
+            if *share > max_share {
+                *share = max_share;
+            } else if *share < max_share {
+                *share += excess * *share / uncapped;
+            }
+        }
+    }
+}
+
+/// Computes the weighted average of venue prices
+///
+/// Prices the strategy cannot weigh count with a zero weight.
+///
+/// Args:
+///   prices: Venue prices to average
+///   strategy: Strategy weighting each price
+///   max_share: Maximum share of the total weight of a venue, see `cap_weights`
+///   now: Time the weights are computed at
+///
+/// Returns:
+///   The weighted average, None if no price has a positive weight
+pub fn weighted_average(
+    prices: &[&ExchangePrice],
+    strategy: &dyn WeightingStrategy,
+    max_share: Decimal,
+    now: SystemTime,
+) -> Option<Decimal> {
+    let weights: Vec<Decimal> = prices
+        .iter()
+        .map(|price| {
+            strategy
+                .weight(price, now)
+                .filter(|weight| *weight > Decimal::ZERO)
+                .unwrap_or(Decimal::ZERO)
+        })
+        .collect();
+    let shares = cap_weights(&weights, max_share)?;
+
+    let total_share: Decimal = shares.iter().sum();
+    let weighted_sum: Decimal = prices
+        .iter()
+        .zip(&shares)
+        .map(|(price, share)| price.mid_price * share)
+        .sum();
+    Some(weighted_sum / total_share)
+}
@@ -0,0 +1,430 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web};
+use async_trait::async_trait;
+use common::price;
+use global_price_index::{
+    aggregator::{Aggregator, IndexSettings},
+    api::{get_global_price, AppState},
+    config::{PriceWeighting, Settings},
+    error::{PriceIndexError, Result},
+    exchanges::{registry::ExchangeRegistry, Exchange},
+    history::HistoryStore,
+    models::{Decimal, ExchangePrice, ExclusionReason, GlobalPriceIndex, Order, OrderBook},
+};
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{sleep, timeout, Duration};
+
+/// Returns an aggregator with the default settings whose index is valid from a single venue
+fn single_venue_aggregator() -> Aggregator {
+    Aggregator::new(16).with_settings(IndexSettings {
+        min_venues: 1,
+        ..IndexSettings::from(&Settings::default())
+    })
+}
+
+/// Exchange stand-in quoting a configurable mid price and counting its fetches
+struct MockExchange {
+    name: &'static str,
+    mid_price: Mutex<Option<Decimal>>,
+    fetches: AtomicUsize,
+}
+
+impl MockExchange {
+    fn new(name: &'static str, mid_price: Decimal) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            mid_price: Mutex::new(Some(mid_price)),
+            fetches: AtomicUsize::new(0),
+        })
+    }
+
+    fn set_mid_price(&self, mid_price: Option<Decimal>) {
+        *self.mid_price.lock().unwrap() = mid_price;
+    }
+
+    fn fetches(&self) -> usize {
+        self.fetches.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Exchange for MockExchange {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        let mid_price =
+            self.mid_price.lock().unwrap().ok_or_else(|| {
+                PriceIndexError::ExchangeError("Exchange unavailable".to_string())
+            })?;
+
+        Ok(OrderBook {
+            bids: vec![Order {
+                price: mid_price - dec!(0.5),
+                quantity: dec!(1),
+            }],
+            asks: vec![Order {
+                price: mid_price + dec!(0.5),
+                quantity: dec!(1),
+            }],
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        })
+    }
+}
+
+/// Tests that the index is only recomputed when a venue's mid price changes.
+///
+/// This test verifies:
+/// 1. The first price of a venue computes the index
+/// 2. An unchanged mid price does not recompute the index
+/// 3. A changed mid price recomputes the index from the latest price of every venue
+/// 4. The exchange prices are listed in a stable order
+#[tokio::test]
+async fn test_record_price_recomputes_on_change() {
+    let aggregator = Aggregator::new(16);
+
+    assert!(aggregator.record_price("BTC/USDT", price("Kraken", dec!(50000))));
+    assert!(!aggregator.record_price("BTC/USDT", price("Kraken", dec!(50000))));
+    assert!(aggregator.record_price("BTC/USDT", price("Binance", dec!(50002))));
+
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.symbol, "BTC/USDT");
+    assert_eq!(index.price, dec!(50001));
+    let exchanges: Vec<&str> = index
+        .exchange_prices
+        .iter()
+        .map(|price| price.exchange.as_str())
+        .collect();
+    assert_eq!(exchanges, vec!["Binance", "Kraken"]);
+
+    assert!(aggregator.latest("ETH/USDT").is_none());
+}
+
+/// Tests that a removed venue leaves the index.
+///
+/// This test verifies:
+/// 1. The index is recomputed from the remaining venues
+/// 2. The index is forgotten once no venue quotes the symbol
+/// 3. Removing a venue without a price changes nothing
+#[tokio::test]
+async fn test_remove_venue() {
+    let aggregator = Aggregator::new(16);
+    aggregator.record_price("BTC/USDT", price("Kraken", dec!(50000)));
+    aggregator.record_price("BTC/USDT", price("Binance", dec!(50002)));
+
+    assert!(aggregator.remove_venue("BTC/USDT", "Binance"));
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(50000));
+    assert_eq!(index.exchange_prices.len(), 1);
+    assert_eq!(aggregator.exchange_prices("BTC/USDT").len(), 1);
+
+    assert!(!aggregator.remove_venue("BTC/USDT", "Binance"));
+    assert!(!aggregator.remove_venue("ETH/USDT", "Kraken"));
+
+    assert!(aggregator.remove_venue("BTC/USDT", "Kraken"));
+    assert!(aggregator.latest("BTC/USDT").is_none());
+    assert!(aggregator.exchange_prices("BTC/USDT").is_empty());
+}
+
+/// Tests that a venue whose book stopped updating is excluded as stale.
+///
+/// This test verifies:
+/// 1. The index is recomputed when an unchanged price becomes stale
+/// 2. The stale venue is listed in the exchange prices and excluded as stale
+/// 3. The index is recomputed again once the venue's book updates
+#[tokio::test]
+async fn test_stale_venue_is_excluded() {
+    let aggregator = Aggregator::new(16);
+    let frozen = |mid_price| ExchangePrice {
+        timestamp: SystemTime::now() - Duration::from_secs(3600),
+        ..price("Kraken", mid_price)
+    };
+
+    assert!(aggregator.record_price("BTC/USDT", price("Binance", dec!(50002))));
+    assert!(aggregator.record_price("BTC/USDT", price("Kraken", dec!(50000))));
+    assert_eq!(aggregator.latest("BTC/USDT").unwrap().price, dec!(50001));
+
+    // Same book, an hour later
+    assert!(aggregator.record_price("BTC/USDT", frozen(dec!(50000))));
+    assert!(!aggregator.record_price("BTC/USDT", frozen(dec!(50000))));
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(50002));
+    assert_eq!(index.exchange_prices.len(), 2);
+    assert_eq!(index.excluded.len(), 1);
+    assert_eq!(index.excluded[0].exchange, "Kraken");
+    assert_eq!(index.excluded[0].reason, ExclusionReason::Stale);
+
+    assert!(aggregator.record_price("BTC/USDT", price("Kraken", dec!(50000))));
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(50001));
+    assert!(index.excluded.is_empty());
+}
+
+/// Tests that no index is served once every venue is stale.
+///
+/// This test verifies:
+/// 1. The index is withdrawn rather than priced at 0 when all its venues are stale
+/// 2. The withdrawn index is neither stored in the history nor published
+/// 3. The HTTP handler answers 503 while no index is available
+/// 4. The index is computed again once a venue's book updates
+#[actix_web::test]
+async fn test_all_venues_stale() {
+    let aggregator =
+        single_venue_aggregator().with_history(HistoryStore::open_in_memory().unwrap());
+    let frozen = |exchange, mid_price| ExchangePrice {
+        timestamp: SystemTime::now() - Duration::from_secs(3600),
+        ..price(exchange, mid_price)
+    };
+    let stored = |aggregator: &Aggregator| {
+        aggregator.flush().unwrap();
+        aggregator
+            .history()
+            .unwrap()
+            .range(
+                "BTC/USDT",
+                UNIX_EPOCH,
+                SystemTime::now() + Duration::from_secs(60),
+                100,
+            )
+            .unwrap()
+    };
+
+    assert!(aggregator.record_price("BTC/USDT", price("Binance", dec!(50000))));
+    assert!(aggregator.record_price("BTC/USDT", frozen("Binance", dec!(50000))));
+    assert!(aggregator.latest("BTC/USDT").is_none());
+    assert_eq!(stored(&aggregator).len(), 1);
+
+    let mut subscription = aggregator
+        .publisher()
+        .subscribe(vec!["BTC/USDT".to_string()], Duration::ZERO);
+    assert!(aggregator.record_price("BTC/USDT", frozen("Kraken", dec!(50010))));
+    assert!(aggregator.latest("BTC/USDT").is_none());
+    assert!(timeout(Duration::from_millis(100), subscription.next())
+        .await
+        .is_err());
+
+    let app = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(AppState::new(
+                vec!["BTC/USDT".to_string()],
+                ExchangeRegistry::new(),
+                aggregator.clone(),
+            )))
+            .route("/global-price", web::get().to(get_global_price)),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/global-price").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    assert!(aggregator.record_price("BTC/USDT", price("Kraken", dec!(50010))));
+    assert_eq!(aggregator.latest("BTC/USDT").unwrap().price, dec!(50010));
+    assert_eq!(subscription.next().await.unwrap().price, dec!(50010));
+    let stored = stored(&aggregator);
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|index| index.price > Decimal::ZERO));
+}
+
+/// Tests that an index priced by fewer venues than its quorum is reported but not served.
+///
+/// This test verifies:
+/// 1. The index is computed and published with the venue count, the quorum
+///    and `quorum_met` false
+/// 2. It is not stored in the history
+/// 3. The HTTP handler answers 503 with the venue count and the quorum
+/// 4. The index is stored and served once enough venues price it
+#[actix_web::test]
+async fn test_index_below_quorum() {
+    let aggregator = Aggregator::new(16)
+        .with_settings(IndexSettings::from(&Settings::default()))
+        .with_history(HistoryStore::open_in_memory().unwrap());
+    let stored = |aggregator: &Aggregator| {
+        aggregator.flush().unwrap();
+        aggregator
+            .history()
+            .unwrap()
+            .range(
+                "BTC/USDT",
+                UNIX_EPOCH,
+                SystemTime::now() + Duration::from_secs(60),
+                100,
+            )
+            .unwrap()
+    };
+    let app = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(AppState::new(
+                vec!["BTC/USDT".to_string()],
+                ExchangeRegistry::new(),
+                aggregator.clone(),
+            )))
+            .route("/global-price", web::get().to(get_global_price)),
+    )
+    .await;
+
+    assert!(aggregator.record_price("BTC/USDT", price("Binance", dec!(50000))));
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(50000));
+    assert_eq!((index.venues, index.min_venues), (1, 2));
+    assert!(!index.quorum_met);
+    assert!(stored(&aggregator).is_empty());
+
+    let req = test::TestRequest::get().uri("/global-price").to_request();
+    let response = test::call_service(&app, req).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(body["venues"], 1);
+    assert_eq!(body["min_venues"], 2);
+    assert_eq!(body["quorum_met"], false);
+
+    assert!(aggregator.record_price("BTC/USDT", price("Kraken", dec!(50010))));
+    assert!(aggregator.latest("BTC/USDT").unwrap().quorum_met);
+    assert_eq!(stored(&aggregator).len(), 1);
+
+    let req = test::TestRequest::get().uri("/global-price").to_request();
+    let index: GlobalPriceIndex = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(index.price, dec!(50005));
+    assert_eq!((index.venues, index.min_venues), (2, 2));
+    assert!(index.quorum_met);
+}
+
+/// Tests that background tasks read each venue on its own schedule and keep
+/// the last known price when a read fails.
+///
+/// This test verifies:
+/// 1. Each venue's price is recorded by its background task
+/// 2. A faster schedule reads its venue more often
+/// 3. A failed read keeps the venue's last price in the index
+#[tokio::test]
+async fn test_spawned_venues_feed_the_index() {
+    let aggregator = Aggregator::new(16);
+    let fast = MockExchange::new("Fast", dec!(100));
+    let slow = MockExchange::new("Slow", dec!(200));
+
+    aggregator.spawn(fast.clone(), Duration::from_millis(10));
+    aggregator.spawn(slow.clone(), Duration::from_millis(200));
+    sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(aggregator.latest("BTC/USDT").unwrap().price, dec!(150));
+    assert!(fast.fetches() > slow.fetches());
+
+    fast.set_mid_price(None);
+    slow.set_mid_price(Some(dec!(300)));
+    sleep(Duration::from_millis(250)).await;
+
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(200));
+    assert_eq!(index.exchange_prices.len(), 2);
+}
+
+/// Tests that a venue read at a changing interval follows its new interval.
+///
+/// This test verifies:
+/// 1. The venue is read at its initial interval
+/// 2. A changed interval applies without restarting the task
+#[tokio::test]
+async fn test_spawn_polling_follows_interval_changes() {
+    let aggregator = Aggregator::new(16);
+    let exchange = MockExchange::new("Mock", dec!(100));
+    let interval_ms = Arc::new(AtomicUsize::new(200));
+    let interval = interval_ms.clone();
+    aggregator.spawn_polling(exchange.clone(), move || {
+        Duration::from_millis(interval.load(Ordering::SeqCst) as u64)
+    });
+    sleep(Duration::from_millis(300)).await;
+    let fetches = exchange.fetches();
+    assert!(fetches <= 3, "{} fetches", fetches);
+
+    // Applied from the read following the change
+    interval_ms.store(10, Ordering::SeqCst);
+    sleep(Duration::from_millis(500)).await;
+    assert!(
+        exchange.fetches() >= fetches + 10,
+        "{} fetches",
+        exchange.fetches()
+    );
+}
+
+/// Tests that the index is not served from the last prices of unreachable venues.
+///
+/// This test verifies:
+/// 1. Failed reads re-evaluate the staleness of the venues
+/// 2. The venue that stopped answering leaves the index once stale, and the
+///    index loses its venue quorum
+/// 3. The index is withdrawn once every venue is stale
+#[tokio::test]
+async fn test_failed_reads_exclude_stale_venues() {
+    let settings = Settings::default();
+    let aggregator = Aggregator::new(16).with_settings(IndexSettings {
+        price_weighting: PriceWeighting {
+            max_age_ms: 100,
+            ..settings.price_weighting.clone()
+        },
+        ..IndexSettings::from(&settings)
+    });
+    let first = MockExchange::new("First", dec!(100));
+    let second = MockExchange::new("Second", dec!(200));
+    aggregator.spawn(first.clone(), Duration::from_millis(10));
+    aggregator.spawn(second.clone(), Duration::from_millis(10));
+    sleep(Duration::from_millis(50)).await;
+    assert!(aggregator.latest("BTC/USDT").unwrap().quorum_met);
+
+    second.set_mid_price(None);
+    sleep(Duration::from_millis(250)).await;
+    let index = aggregator.latest("BTC/USDT").unwrap();
+    assert_eq!(index.price, dec!(100));
+    assert!(!index.quorum_met);
+    assert_eq!(index.excluded.len(), 1);
+    assert_eq!(index.excluded[0].exchange, "Second");
+    assert_eq!(index.excluded[0].reason, ExclusionReason::Stale);
+
+    first.set_mid_price(None);
+    sleep(Duration::from_millis(250)).await;
+    assert!(aggregator.latest("BTC/USDT").is_none());
+}
+
+/// Tests that the HTTP handler serves the cached index without reading the
+/// exchanges.
+///
+/// This test verifies:
+/// 1. The response carries the aggregator's latest index
+/// 2. Requests do not trigger exchange fetches
+#[actix_web::test]
+async fn test_handler_reads_cached_index() {
+    let aggregator = single_venue_aggregator();
+    let exchange = MockExchange::new("Mock", dec!(100));
+    aggregator.spawn(exchange.clone(), Duration::from_secs(3600));
+    while aggregator.latest("BTC/USDT").is_none() {
+        sleep(Duration::from_millis(5)).await;
+    }
+
+    let app = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(AppState::new(
+                vec!["BTC/USDT".to_string()],
+                ExchangeRegistry::new(),
+                aggregator,
+            )))
+            .route("/global-price", web::get().to(get_global_price)),
+    )
+    .await;
+
+    let fetches = exchange.fetches();
+    for _ in 0..5 {
+        let req = test::TestRequest::get().uri("/global-price").to_request();
+        let index: GlobalPriceIndex = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(index.price, dec!(100));
+    }
+    assert_eq!(exchange.fetches(), fetches);
+}
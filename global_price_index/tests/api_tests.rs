@@ -1,10 +1,45 @@
 use actix_web::{test, web};
 use global_price_index::{
-    exchanges::{binance::BinanceExchange, huobi::HuobiExchange, kraken::KrakenExchange},
-    models::GlobalPriceIndex,
+    aggregator::Aggregator,
+    api::{resolve_symbol, AppState},
+    exchanges::{registry::ExchangeRegistry, ExchangeKind},
+    models::{Decimal, GlobalPriceIndex},
 };
-use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::time::{sleep, Duration, Instant};
+
+/// Creates the app state for BTC/USDT backed by the real exchanges
+///
+/// Waits until the aggregator has computed a first index.
+async fn btc_usdt_app_state() -> AppState {
+    let symbol = "BTC/USDT";
+    let registry = ExchangeRegistry::new();
+    let aggregator = Aggregator::new(16);
+    for kind in ExchangeKind::ALL {
+        registry.start(kind, symbol.to_string(), &aggregator);
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while aggregator.latest(symbol).is_none() {
+        assert!(
+            Instant::now() < deadline,
+            "No index computed for {}",
+            symbol
+        );
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    AppState::new(vec![symbol.to_string()], registry, aggregator)
+}
+
+/// Creates an app state indexing symbols that no exchange lists
+fn app_state_without_exchanges(symbols: &[&str]) -> AppState {
+    AppState::new(
+        symbols.iter().map(|symbol| symbol.to_string()).collect(),
+        ExchangeRegistry::new(),
+        Aggregator::new(16),
+    )
+}
 
 /// Tests the main global price endpoint to ensure it correctly
 /// aggregates price data from all exchanges and returns a valid response.
@@ -17,31 +52,10 @@ use std::time::SystemTime;
 /// 5. Exchange prices are included and valid
 #[actix_web::test]
 async fn test_global_price_endpoint() {
-    // Initialize exchanges
-    let binance = Arc::new(
-        BinanceExchange::new()
-            .await
-            .expect("Failed to create Binance exchange"),
-    );
-    let kraken = Arc::new(
-        KrakenExchange::new()
-            .await
-            .expect("Failed to create Kraken exchange"),
-    );
-    let huobi = Arc::new(
-        HuobiExchange::new()
-            .await
-            .expect("Failed to create Huobi exchange"),
-    );
-
     // Create test app
     let app = test::init_service(
         actix_web::App::new()
-            .app_data(web::Data::new(global_price_index::api::AppState {
-                binance,
-                kraken,
-                huobi,
-            }))
+            .app_data(web::Data::new(btc_usdt_app_state().await))
             .route(
                 "/global-price",
                 web::get().to(global_price_index::api::get_global_price),
@@ -61,13 +75,14 @@ async fn test_global_price_endpoint() {
     let global_index: GlobalPriceIndex = serde_json::from_slice(&body).unwrap();
 
     // Verify global price index structure
-    assert!(global_index.price > 0.0);
+    assert_eq!(global_index.symbol, "BTC/USDT");
+    assert!(global_index.price > Decimal::ZERO);
     assert!(global_index.timestamp <= SystemTime::now());
     assert!(!global_index.exchange_prices.is_empty());
 
     // Verify individual exchange prices are present
     for price in global_index.exchange_prices {
-        assert!(price.mid_price > 0.0);
+        assert!(price.mid_price > Decimal::ZERO);
         assert!(price.timestamp <= SystemTime::now());
     }
 }
@@ -79,29 +94,9 @@ async fn test_global_price_endpoint() {
 /// 1. The API returns a client error (4xx) status code for invalid paths
 #[actix_web::test]
 async fn test_error_handling() {
-    let binance = Arc::new(
-        BinanceExchange::new()
-            .await
-            .expect("Failed to create Binance exchange"),
-    );
-    let kraken = Arc::new(
-        KrakenExchange::new()
-            .await
-            .expect("Failed to create Kraken exchange"),
-    );
-    let huobi = Arc::new(
-        HuobiExchange::new()
-            .await
-            .expect("Failed to create Huobi exchange"),
-    );
-
     let app = test::init_service(
         actix_web::App::new()
-            .app_data(web::Data::new(global_price_index::api::AppState {
-                binance,
-                kraken,
-                huobi,
-            }))
+            .app_data(web::Data::new(btc_usdt_app_state().await))
             .route(
                 "/global-price",
                 web::get().to(global_price_index::api::get_global_price),
@@ -114,3 +109,66 @@ async fn test_error_handling() {
 
     assert!(resp.status().is_client_error());
 }
+
+/// Tests that symbols given in request paths resolve to indexed symbols.
+///
+/// This test verifies:
+/// 1. Dash, underscore and slash separators are accepted
+/// 2. Matching is case-insensitive
+/// 3. Symbols that are not indexed do not resolve
+#[actix_web::test]
+async fn test_resolve_symbol() {
+    let symbols = vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()];
+
+    assert_eq!(resolve_symbol(&symbols, "BTC-USDT"), Some("BTC/USDT"));
+    assert_eq!(resolve_symbol(&symbols, "eth_usdt"), Some("ETH/USDT"));
+    assert_eq!(resolve_symbol(&symbols, "ETH/USDT"), Some("ETH/USDT"));
+    assert_eq!(resolve_symbol(&symbols, "SOL-USDT"), None);
+    assert_eq!(resolve_symbol(&symbols, "BTCUSDT"), None);
+}
+
+/// Tests that the per-symbol endpoint routes requests by symbol.
+///
+/// This test verifies:
+/// 1. An unknown symbol is answered with 404 and the list of indexed symbols
+/// 2. An indexed symbol no exchange could price is answered with 503
+/// 3. The /symbols endpoint lists the indexed symbols in configuration order
+#[actix_web::test]
+async fn test_global_price_symbol_routing() {
+    let app = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(app_state_without_exchanges(&[
+                "BTC/USDT", "ETH/USDT",
+            ])))
+            .route(
+                "/global-price/{symbol}",
+                web::get().to(global_price_index::api::get_global_price_for_symbol),
+            )
+            .route(
+                "/symbols",
+                web::get().to(global_price_index::api::get_symbols),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/global-price/DOGE-USDT")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["symbols"], serde_json::json!(["BTC/USDT", "ETH/USDT"]));
+
+    let req = test::TestRequest::get()
+        .uri("/global-price/eth-usdt")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.status(),
+        actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    let req = test::TestRequest::get().uri("/symbols").to_request();
+    let symbols: Vec<String> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(symbols, vec!["BTC/USDT", "ETH/USDT"]);
+}
@@ -0,0 +1,356 @@
+mod common;
+
+use common::ws::{btc_usdt_market, start_ws_server, text, wait_for_book};
+use global_price_index::{
+    config::Settings,
+    exchanges::{binance::BinanceExchange, Exchange},
+    models::{timestamp_from_millis, Decimal},
+};
+use rust_decimal_macros::dec;
+use serde_json::json;
+use tokio::time::{sleep, Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Mounts a snapshot response on the mock REST server that is served once
+async fn mount_snapshot(server: &MockServer, snapshot: serde_json::Value) {
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(snapshot))
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+}
+
+/// Builds a diff-depth event in the Binance wire format
+fn depth_event(first: u64, last: u64, bids: serde_json::Value, asks: serde_json::Value) -> String {
+    json!({
+        "e": "depthUpdate",
+        "E": 1_700_000_000_000u64,
+        "s": "BTCUSDT",
+        "U": first,
+        "u": last,
+        "b": bids,
+        "a": asks,
+    })
+    .to_string()
+}
+
+/// Tests that events already covered by the snapshot are dropped and that
+/// the first event bridging the snapshot and the following ones are applied.
+///
+/// This test verifies:
+/// 1. An event with `u <= lastUpdateId` does not modify the book
+/// 2. An event with `U <= lastUpdateId + 1 <= u` is applied
+/// 3. A subsequent in-sequence event is applied and zero quantities remove levels
+/// 4. The book carries the event time of the last applied event
+#[tokio::test]
+async fn test_stale_events_dropped_and_sequence_applied() {
+    let rest_server = MockServer::start().await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 100,
+            "bids": [["100.0", "1.0"], ["99.0", "1.0"]],
+            "asks": [["101.0", "1.0"], ["102.0", "1.0"]],
+        }),
+    )
+    .await;
+    let server = start_ws_server(text).await;
+
+    let settings = Settings::default();
+    let exchange = BinanceExchange::with_urls(
+        btc_usdt_market("BTCUSDT"),
+        format!("{}/api/v3/depth", rest_server.uri()),
+        server.url,
+        &settings.exchange.binance.ticker_url,
+        &settings.exchange.config,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Binance exchange");
+
+    // Stale: would remove the best bid if applied
+    server
+        .frames
+        .send(depth_event(95, 100, json!([["100.0", "0"]]), json!([])))
+        .unwrap();
+    // Bridges the snapshot (U=99 <= 101 <= u=102)
+    server
+        .frames
+        .send(depth_event(99, 102, json!([["100.5", "2.0"]]), json!([])))
+        .unwrap();
+    // Continues the stream and removes the best ask
+    server
+        .frames
+        .send(depth_event(103, 103, json!([]), json!([["101.0", "0"]])))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.asks.first().map(|a| a.price) == Some(dec!(102.0))
+    })
+    .await;
+
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(100.5), dec!(100.0), dec!(99.0)]);
+    assert_eq!(order_book.asks.len(), 1);
+    assert_eq!(
+        order_book.exchange_timestamp,
+        Some(timestamp_from_millis(1_700_000_000_000))
+    );
+    assert_eq!(rest_server.received_requests().await.unwrap().len(), 1);
+}
+
+/// Tests that a gap in the update ids discards the book and triggers a new
+/// snapshot, on top of which buffered and later events are replayed.
+///
+/// This test verifies:
+/// 1. An event with `U > lastUpdateId + 1` triggers a second snapshot request
+/// 2. The book is replaced by the new snapshot
+/// 3. Buffered events covered by the new snapshot are dropped
+/// 4. Events continuing the new snapshot are applied
+#[tokio::test]
+async fn test_gap_triggers_resnapshot() {
+    let rest_server = MockServer::start().await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 100,
+            "bids": [["100.0", "1.0"]],
+            "asks": [["101.0", "1.0"]],
+        }),
+    )
+    .await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 200,
+            "bids": [["200.0", "1.0"]],
+            "asks": [["201.0", "1.0"]],
+        }),
+    )
+    .await;
+    let server = start_ws_server(text).await;
+
+    let settings = Settings::default();
+    let exchange = BinanceExchange::with_urls(
+        btc_usdt_market("BTCUSDT"),
+        format!("{}/api/v3/depth", rest_server.uri()),
+        server.url,
+        &settings.exchange.binance.ticker_url,
+        &settings.exchange.config,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Binance exchange");
+
+    // Gap: updates 101..149 were never received
+    server
+        .frames
+        .send(depth_event(150, 151, json!([["150.0", "1.0"]]), json!([])))
+        .unwrap();
+    // Continues the second snapshot
+    server
+        .frames
+        .send(depth_event(201, 201, json!([["200.5", "1.0"]]), json!([])))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.bids.first().map(|b| b.price) == Some(dec!(200.5))
+    })
+    .await;
+
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(200.5), dec!(200.0)]);
+    assert_eq!(order_book.asks.first().map(|a| a.price), Some(dec!(201.0)));
+    assert_eq!(rest_server.received_requests().await.unwrap().len(), 2);
+}
+
+/// Tests that a snapshot older than the buffered events is discarded and
+/// another snapshot is fetched.
+///
+/// This test verifies:
+/// 1. A snapshot whose lastUpdateId precedes the first buffered event is not used
+/// 2. The book is synchronized once a recent enough snapshot is received
+#[tokio::test]
+async fn test_outdated_snapshot_is_refetched() {
+    let rest_server = MockServer::start().await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 100,
+            "bids": [["100.0", "1.0"]],
+            "asks": [["101.0", "1.0"]],
+        }),
+    )
+    .await;
+    // Too old for the buffered event starting at U=250
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 120,
+            "bids": [["120.0", "1.0"]],
+            "asks": [["121.0", "1.0"]],
+        }),
+    )
+    .await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 300,
+            "bids": [["300.0", "1.0"]],
+            "asks": [["301.0", "1.0"]],
+        }),
+    )
+    .await;
+    let server = start_ws_server(text).await;
+
+    let settings = Settings::default();
+    let exchange = BinanceExchange::with_urls(
+        btc_usdt_market("BTCUSDT"),
+        format!("{}/api/v3/depth", rest_server.uri()),
+        server.url,
+        &settings.exchange.binance.ticker_url,
+        &settings.exchange.config,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Binance exchange");
+
+    server
+        .frames
+        .send(depth_event(250, 260, json!([["250.0", "1.0"]]), json!([])))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.bids.first().map(|b| b.price) == Some(dec!(300.0))
+    })
+    .await;
+
+    assert_eq!(order_book.bids.len(), 1);
+    assert_eq!(rest_server.received_requests().await.unwrap().len(), 3);
+}
+
+/// Tests that the stream is opened before the snapshot is fetched.
+///
+/// This test verifies:
+/// 1. The WebSocket connection is open while the snapshot request is pending
+/// 2. Events received before the snapshot are buffered, those covered by it
+///    dropped and the following ones applied
+/// 3. A single snapshot is requested
+#[tokio::test]
+async fn test_stream_opened_before_snapshot() {
+    let rest_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "lastUpdateId": 100,
+                    "bids": [["100.0", "1.0"]],
+                    "asks": [["101.0", "1.0"]],
+                }))
+                .set_delay(Duration::from_millis(500)),
+        )
+        .mount(&rest_server)
+        .await;
+    let mut server = start_ws_server(text).await;
+
+    let settings = Settings::default();
+    let (exchange, ()) = tokio::join!(
+        BinanceExchange::with_urls(
+            btc_usdt_market("BTCUSDT"),
+            format!("{}/api/v3/depth", rest_server.uri()),
+            server.url.clone(),
+            &settings.exchange.binance.ticker_url,
+            &settings.exchange.config,
+            &settings.capture,
+        ),
+        async {
+            tokio::time::timeout(Duration::from_millis(250), server.connections.recv())
+                .await
+                .expect("Stream not opened before the snapshot")
+                .unwrap();
+
+            // Covered by the snapshot, then continuing it
+            server
+                .frames
+                .send(depth_event(90, 100, json!([["100.0", "0"]]), json!([])))
+                .unwrap();
+            server
+                .frames
+                .send(depth_event(101, 101, json!([["100.5", "1.0"]]), json!([])))
+                .unwrap();
+        }
+    );
+    let exchange = exchange.expect("Failed to create Binance exchange");
+    let order_book = wait_for_book(&exchange, |book| {
+        book.bids.first().map(|b| b.price) == Some(dec!(100.5))
+    })
+    .await;
+
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(100.5), dec!(100.0)]);
+    assert_eq!(rest_server.received_requests().await.unwrap().len(), 1);
+}
+
+/// Tests that an out-of-sync book is not served until it is resynchronized.
+///
+/// This test verifies:
+/// 1. The book is served once synchronized
+/// 2. After a gap, fetching the book fails while the new snapshot is pending
+/// 3. The book is served again once the new snapshot is applied
+#[tokio::test]
+async fn test_book_withheld_while_resynchronizing() {
+    let rest_server = MockServer::start().await;
+    mount_snapshot(
+        &rest_server,
+        json!({
+            "lastUpdateId": 100,
+            "bids": [["100.0", "1.0"]],
+            "asks": [["101.0", "1.0"]],
+        }),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "lastUpdateId": 200,
+                    "bids": [["200.0", "1.0"]],
+                    "asks": [["201.0", "1.0"]],
+                }))
+                .set_delay(Duration::from_millis(500)),
+        )
+        .mount(&rest_server)
+        .await;
+    let server = start_ws_server(text).await;
+
+    let settings = Settings::default();
+    let exchange = BinanceExchange::with_urls(
+        btc_usdt_market("BTCUSDT"),
+        format!("{}/api/v3/depth", rest_server.uri()),
+        server.url,
+        &settings.exchange.binance.ticker_url,
+        &settings.exchange.config,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Binance exchange");
+    assert!(exchange.fetch_order_book().await.is_ok());
+
+    // Gap: updates 101..149 were never received
+    server
+        .frames
+        .send(depth_event(150, 151, json!([["150.0", "1.0"]]), json!([])))
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while exchange.fetch_order_book().await.is_ok() {
+        assert!(Instant::now() < deadline, "Out-of-sync book still served");
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let order_book = wait_for_book(&exchange, |_| true).await;
+    assert_eq!(order_book.bids.first().map(|b| b.price), Some(dec!(200.0)));
+}
@@ -0,0 +1,19 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+pub mod ws;
+
+use global_price_index::models::{Decimal, ExchangePrice, Liquidity};
+use std::time::SystemTime;
+
+/// Builds a price quoted by an exchange now
+pub fn price(exchange: &str, mid_price: Decimal) -> ExchangePrice {
+    ExchangePrice {
+        exchange: exchange.to_string(),
+        mid_price,
+        timestamp: SystemTime::now(),
+        liquidity: Liquidity::default(),
+        exchange_timestamp: None,
+        latency_ms: None,
+    }
+}
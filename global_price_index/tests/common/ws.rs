@@ -0,0 +1,177 @@
+//! WebSocket server standing in for the exchange feeds
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{SinkExt, StreamExt};
+use global_price_index::{
+    config::MarketConfig, error::Result, exchanges::Exchange, models::OrderBook,
+};
+use std::future::Future;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Local WebSocket server standing in for an exchange feed
+///
+/// Frames sent on `frames` are encoded and forwarded to the connected
+/// client, those sent before it connects being queued. The text frames the
+/// client sends are parsed as JSON and forwarded on `requests`, the number
+/// of each connection accepted on `connections` and the code of each close
+/// frame received on `closes`.
+pub struct WsServer<T> {
+    pub url: String,
+    pub frames: mpsc::UnboundedSender<T>,
+    pub requests: mpsc::UnboundedReceiver<serde_json::Value>,
+    pub connections: mpsc::UnboundedReceiver<usize>,
+    pub closes: mpsc::UnboundedReceiver<Option<CloseCode>>,
+}
+
+/// Encodes frames as text frames
+pub fn text(frame: String) -> Message {
+    Message::Text(frame)
+}
+
+/// Encodes JSON messages as gzip-compressed binary frames, as Huobi sends them
+pub fn gzip_json(frame: serde_json::Value) -> Message {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(frame.to_string().as_bytes()).unwrap();
+    Message::Binary(encoder.finish().unwrap())
+}
+
+/// Starts a stand-in server sending the frames encoded by `encode`
+///
+/// Every client that connects is served in turn, so a client reconnecting
+/// receives the frames its previous connection did not.
+pub async fn start_ws_server<T, E>(encode: E) -> WsServer<T>
+where
+    T: Send + 'static,
+    E: Fn(T) -> Message + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    let (connections_tx, connections_rx) = mpsc::unbounded_channel();
+    let (closes_tx, closes_rx) = mpsc::unbounded_channel();
+    let frames_rx = Arc::new(Mutex::new(frames_rx));
+    let encode = Arc::new(encode);
+
+    tokio::spawn(async move {
+        for connection in 1.. {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (frames_rx, encode) = (frames_rx.clone(), encode.clone());
+            let (requests_tx, closes_tx) = (requests_tx.clone(), closes_tx.clone());
+            let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let _ = connections_tx.send(connection);
+
+            tokio::spawn(async move {
+                let mut frames = frames_rx.lock().await;
+                // Client frames are read throughout so pings are answered
+                loop {
+                    tokio::select! {
+                        frame = frames.recv() => match frame {
+                            Some(frame) => {
+                                if ws_stream.send(encode(frame)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            None => break,
+                        },
+                        message = ws_stream.next() => match message {
+                            Some(Ok(Message::Text(text))) => {
+                                if let Ok(request) = serde_json::from_str(&text) {
+                                    let _ = requests_tx.send(request);
+                                }
+                            }
+                            Some(Ok(Message::Close(frame))) => {
+                                let _ = closes_tx.send(frame.map(|frame| frame.code));
+                                break;
+                            }
+                            Some(Ok(_)) => {}
+                            _ => break,
+                        },
+                    }
+                }
+            });
+        }
+    });
+
+    WsServer {
+        url: format!("ws://{}/ws", addr),
+        frames: frames_tx,
+        requests: requests_rx,
+        connections: connections_rx,
+        closes: closes_rx,
+    }
+}
+
+impl<T> WsServer<T> {
+    /// Receives the next request sent by the client
+    pub async fn next_request(&mut self) -> serde_json::Value {
+        timeout(Duration::from_secs(5), self.requests.recv())
+            .await
+            .expect("No request received from the client")
+            .unwrap()
+    }
+
+    /// Connects an exchange to the server and answers its subscription
+    ///
+    /// The exchange is created by `connecting` in the background while its
+    /// subscription request is handed to `answer`, whose frames are sent
+    /// back to it.
+    pub async fn connect<X, F>(
+        &mut self,
+        connecting: F,
+        answer: impl FnOnce(serde_json::Value) -> Vec<T>,
+    ) -> X
+    where
+        X: Send + 'static,
+        F: Future<Output = Result<X>> + Send + 'static,
+    {
+        let exchange = tokio::spawn(connecting);
+
+        let request = self.next_request().await;
+        for frame in answer(request) {
+            self.frames.send(frame).unwrap();
+        }
+
+        exchange.await.unwrap().expect("Failed to create exchange")
+    }
+}
+
+/// Returns a BTC/USDT market listed under the given exchange symbol
+pub fn btc_usdt_market(exchange_symbol: &str) -> MarketConfig {
+    MarketConfig {
+        symbol: "BTC/USDT".to_string(),
+        exchange_symbol: exchange_symbol.to_string(),
+        ws_symbol: None,
+        price_precision: 2,
+        quantity_precision: 8,
+        enabled: true,
+    }
+}
+
+/// Polls the exchange until its order book satisfies the condition or 5 seconds pass
+///
+/// Failed reads, e.g. while the book resynchronizes, are retried.
+pub async fn wait_for_book<F>(exchange: &impl Exchange, condition: F) -> OrderBook
+where
+    F: Fn(&OrderBook) -> bool,
+{
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let order_book = exchange.fetch_order_book().await;
+        if let Some(order_book) = order_book.as_ref().ok().filter(|book| condition(book)) {
+            return order_book.clone();
+        }
+        assert!(
+            Instant::now() < deadline,
+            "Order book did not reach the expected state: {:?}",
+            order_book
+        );
+        sleep(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,354 @@
+use clap::Parser;
+use global_price_index::{
+    cli::{check_config, Cli},
+    config::{ConfigSources, Settings, CONFIG_FILE},
+    error::PriceIndexError,
+};
+use std::path::PathBuf;
+
+/// Writes a configuration file to a path in the temporary directory unique to this test run
+fn write_config(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "global_price_index_config_{}_{}.toml",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Returns the sources of a configuration file, without the environment
+fn file_sources(file: PathBuf, overrides: &[(&str, &str)]) -> ConfigSources {
+    ConfigSources {
+        file,
+        required: true,
+        env: false,
+        overrides: overrides
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+/// Tests that the settings are layered over the default values.
+///
+/// This test verifies:
+/// 1. Sections and keys missing from the file keep their default values
+/// 2. The file overrides the defaults
+/// 3. Command line overrides take precedence over the file
+/// 4. Lists are given as comma-separated values
+#[test]
+fn test_settings_are_layered() {
+    let path = write_config(
+        "layered",
+        "[server]\napi_port = 9000\n\n[price_weighting]\ndecay_factor = 100\n",
+    );
+    let defaults = Settings::default();
+
+    let settings = Settings::from_sources(&file_sources(path.clone(), &[])).unwrap();
+    assert_eq!(settings.server.api_port, 9000);
+    assert_eq!(settings.server.api_host, defaults.server.api_host);
+    assert_eq!(settings.price_weighting.decay_factor, 100.0);
+    assert_eq!(
+        settings.price_weighting.max_age_ms,
+        defaults.price_weighting.max_age_ms
+    );
+    assert_eq!(settings.exchange.binance, defaults.exchange.binance);
+
+    let settings = Settings::from_sources(&file_sources(
+        path.clone(),
+        &[
+            ("server.api_port", "9100"),
+            ("index.symbols", "ETH/USDT,BTC/USDT"),
+            ("price_weighting.method", "volume"),
+        ],
+    ))
+    .unwrap();
+    assert_eq!(settings.server.api_port, 9100);
+    assert_eq!(settings.index.symbols, vec!["ETH/USDT", "BTC/USDT"]);
+    assert!(settings.price_weighting.method.uses_liquidity());
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Tests that `GPI_` environment variables override the configuration file.
+///
+/// This test verifies:
+/// 1. Nested keys are separated by a double underscore
+/// 2. Lists are given as comma-separated values
+/// 3. Command line overrides take precedence over the environment
+#[test]
+fn test_environment_overrides_file() {
+    std::env::set_var("GPI_SERVER__API_PORT", "9200");
+    std::env::set_var("GPI_OUTLIER_FILTER__ENABLED", "false");
+    std::env::set_var("GPI_INDEX__SYMBOLS", "SOL/USDT,BTC/USDT");
+    let sources = ConfigSources::default();
+
+    let settings = Settings::from_sources(&sources).unwrap();
+    assert_eq!(settings.server.api_port, 9200);
+    assert!(!settings.outlier_filter.enabled);
+    assert_eq!(settings.index.symbols, vec!["SOL/USDT", "BTC/USDT"]);
+
+    let settings = Settings::from_sources(&ConfigSources {
+        overrides: vec![("server.api_port".to_string(), "9300".to_string())],
+        ..sources
+    })
+    .unwrap();
+    assert_eq!(settings.server.api_port, 9300);
+
+    std::env::remove_var("GPI_SERVER__API_PORT");
+    std::env::remove_var("GPI_OUTLIER_FILTER__ENABLED");
+    std::env::remove_var("GPI_INDEX__SYMBOLS");
+}
+
+/// Tests that the command line arguments select the sources of the settings.
+///
+/// This test verifies:
+/// 1. The configuration file defaults to config.toml and the environment is read
+/// 2. `--config` selects another file
+/// 3. The dedicated options take precedence over `--set` values of the same key
+/// 4. `--set` arguments without a key are rejected
+#[test]
+fn test_command_line_sources() {
+    let sources = Cli::try_parse_from(["global_price_index"])
+        .unwrap()
+        .config_sources();
+    assert_eq!(sources.file, PathBuf::from(CONFIG_FILE));
+    assert!(!sources.required);
+    assert!(sources.env);
+    assert!(sources.overrides.is_empty());
+
+    let path = write_config("cli", "[server]\napi_port = 9400\n");
+    let cli = Cli::try_parse_from([
+        "global_price_index",
+        "--config",
+        path.to_str().unwrap(),
+        "--set",
+        "server.api_port=9500",
+        "--set",
+        "health.min_venues=1",
+        "--api-port",
+        "9600",
+        "--symbols",
+        "ETH/USDT",
+    ])
+    .unwrap();
+    assert!(cli.config_sources().required);
+    let settings = Settings::from_sources(&ConfigSources {
+        env: false,
+        ..cli.config_sources()
+    })
+    .unwrap();
+    assert_eq!(settings.server.api_port, 9600);
+    assert_eq!(settings.health.min_venues, 1);
+    assert_eq!(settings.index.symbols, vec!["ETH/USDT"]);
+
+    assert!(Cli::try_parse_from(["global_price_index", "--set", "=1"]).is_err());
+    assert!(Cli::try_parse_from(["global_price_index", "--set", "server.api_port"]).is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Tests that configuration errors are reported instead of falling back to defaults.
+///
+/// This test verifies:
+/// 1. A missing configuration file is an error when it is required, and
+///    the default values are used otherwise
+/// 2. A misspelt key is an error naming the key
+/// 3. A value of the wrong type is an error
+#[test]
+fn test_invalid_configuration_is_reported() {
+    let missing = std::env::temp_dir().join("global_price_index_missing.toml");
+    assert!(Settings::from_sources(&file_sources(missing.clone(), &[])).is_err());
+    let settings = Settings::from_sources(&ConfigSources {
+        required: false,
+        ..file_sources(missing, &[])
+    })
+    .unwrap();
+    assert_eq!(
+        settings.server.api_port,
+        Settings::default().server.api_port
+    );
+
+    let path = write_config("misspelt", "[server]\napi_prot = 9000\n");
+    let error = Settings::from_sources(&file_sources(path.clone(), &[]))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("api_prot"), "{}", error);
+    let _ = std::fs::remove_file(&path);
+
+    let path = write_config("mistyped", "[server]\napi_port = \"high\"\n");
+    assert!(Settings::from_sources(&file_sources(path.clone(), &[])).is_err());
+    assert!(Settings::from_sources(&file_sources(
+        CONFIG_FILE.into(),
+        &[("price_weighting.method", "fastest")]
+    ))
+    .is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Tests that validation reports every problem of the settings at once.
+///
+/// This test verifies:
+/// 1. The checked-in configuration and the default values are valid, also
+///    when only some of the symbols with markets are indexed
+/// 2. Each problem names its key, e.g. a zero decay factor, a reconnect delay
+///    range whose maximum is below its initial delay, a WebSocket URL with an
+///    HTTP scheme and API and frontend servers on the same port
+/// 3. The problems are returned as a `PriceIndexError::ConfigError`
+#[test]
+fn test_validation_reports_every_problem() {
+    assert!(Settings::load(CONFIG_FILE).unwrap().validate().is_ok());
+    assert!(Settings::default().validate().is_ok());
+    let mut settings = Settings::load(CONFIG_FILE).unwrap();
+    settings.index.symbols = vec!["BTC/USDT".to_string()];
+    assert!(settings.validate().is_ok());
+
+    let mut settings = Settings::default();
+    settings.price_weighting.decay_factor = 0.0;
+    settings.exchange.config.initial_reconnect_delay = 10;
+    settings.exchange.config.max_reconnect_delay = 5;
+    settings.exchange.kraken.ws_url = "https://ws.kraken.com".to_string();
+    settings.server.frontend_port = settings.server.api_port;
+    settings.index.symbols.push("BTCUSDT".to_string());
+
+    match settings.validate() {
+        Err(PriceIndexError::ConfigError(problems)) => {
+            assert_eq!(problems.len(), 5, "{:?}", problems);
+            for key in [
+                "price_weighting.decay_factor",
+                "exchange.config.max_reconnect_delay",
+                "exchange.kraken.ws_url",
+                "server.frontend_port",
+                "index.symbols",
+            ] {
+                assert!(
+                    problems.iter().any(|problem| problem.contains(key)),
+                    "No problem reported for {}: {:?}",
+                    key,
+                    problems
+                );
+            }
+        }
+        other => panic!("Expected a configuration error, got {:?}", other),
+    }
+
+    settings.server.frontend_host = "10.0.0.1".to_string();
+    let problems = settings.validate().unwrap_err().to_string();
+    assert!(!problems.contains("server.frontend_port"), "{}", problems);
+}
+
+/// Tests that values the service cannot run with are rejected.
+///
+/// This test verifies:
+/// 1. A zero ping retry count, venue quorum, maximum price age, close or
+///    drain timeout or reload debounce delay is rejected
+/// 2. A price or quantity precision beyond the 28 decimal places of a
+///    Decimal is rejected
+/// 3. A default stream throttle beyond one hour is rejected
+/// 4. A negative or non-finite VWAP notional or impact size is rejected
+#[test]
+fn test_validation_rejects_unusable_values() {
+    let problems = |change: &dyn Fn(&mut Settings)| {
+        let mut settings = Settings::default();
+        change(&mut settings);
+        match settings.validate() {
+            Err(PriceIndexError::ConfigError(problems)) => problems,
+            other => panic!("Expected a configuration error, got {:?}", other),
+        }
+    };
+
+    let reported = problems(&|settings| settings.exchange.config.ping_retry_count = 0);
+    assert_eq!(
+        reported,
+        vec!["exchange.config.ping_retry_count must be positive"]
+    );
+    let reported = problems(&|settings| settings.health.min_venues = 0);
+    assert_eq!(reported, vec!["health.min_venues must be positive"]);
+    let reported = problems(&|settings| settings.price_weighting.max_age_ms = 0);
+    assert_eq!(
+        reported,
+        vec!["price_weighting.max_age_ms must be positive"]
+    );
+    let reported = problems(&|settings| settings.exchange.config.close_timeout = 0);
+    assert_eq!(
+        reported,
+        vec!["exchange.config.close_timeout must be positive"]
+    );
+    let reported = problems(&|settings| settings.server.drain_timeout_secs = 0);
+    assert_eq!(reported, vec!["server.drain_timeout_secs must be positive"]);
+    let reported = problems(&|settings| settings.reload.debounce_ms = 0);
+    assert_eq!(reported, vec!["reload.debounce_ms must be positive"]);
+    let reported = problems(&|settings| settings.stream.default_throttle_ms = u64::MAX);
+    assert_eq!(reported.len(), 1, "{:?}", reported);
+    assert!(reported[0].starts_with("stream.default_throttle_ms must not exceed"));
+    for value in [f64::NAN, f64::INFINITY, -1.0] {
+        let reported = problems(&|settings| {
+            settings.mid_price.vwap_notional = value;
+            settings.mid_price.impact_size = value;
+        });
+        assert_eq!(reported.len(), 2, "{:?}", reported);
+        assert!(reported[0].starts_with("mid_price.vwap_notional must be positive"));
+        assert!(reported[1].starts_with("mid_price.impact_size must be positive"));
+    }
+
+    let reported = problems(&|settings| {
+        settings.exchange.binance.markets[0].price_precision = 29;
+    });
+    assert_eq!(reported.len(), 1, "{:?}", reported);
+    assert!(reported[0].starts_with("exchange.binance.markets: BTC/USDT price_precision"));
+    let reported = problems(&|settings| {
+        settings.exchange.huobi.markets[0].quantity_precision = 29;
+    });
+    assert_eq!(reported.len(), 1, "{:?}", reported);
+    assert!(reported[0].starts_with("exchange.huobi.markets"));
+}
+
+/// Tests the check-config command.
+///
+/// This test verifies:
+/// 1. The effective configuration is printed as TOML that loads back to the same settings
+/// 2. A valid configuration succeeds
+/// 3. An invalid configuration fails and lists every problem
+/// 4. A configuration that cannot be loaded fails with the error
+#[test]
+fn test_check_config() {
+    let check = |overrides: &[(&str, &str)]| {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let valid = check_config(
+            &file_sources(CONFIG_FILE.into(), overrides),
+            &mut out,
+            &mut err,
+        )
+        .unwrap();
+        (
+            valid,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    };
+
+    let (valid, out, _) = check(&[("server.api_port", "9700")]);
+    assert!(valid);
+    let path = write_config("effective", &out);
+    let settings = Settings::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(settings.server.api_port, 9700);
+    assert_eq!(
+        settings.exchange.kraken,
+        Settings::load(CONFIG_FILE).unwrap().exchange.kraken
+    );
+    let _ = std::fs::remove_file(&path);
+
+    let (valid, out, err) = check(&[
+        ("price_weighting.max_weight", "2"),
+        ("stream.channel_capacity", "0"),
+    ]);
+    assert!(!valid);
+    assert!(out.contains("channel_capacity = 0"));
+    assert!(err.contains("2 problem(s)"), "{}", err);
+    assert!(err.contains("price_weighting.max_weight"));
+    assert!(err.contains("stream.channel_capacity"));
+
+    let (valid, out, err) = check(&[("server.api_port", "high")]);
+    assert!(!valid);
+    assert!(out.is_empty());
+    assert!(err.contains("server.api_port"), "{}", err);
+}
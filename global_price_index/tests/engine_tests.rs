@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use global_price_index::{
+    config::{PriceWeighting, Settings},
+    engine::PriceIndexEngine,
+    error::{PriceIndexError, Result},
+    exchanges::Exchange,
+    models::{Decimal, ExclusionReason, Order, OrderBook},
+};
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::{timeout, Duration};
+
+/// Exchange stand-in quoting a fixed BTC/USDT book around a mid price
+struct FixedExchange {
+    name: &'static str,
+    mid_price: Decimal,
+    stopped: AtomicBool,
+}
+
+impl FixedExchange {
+    fn new(name: &'static str, mid_price: Decimal) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            mid_price,
+            stopped: AtomicBool::new(false),
+        })
+    }
+}
+
+#[async_trait]
+impl Exchange for FixedExchange {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        Ok(OrderBook {
+            bids: vec![Order {
+                price: self.mid_price - dec!(1),
+                quantity: dec!(1),
+            }],
+            asks: vec![Order {
+                price: self.mid_price + dec!(1),
+                quantity: dec!(1),
+            }],
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        })
+    }
+}
+
+/// Tests that the engine computes the index of its exchanges without an HTTP server.
+///
+/// This test verifies:
+/// 1. The index is published to subscribers once every venue was read
+/// 2. The explicit weighting settings and the default outlier filter are applied
+/// 3. The latest index is returned for an indexed symbol only
+/// 4. Shutting down stops the exchange feeds and ends the subscriptions
+#[tokio::test]
+async fn test_engine_computes_index() {
+    let exchanges = [
+        FixedExchange::new("Binance", dec!(100)),
+        FixedExchange::new("Kraken", dec!(102)),
+        FixedExchange::new("Huobi", dec!(200)),
+    ];
+    // Weight the venues equally whatever the age of their prices
+    let weighting = PriceWeighting {
+        decay_factor: 1e12,
+        ..Settings::default().price_weighting
+    };
+    let mut builder = PriceIndexEngine::builder()
+        .symbols(["BTC/USDT"])
+        .weighting(weighting)
+        .poll_interval(Duration::from_millis(10));
+    for exchange in &exchanges {
+        builder = builder.exchange(exchange.clone());
+    }
+    let engine = builder.start().expect("Failed to start the engine");
+
+    let mut updates = engine.subscribe().into_stream();
+    let index = timeout(Duration::from_secs(5), async {
+        loop {
+            let index = updates.next().await.expect("Subscription ended");
+            if index.exchange_prices.len() == 3 {
+                return index;
+            }
+        }
+    })
+    .await
+    .expect("No index of every venue published");
+
+    assert_eq!(index.symbol, "BTC/USDT");
+    assert_eq!(index.price, dec!(101));
+    assert_eq!(index.excluded.len(), 1);
+    assert_eq!(index.excluded[0].exchange, "Huobi");
+    assert_eq!(index.excluded[0].reason, ExclusionReason::MaxDeviation);
+    assert_eq!(engine.latest("BTC/USDT").unwrap().price, dec!(101));
+    assert!(engine.latest("ETH/USDT").is_none());
+
+    engine.shutdown().await;
+    assert!(exchanges
+        .iter()
+        .all(|exchange| exchange.stopped.load(Ordering::SeqCst)));
+    let ended = timeout(Duration::from_secs(5), async {
+        while updates.next().await.is_some() {}
+    })
+    .await;
+    assert!(ended.is_ok(), "Subscription still open after shutdown");
+}
+
+/// Tests that an invalid engine setup is reported before anything starts.
+///
+/// This test verifies:
+/// 1. Missing symbols, exchanges of unindexed symbols and an empty update
+///    buffer are all reported at once
+/// 2. The problems are returned as a `PriceIndexError::ConfigError`
+#[tokio::test]
+async fn test_engine_rejects_invalid_setup() {
+    let result = PriceIndexEngine::builder()
+        .exchange(FixedExchange::new("Binance", dec!(100)))
+        .channel_capacity(0)
+        .start();
+
+    match result {
+        Err(PriceIndexError::ConfigError(problems)) => {
+            assert_eq!(problems.len(), 3, "{:?}", problems);
+            assert!(problems[0].contains("symbols"));
+            assert!(problems[1].contains("Binance tracks BTC/USDT"));
+            assert!(problems[2].contains("channel_capacity"));
+        }
+        Err(e) => panic!("Expected a configuration error, got {}", e),
+        Ok(_) => panic!("Started an engine without symbols"),
+    }
+}
@@ -1,8 +1,10 @@
 use global_price_index::{
+    config::{Settings, CONFIG_FILE},
     error::Result,
     exchanges::{binance::BinanceExchange, huobi::HuobiExchange, kraken::KrakenExchange, Exchange},
-    models::{Order, OrderBook},
+    models::{Decimal, Order, OrderBook},
 };
+use rust_decimal_macros::dec;
 use std::time::SystemTime;
 
 /// Tests that the Binance exchange correctly provides order book data
@@ -10,13 +12,20 @@ use std::time::SystemTime;
 ///
 /// This test verifies:
 /// 1. The order book contains both bids and asks
-/// 2. All prices are valid finite numbers
+/// 2. All prices are positive
 /// 3. The timestamp is current (not in the future)
 ///
 /// Integration test that connects to the real Binance API.
 #[tokio::test]
 async fn test_binance_order_book_calculation() -> Result<()> {
-    let exchange = BinanceExchange::new().await?;
+    let settings = Settings::load(CONFIG_FILE)?;
+    let exchange = BinanceExchange::new(
+        "BTC/USDT",
+        &settings.exchange.binance,
+        &settings.exchange.config,
+        &settings.capture,
+    )
+    .await?;
     let order_book = exchange.fetch_order_book().await?;
 
     // Verify the order book structure
@@ -25,11 +34,11 @@ async fn test_binance_order_book_calculation() -> Result<()> {
 
     // Verify price format
     for Order { price, .. } in order_book.bids.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     for Order { price, .. } in order_book.asks.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     // Verify timestamp
@@ -43,24 +52,26 @@ async fn test_binance_order_book_calculation() -> Result<()> {
 ///
 /// This test verifies:
 /// 1. The order book contains both bids and asks
-/// 2. All prices are valid finite numbers
+/// 2. All prices are positive
 /// 3. The timestamp is current (not in the future)
 ///
 /// Integration test that connects to the real Kraken API.
 #[tokio::test]
 async fn test_kraken_order_book_calculation() -> Result<()> {
-    let exchange = KrakenExchange::new().await?;
+    let settings = Settings::load(CONFIG_FILE)?;
+    let exchange =
+        KrakenExchange::new("BTC/USDT", &settings.exchange.kraken, &settings.capture).await?;
     let order_book = exchange.fetch_order_book().await?;
 
     assert!(!order_book.bids.is_empty());
     assert!(!order_book.asks.is_empty());
 
     for Order { price, .. } in order_book.bids.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     for Order { price, .. } in order_book.asks.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     assert!(order_book.timestamp <= SystemTime::now());
@@ -73,24 +84,26 @@ async fn test_kraken_order_book_calculation() -> Result<()> {
 ///
 /// This test verifies:
 /// 1. The order book contains both bids and asks
-/// 2. All prices are valid finite numbers
+/// 2. All prices are positive
 /// 3. The timestamp is current (not in the future)
 ///
 /// Integration test that connects to the real Huobi API.
 #[tokio::test]
 async fn test_huobi_orderbook_calculation() -> Result<()> {
-    let exchange = HuobiExchange::new().await?;
+    let settings = Settings::load(CONFIG_FILE)?;
+    let exchange =
+        HuobiExchange::new("BTC/USDT", &settings.exchange.huobi, &settings.capture).await?;
     let order_book = exchange.fetch_order_book().await?;
 
     assert!(!order_book.bids.is_empty());
     assert!(!order_book.asks.is_empty());
 
     for Order { price, .. } in order_book.bids.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     for Order { price, .. } in order_book.asks.iter() {
-        assert!(price.is_finite());
+        assert!(*price > Decimal::ZERO);
     }
 
     assert!(order_book.timestamp <= SystemTime::now());
@@ -109,10 +122,10 @@ async fn test_huobi_orderbook_calculation() -> Result<()> {
 /// Integration test that connects to the real Binance API.
 #[tokio::test]
 async fn test_mid_price_calculation() -> Result<()> {
-    let exchange = BinanceExchange::new().await?;
+    let exchange = BinanceExchange::configured("BTC/USDT").await?;
     let price = exchange.get_mid_price().await?;
 
-    assert!(price.mid_price > 0.0);
+    assert!(price.mid_price > Decimal::ZERO);
     assert!(!price.exchange.is_empty());
     assert!(price.timestamp <= SystemTime::now());
 
@@ -124,40 +137,39 @@ async fn test_mid_price_calculation() -> Result<()> {
 ///
 /// This test verifies:
 /// 1. The mid price is correctly calculated as the average of best bid and best ask
-/// 2. The rounding to 2 decimal places works correctly
+/// 2. The result is exact and keeps the precision of the quotes
 ///
-/// Using a tolerance of 0.01 (a difference < 1 cent) because:
-/// - This is a simple mathematical calculation
-/// - We expect exact results to the cent level
-/// - No complex functions are involved
+/// Using exact equality because prices are decimals and the mid price
+/// of two quotes is always representable exactly.
 #[test]
 fn test_order_book_mid_price_calculation() {
     let order_book = OrderBook {
         bids: vec![
             // Best bid: 2.0 BTC at 50,000 USDT (highest price someone will buy at)
             Order {
-                price: 50000.0,
-                quantity: 2.0,
+                price: dec!(50000.0),
+                quantity: dec!(2.0),
             },
             // 3.0 BTC at 49,900 USDT
             Order {
-                price: 49900.0,
-                quantity: 3.0,
+                price: dec!(49900.0),
+                quantity: dec!(3.0),
             },
         ],
         asks: vec![
             // Best ask: 1.0 BTC at 50,100 USDT (lowest price someone will sell at)
             Order {
-                price: 50100.0,
-                quantity: 1.0,
+                price: dec!(50100.0),
+                quantity: dec!(1.0),
             },
             // 2.0 BTC at 50,200 USDT
             Order {
-                price: 50200.0,
-                quantity: 2.0,
+                price: dec!(50200.0),
+                quantity: dec!(2.0),
             },
         ],
         timestamp: SystemTime::now(),
+        exchange_timestamp: None,
     };
 
     let mid_price = order_book.calculate_mid_price().unwrap();
@@ -166,8 +178,8 @@ fn test_order_book_mid_price_calculation() {
     // Best bid: 50000.0 (highest buy price)
     // Best ask: 50100.0 (lowest sell price)
     // Mid price: (50000.0 + 50100.0) / 2 = 50050.0
-    // Implementation rounds to 2 decimal places
-    assert!((mid_price - 50050.0).abs() < 0.01);
+    assert_eq!(mid_price, dec!(50050.0));
+    assert_eq!(mid_price.to_string(), "50050.0");
 }
 
 /// Tests that an empty order book correctly returns None
@@ -182,6 +194,7 @@ fn test_empty_order_book_mid_price() {
         bids: vec![],
         asks: vec![],
         timestamp: SystemTime::now(),
+        exchange_timestamp: None,
     };
 
     let mid_price = order_book.calculate_mid_price();
@@ -198,16 +211,42 @@ fn test_empty_order_book_mid_price() {
 fn test_invalid_order_book_mid_price() {
     let order_book = OrderBook {
         bids: vec![Order {
-            price: 0.0,
-            quantity: 1.0,
+            price: dec!(0.0),
+            quantity: dec!(1.0),
         }],
         asks: vec![Order {
-            price: 0.0,
-            quantity: 1.0,
+            price: dec!(0.0),
+            quantity: dec!(1.0),
         }],
         timestamp: SystemTime::now(),
+        exchange_timestamp: None,
     };
 
     let mid_price = order_book.calculate_mid_price();
     assert!(mid_price.is_none());
 }
+
+/// Tests that a mid price between two ticks gets one more decimal place
+/// instead of being rounded.
+///
+/// This test verifies:
+/// 1. An odd spread in ticks produces a half-tick mid price
+/// 2. No rounding is applied
+#[test]
+fn test_order_book_mid_price_is_exact() {
+    let order_book = OrderBook {
+        bids: vec![Order {
+            price: dec!(50000.01),
+            quantity: dec!(1.0),
+        }],
+        asks: vec![Order {
+            price: dec!(50000.02),
+            quantity: dec!(1.0),
+        }],
+        timestamp: SystemTime::now(),
+        exchange_timestamp: None,
+    };
+
+    let mid_price = order_book.calculate_mid_price().unwrap();
+    assert_eq!(mid_price.to_string(), "50000.015");
+}
@@ -1,6 +1,26 @@
-use global_price_index::models::{ExchangePrice, GlobalPriceIndex};
+use global_price_index::config::{PriceWeighting, Settings, CONFIG_FILE};
+use global_price_index::models::{Decimal, ExchangePrice, GlobalPriceIndex, Liquidity};
+use rust_decimal_macros::dec;
 use std::time::{Duration, SystemTime};
 
+/// Computes the BTC/USDT index with the checked-in weighting and outlier settings
+///
+/// Old prices are weighted down instead of being excluded as stale.
+fn index(exchange_prices: Vec<ExchangePrice>) -> Option<GlobalPriceIndex> {
+    let settings = Settings::load(CONFIG_FILE).expect("Failed to load config.toml");
+    let weighting = PriceWeighting {
+        max_age_ms: u64::MAX,
+        ..settings.price_weighting
+    };
+    GlobalPriceIndex::new(
+        "BTC/USDT",
+        exchange_prices,
+        &weighting,
+        &settings.outlier_filter,
+        settings.health.min_venues,
+    )
+}
+
 /// Tests that the global price index correctly applies time-based weighting
 /// to prices from different timestamps.
 ///
@@ -21,25 +41,34 @@ fn test_global_price_index_weighting() {
         // Current price
         ExchangePrice {
             exchange: "Exchange1".to_string(),
-            mid_price: 50000.0,
+            mid_price: dec!(50000.0),
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         // 5 minutes old price
         ExchangePrice {
             exchange: "Exchange2".to_string(),
-            mid_price: 51000.0,
+            mid_price: dec!(51000.0),
             timestamp: now.checked_sub(Duration::from_secs(300)).unwrap(),
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         // 10 minutes old price
         ExchangePrice {
             exchange: "Exchange3".to_string(),
-            mid_price: 52000.0,
+            mid_price: dec!(52000.0),
             timestamp: now.checked_sub(Duration::from_secs(600)).unwrap(),
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
     ];
 
     // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    let global_index = index(exchange_prices).expect("No index computed");
 
     // With decay_factor = 300.0:
     // - Exchange1: weight = 1.0 (100%)
@@ -50,9 +79,9 @@ fn test_global_price_index_weighting() {
     // (50000 * 1.0 + 51000 * 0.368 + 52000 * 0.135) / (1.0 + 0.368 + 0.135)
     //
     // The actual value from test execution is 50424.79
-    let expected_price = 50424.79;
+    let expected_price = dec!(50424.79);
     assert!(
-        (global_index.price - expected_price).abs() < 1.0,
+        (global_index.price - expected_price).abs() < dec!(1.0),
         "Expected price around {}, but got {}",
         expected_price,
         global_index.price
@@ -66,10 +95,9 @@ fn test_global_price_index_weighting() {
 /// 1. When all timestamps are equal, all weights should be equal (1.0)
 /// 2. Equal weights produce a simple average of all prices
 ///
-/// Using a smaller tolerance of 0.01 (a difference < 1 cent) because:
+/// Using exact equality because:
 /// - This is a simple arithmetic mean calculation
-/// - No complex exponential functions are involved
-/// - There should be minimal floating-point error
+/// - Prices are decimals, so no floating-point error is involved
 #[test]
 fn test_global_price_index_equal_timestamps() {
     // Create mock prices with equal timestamps
@@ -78,31 +106,39 @@ fn test_global_price_index_equal_timestamps() {
     let exchange_prices = vec![
         ExchangePrice {
             exchange: "Exchange1".to_string(),
-            mid_price: 50000.0,
+            mid_price: dec!(50000.0),
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         ExchangePrice {
             exchange: "Exchange2".to_string(),
-            mid_price: 51000.0,
+            mid_price: dec!(51000.0),
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         ExchangePrice {
             exchange: "Exchange3".to_string(),
-            mid_price: 52000.0,
+            mid_price: dec!(52000.0),
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
     ];
 
     // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    let global_index = index(exchange_prices).expect("No index computed");
 
     // All weights should be 1.0, so this should be a simple average
-    let expected_price = (50000.0 + 51000.0 + 52000.0) / 3.0;
-    assert!(
-        (global_index.price - expected_price).abs() < 0.01,
+    let expected_price = dec!(51000);
+    assert_eq!(
+        global_index.price, expected_price,
         "Expected simple average {}, but got {}",
-        expected_price,
-        global_index.price
+        expected_price, global_index.price
     );
 }
 
@@ -113,10 +149,10 @@ fn test_global_price_index_equal_timestamps() {
 /// 1. Single price handling works correctly
 /// 2. No unexpected modifications are made to a lone price
 ///
-/// Using a tolerance of 0.01 (a difference < 1 cent) because:
+/// Using exact equality because:
 /// - This is a direct assignment operation (price = single_price)
 /// - No complex calculations are involved
-/// - The result should be exact to the cent
+/// - Prices are decimals, so the result is exact
 #[test]
 fn test_global_price_index_one_valid_price() {
     // Create a single valid price
@@ -124,16 +160,20 @@ fn test_global_price_index_one_valid_price() {
 
     let exchange_prices = vec![ExchangePrice {
         exchange: "Exchange1".to_string(),
-        mid_price: 50000.0,
+        mid_price: dec!(50000.0),
         timestamp: now,
+        liquidity: Liquidity::default(),
+        exchange_timestamp: None,
+        latency_ms: None,
     }];
 
     // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    let global_index = index(exchange_prices).expect("No index computed");
 
     // Should be exactly the single price
-    assert!(
-        (global_index.price - 50000.0).abs() < 0.01,
+    assert_eq!(
+        global_index.price,
+        dec!(50000),
         "Expected single price 50000.0, but got {}",
         global_index.price
     );
@@ -147,7 +187,7 @@ fn test_global_price_index_one_valid_price() {
 /// 2. Zero prices are rejected
 /// 3. The calculation proceeds with only valid prices
 ///
-/// Using a tolerance of 0.01 (a difference < 1 cent) because:
+/// Using exact equality because:
 /// - This is a simple filtering operation followed by a direct assignment
 /// - The result should match the single valid price exactly
 /// - No complex calculations are involved when only one price remains
@@ -159,27 +199,37 @@ fn test_global_price_index_invalid_prices() {
     let exchange_prices = vec![
         ExchangePrice {
             exchange: "Exchange1".to_string(),
-            mid_price: -50000.0, // Invalid
+            mid_price: dec!(-50000.0), // Invalid
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         ExchangePrice {
             exchange: "Exchange2".to_string(),
-            mid_price: 0.0, // Invalid
+            mid_price: dec!(0.0), // Invalid
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         ExchangePrice {
             exchange: "Exchange3".to_string(),
-            mid_price: 52000.0, // Valid
+            mid_price: dec!(52000.0), // Valid
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
     ];
 
     // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    let global_index = index(exchange_prices).expect("No index computed");
 
     // Should only use the single valid price
-    assert!(
-        (global_index.price - 52000.0).abs() < 0.01,
+    assert_eq!(
+        global_index.price,
+        dec!(52000),
         "Expected only valid price 52000.0, but got {}",
         global_index.price
     );
@@ -206,53 +256,73 @@ fn test_global_price_index_very_old_prices() {
         // Current price
         ExchangePrice {
             exchange: "Exchange1".to_string(),
-            mid_price: 50000.0,
+            mid_price: dec!(50000.0),
             timestamp: now,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
         // 30 minutes old (should have ~0.05% influence)
         ExchangePrice {
             exchange: "Exchange2".to_string(),
-            mid_price: 30000.0, // Very different to show the low influence
+            mid_price: dec!(30000.0), // Very different to show the low influence
             timestamp: now.checked_sub(Duration::from_secs(1800)).unwrap(),
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         },
     ];
 
     // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    let global_index = index(exchange_prices).expect("No index computed");
 
     // The 30-minute old price should have almost no influence
     // Global price should be very close to the current price (50000.0)
     assert!(
-        (global_index.price - 50000.0).abs() < 100.0,
+        (global_index.price - dec!(50000.0)).abs() < dec!(100.0),
         "Old price had too much influence, expected close to 50000.0, but got {}",
         global_index.price
     );
 }
 
-/// Tests the behavior when provided with an empty list of prices.
+/// Tests that no index is computed when no venue price is left to compute it from.
 ///
 /// This test verifies:
-/// 1. The system handles empty input gracefully
-/// 2. The default value for empty input is 0.0
-///
-/// Using exact equality (assert_eq!) because:
-/// - This is a simple edge case with a defined return value (0.0)
-/// - No calculations are performed, so no floating-point errors exist
-/// - The behavior should be deterministic and exact
+/// 1. An empty list of prices gives no index rather than a price of 0
+/// 2. Prices that are all invalid (non-positive) give no index
+/// 3. Prices that are all stale give no index
 #[test]
-fn test_global_price_index_empty_prices() {
-    // Empty price list
-    let exchange_prices = vec![];
+fn test_global_price_index_no_valid_prices() {
+    let now = SystemTime::now();
+    let price = |exchange: &str, mid_price: Decimal, timestamp: SystemTime| ExchangePrice {
+        exchange: exchange.to_string(),
+        mid_price,
+        timestamp,
+        liquidity: Liquidity::default(),
+        exchange_timestamp: None,
+        latency_ms: None,
+    };
 
-    // Calculate the global price index
-    let global_index = GlobalPriceIndex::new(exchange_prices);
+    assert!(index(vec![]).is_none());
+    assert!(index(vec![
+        price("Exchange1", dec!(-50000.0), now),
+        price("Exchange2", Decimal::ZERO, now),
+    ])
+    .is_none());
 
-    // Should be 0.0 for empty prices
-    assert_eq!(
-        global_index.price, 0.0,
-        "Expected 0.0 for empty prices, but got {}",
-        global_index.price
-    );
+    let settings = Settings::load(CONFIG_FILE).expect("Failed to load config.toml");
+    let stale = now - settings.price_weighting.max_age() - Duration::from_secs(1);
+    assert!(GlobalPriceIndex::new(
+        "BTC/USDT",
+        vec![
+            price("Exchange1", dec!(50000.0), stale),
+            price("Exchange2", dec!(50010.0), stale),
+        ],
+        &settings.price_weighting,
+        &settings.outlier_filter,
+        settings.health.min_venues,
+    )
+    .is_none());
 }
 
 /// Directly tests the exponential decay weight calculation formula
@@ -285,10 +355,13 @@ fn test_weight_calculation() {
         // Create a price with the specified time difference
         let _price = ExchangePrice {
             exchange: "Test".to_string(),
-            mid_price: 50000.0,
+            mid_price: dec!(50000.0),
             timestamp: now
                 .checked_sub(Duration::from_secs(time_diff_secs))
                 .unwrap(),
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
         };
 
         // Calculate the weight manually using the same formula as in the implementation
@@ -0,0 +1,202 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use async_trait::async_trait;
+use common::price;
+use global_price_index::{
+    aggregator::Aggregator,
+    api::AppState,
+    config::Settings,
+    error::Result,
+    exchanges::{registry::ExchangeRegistry, Exchange, ExchangeKind},
+    health::{get_health, get_ready, ConnectionState, HealthReport},
+    models::{ExchangePrice, OrderBook},
+};
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Exchange stand-in whose feed can be switched off
+struct MockExchange {
+    name: &'static str,
+    connected: AtomicBool,
+}
+
+impl MockExchange {
+    fn new(name: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            connected: AtomicBool::new(true),
+        })
+    }
+}
+
+#[async_trait]
+impl Exchange for MockExchange {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        Ok(OrderBook {
+            bids: Vec::new(),
+            asks: Vec::new(),
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        })
+    }
+}
+
+/// Tests the connection state, staleness and quorum reported for each venue.
+///
+/// This test verifies:
+/// 1. A started market that has not connected yet is reported as connecting
+/// 2. A registered exchange is connected or disconnected depending on its feed
+/// 3. Venues not updated within the staleness threshold are stale
+/// 4. Only connected, fresh venues count towards the quorum
+#[tokio::test]
+async fn test_health_report() {
+    let registry = ExchangeRegistry::new();
+    let aggregator = Aggregator::new(16);
+    let now = SystemTime::now();
+
+    let fresh = MockExchange::new("Fresh");
+    let old = MockExchange::new("Old");
+    let down = MockExchange::new("Down");
+    down.connected.store(false, Ordering::SeqCst);
+    for exchange in [&fresh, &old, &down] {
+        registry.register(exchange.clone());
+    }
+    aggregator.record_price(
+        "BTC/USDT",
+        ExchangePrice {
+            timestamp: now,
+            ..price("Fresh", dec!(100))
+        },
+    );
+    aggregator.record_price(
+        "BTC/USDT",
+        ExchangePrice {
+            timestamp: now - Duration::from_secs(60),
+            ..price("Old", dec!(101))
+        },
+    );
+    aggregator.record_price(
+        "BTC/USDT",
+        ExchangePrice {
+            timestamp: now,
+            ..price("Down", dec!(99))
+        },
+    );
+    // No Huobi market is configured for this symbol, so it keeps retrying
+    registry.start(ExchangeKind::Huobi, "MTR/USDT".to_string(), &aggregator);
+
+    let state = AppState::new(
+        vec!["BTC/USDT".to_string(), "MTR/USDT".to_string()],
+        registry,
+        aggregator,
+    );
+    let report = HealthReport::new(&state, Duration::from_secs(10), 1, now);
+    let feed = |exchange: &str| {
+        report
+            .exchanges
+            .iter()
+            .find(|feed| feed.exchange == exchange)
+            .unwrap()
+    };
+
+    assert_eq!(report.exchanges.len(), 4);
+    assert_eq!(feed("Huobi").state, ConnectionState::Connecting);
+    assert_eq!(feed("Huobi").symbol, "MTR/USDT");
+    assert!(feed("Huobi").last_update.is_none());
+    assert!(feed("Huobi").stale);
+
+    assert_eq!(feed("Fresh").state, ConnectionState::Connected);
+    assert_eq!(feed("Fresh").age_ms, Some(0));
+    assert!(!feed("Fresh").stale);
+    assert!(feed("Fresh").is_healthy());
+
+    assert_eq!(feed("Old").state, ConnectionState::Connected);
+    assert_eq!(feed("Old").age_ms, Some(60_000));
+    assert!(feed("Old").stale);
+    assert!(!feed("Old").is_healthy());
+
+    assert_eq!(feed("Down").state, ConnectionState::Disconnected);
+    assert!(!feed("Down").stale);
+    assert!(!feed("Down").is_healthy());
+
+    assert_eq!(report.symbols[0].healthy_venues, 1);
+    assert!(report.symbols[0].quorum_met);
+    assert_eq!(report.symbols[1].healthy_venues, 0);
+    assert!(!report.symbols[1].quorum_met);
+    assert!(!report.ready);
+
+    let report = HealthReport::new(&state, Duration::from_secs(120), 2, now);
+    assert_eq!(report.symbols[0].healthy_venues, 2);
+    assert!(report.symbols[0].quorum_met);
+}
+
+/// Tests the /health and /ready endpoints.
+///
+/// This test verifies:
+/// 1. /health answers 200 even when the service is not ready
+/// 2. /ready answers 503 until the venue quorum is met, then 200
+/// 3. Both serve the report as JSON
+/// 4. Venues are stale beyond the maximum age of the prices the index is computed from
+#[actix_web::test]
+async fn test_health_endpoints() {
+    let registry = ExchangeRegistry::new();
+    let aggregator = Aggregator::new(16);
+    let mut settings = Settings::default();
+    settings.price_weighting.max_age_ms = 5000;
+    let state = web::Data::new(
+        AppState::new(
+            vec!["BTC/USDT".to_string()],
+            registry.clone(),
+            aggregator.clone(),
+        )
+        .with_settings(settings),
+    );
+    let app = test::init_service(
+        App::new()
+            .app_data(state)
+            .route("/health", web::get().to(get_health))
+            .route("/ready", web::get().to(get_ready)),
+    )
+    .await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    let response = test::call_service(&app, get("/health")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(report["ready"], false);
+    assert_eq!(report["max_age_ms"], 5000);
+
+    let response = test::call_service(&app, get("/ready")).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Meets the default quorum of two venues
+    for name in ["Binance", "Kraken"] {
+        registry.register(MockExchange::new(name));
+        aggregator.record_price("BTC/USDT", price(name, dec!(100)));
+    }
+
+    let response = test::call_service(&app, get("/ready")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: serde_json::Value = test::read_body_json(response).await;
+    assert_eq!(report["ready"], true);
+    assert_eq!(report["symbols"][0]["healthy_venues"], 2);
+    assert_eq!(report["exchanges"][0]["exchange"], "Binance");
+    assert_eq!(report["exchanges"][0]["state"], "connected");
+    assert_eq!(report["exchanges"][0]["stale"], false);
+    assert!(report["exchanges"][0]["last_update"].is_string());
+}
@@ -0,0 +1,362 @@
+use actix_web::{http::StatusCode, test as actix_test, web};
+use global_price_index::{
+    aggregator::{Aggregator, IndexSettings},
+    api::AppState,
+    config::Settings,
+    exchanges::registry::ExchangeRegistry,
+    history::{get_candles, get_history, Candle, HistoryStore, Resolution},
+    models::{Decimal, ExchangePrice, ExcludedPrice, ExclusionReason, GlobalPriceIndex, Liquidity},
+};
+use rust_decimal_macros::dec;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 2025-04-08T09:00:00Z
+const START_SECS: u64 = 1_744_102_800;
+
+/// Returns the time a number of seconds after 2025-04-08T09:00:00Z
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(START_SECS + secs)
+}
+
+/// Builds an index of a symbol computed at a time from a single Binance price
+fn index(symbol: &str, price: Decimal, timestamp: SystemTime) -> GlobalPriceIndex {
+    GlobalPriceIndex {
+        symbol: symbol.to_string(),
+        price,
+        timestamp,
+        exchange_prices: vec![ExchangePrice {
+            exchange: "Binance".to_string(),
+            mid_price: price,
+            timestamp,
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
+        }],
+        excluded: vec![],
+        venues: 1,
+        min_venues: 1,
+        quorum_met: true,
+    }
+}
+
+/// Returns a store holding BTC/USDT indices spread over the first six minutes
+fn store_with_ticks() -> HistoryStore {
+    let store = HistoryStore::open_in_memory().unwrap();
+    for (secs, price) in [
+        (10, dec!(100)),
+        (50, dec!(110)),
+        (65, dec!(90)),
+        (299, dec!(95)),
+        (300, dec!(105)),
+    ] {
+        store.record(&index("BTC/USDT", price, at(secs))).unwrap();
+    }
+    store
+}
+
+/// Returns the state of an API serving a history store
+fn app_state(history: Option<HistoryStore>) -> AppState {
+    let aggregator = Aggregator::new(16);
+    let aggregator = match history {
+        Some(history) => aggregator.with_history(history),
+        None => aggregator,
+    };
+    AppState::new(
+        vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()],
+        ExchangeRegistry::new(),
+        aggregator,
+    )
+}
+
+/// Tests that stored indices are read back exactly over a time range.
+///
+/// This test verifies:
+/// 1. Prices, venue prices, liquidity and excluded venues are read back unchanged
+/// 2. Only the requested symbol is returned, oldest first
+/// 3. The start of the range is inclusive and its end exclusive
+/// 4. The number of returned indices is limited
+#[test]
+fn test_record_and_range() {
+    let store = HistoryStore::open_in_memory().unwrap();
+    let mut first = index("BTC/USDT", dec!(50000.12345678), at(0));
+    first.exchange_prices.push(ExchangePrice {
+        exchange: "Kraken".to_string(),
+        mid_price: dec!(50000.1),
+        timestamp: at(0),
+        liquidity: Liquidity {
+            top_of_book_depth: Some(dec!(12.5)),
+            depth_within_bps: None,
+            volume: Some(dec!(1000)),
+        },
+        exchange_timestamp: None,
+        latency_ms: None,
+    });
+    first.excluded.push(ExcludedPrice {
+        exchange: "Huobi".to_string(),
+        mid_price: dec!(5000),
+        reason: ExclusionReason::MaxDeviation,
+    });
+    store.record(&first).unwrap();
+    store.record(&index("ETH/USDT", dec!(3000), at(1))).unwrap();
+    store
+        .record(&index("BTC/USDT", dec!(50001), at(2)))
+        .unwrap();
+
+    let history = store.range("BTC/USDT", at(0), at(3), 100).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].price, dec!(50000.12345678));
+    assert_eq!(history[0].timestamp, at(0));
+    assert_eq!(history[0].exchange_prices.len(), 2);
+    assert_eq!(history[0].exchange_prices[1].exchange, "Kraken");
+    assert_eq!(
+        history[0].exchange_prices[1].liquidity,
+        first.exchange_prices[1].liquidity
+    );
+    assert_eq!(history[0].excluded, first.excluded);
+    assert_eq!(history[1].price, dec!(50001));
+    assert_eq!(history[1].exchange_prices.len(), 1);
+
+    assert_eq!(store.range("BTC/USDT", at(0), at(2), 100).unwrap().len(), 1);
+    assert_eq!(store.range("BTC/USDT", at(0), at(3), 1).unwrap().len(), 1);
+}
+
+/// Tests that OHLC candles are built from the stored indices.
+///
+/// This test verifies:
+/// 1. Candles are aligned on the resolution and carry open, high, low, close and tick count
+/// 2. Periods without indices have no candle
+/// 3. Coarser resolutions merge the same indices into fewer candles
+/// 4. Highs and lows compare the prices as numbers rather than as the stored strings
+#[test]
+fn test_candles() {
+    let store = store_with_ticks();
+
+    let candles = store
+        .candles("BTC/USDT", at(0), at(3600), Resolution::OneMinute)
+        .unwrap();
+    assert_eq!(
+        candles,
+        vec![
+            Candle {
+                start: at(0),
+                open: dec!(100),
+                high: dec!(110),
+                low: dec!(100),
+                close: dec!(110),
+                ticks: 2,
+            },
+            Candle {
+                start: at(60),
+                open: dec!(90),
+                high: dec!(90),
+                low: dec!(90),
+                close: dec!(90),
+                ticks: 1,
+            },
+            Candle {
+                start: at(240),
+                open: dec!(95),
+                high: dec!(95),
+                low: dec!(95),
+                close: dec!(95),
+                ticks: 1,
+            },
+            Candle {
+                start: at(300),
+                open: dec!(105),
+                high: dec!(105),
+                low: dec!(105),
+                close: dec!(105),
+                ticks: 1,
+            },
+        ]
+    );
+
+    let candles = store
+        .candles("BTC/USDT", at(0), at(3600), Resolution::FiveMinutes)
+        .unwrap();
+    assert_eq!(candles.len(), 2);
+    assert_eq!(
+        (
+            candles[0].open,
+            candles[0].high,
+            candles[0].low,
+            candles[0].close
+        ),
+        (dec!(100), dec!(110), dec!(90), dec!(95))
+    );
+    assert_eq!(candles[0].ticks, 4);
+
+    let candles = store
+        .candles("BTC/USDT", at(0), at(3600), Resolution::OneHour)
+        .unwrap();
+    assert_eq!(candles.len(), 1);
+    assert_eq!(candles[0].close, dec!(105));
+    assert_eq!(candles[0].ticks, 5);
+}
+
+/// Tests that a database created before the venue quorum was stored is upgraded.
+///
+/// This test verifies:
+/// 1. The quorum columns are added to an existing database when it is opened
+/// 2. The indices stored before are read back as meeting their quorum
+/// 3. New indices are stored with their venue count and quorum
+#[test]
+fn test_history_adds_missing_columns() {
+    let path = std::env::temp_dir().join(format!(
+        "global_price_index_history_upgrade_{}.db",
+        std::process::id()
+    ));
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    connection
+        .execute_batch(&format!(
+            "CREATE TABLE indices (
+                 id INTEGER PRIMARY KEY,
+                 symbol TEXT NOT NULL,
+                 timestamp_ms INTEGER NOT NULL,
+                 price TEXT NOT NULL,
+                 excluded TEXT NOT NULL
+             );
+             INSERT INTO indices (symbol, timestamp_ms, price, excluded)
+             VALUES ('BTC/USDT', {}, '100', '[]');",
+            START_SECS * 1000
+        ))
+        .unwrap();
+    drop(connection);
+
+    let store = HistoryStore::open(path.to_str().unwrap()).unwrap();
+    store
+        .record(&GlobalPriceIndex {
+            venues: 3,
+            min_venues: 2,
+            ..index("BTC/USDT", dec!(110), at(60))
+        })
+        .unwrap();
+    let history = store.range("BTC/USDT", at(0), at(120), 10).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].price, dec!(100));
+    assert!(history[0].quorum_met);
+    assert_eq!((history[1].venues, history[1].min_venues), (3, 2));
+    assert!(history[1].quorum_met);
+
+    drop(store);
+    for file in [
+        path.clone(),
+        path.with_extension("db-wal"),
+        path.with_extension("db-shm"),
+    ] {
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+/// Tests that the aggregator stores every index it computes.
+///
+/// This test verifies:
+/// 1. A recomputed index is written to the history store once the queued
+///    writes are flushed
+/// 2. An unchanged price does not add an index
+#[test]
+fn test_aggregator_records_history() {
+    let store = HistoryStore::open_in_memory().unwrap();
+    let aggregator = Aggregator::new(16)
+        .with_settings(IndexSettings {
+            min_venues: 1,
+            ..IndexSettings::from(&Settings::default())
+        })
+        .with_history(store.clone());
+    let price = ExchangePrice {
+        exchange: "Binance".to_string(),
+        mid_price: dec!(50000),
+        timestamp: SystemTime::now(),
+        liquidity: Liquidity::default(),
+        exchange_timestamp: None,
+        latency_ms: None,
+    };
+
+    assert!(aggregator.record_price("BTC/USDT", price.clone()));
+    assert!(!aggregator.record_price("BTC/USDT", price));
+    aggregator.flush().unwrap();
+
+    let now = SystemTime::now();
+    let history = store
+        .range(
+            "BTC/USDT",
+            now - Duration::from_secs(60),
+            now + Duration::from_secs(60),
+            100,
+        )
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].price, dec!(50000));
+}
+
+/// Tests the /history/{symbol} and /history/{symbol}/candles endpoints.
+///
+/// This test verifies:
+/// 1. The history of a time range is served as GlobalPriceIndex JSON
+/// 2. Candles are served at the requested resolution
+/// 3. Invalid times, empty ranges and unknown resolutions are rejected with 400
+/// 4. Unknown symbols are rejected with 404
+/// 5. A disabled history is reported with 503
+#[actix_web::test]
+async fn test_history_endpoints() {
+    let app = actix_test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(app_state(Some(store_with_ticks()))))
+            .route("/history/{symbol}", web::get().to(get_history))
+            .route("/history/{symbol}/candles", web::get().to(get_candles)),
+    )
+    .await;
+
+    let req = actix_test::TestRequest::get()
+        .uri("/history/BTC-USDT?from=2025-04-08T09:00:00Z&to=2025-04-08T09:05:00Z&limit=3")
+        .to_request();
+    let history: Vec<GlobalPriceIndex> = actix_test::call_and_read_body_json(&app, req).await;
+    let prices: Vec<Decimal> = history.iter().map(|index| index.price).collect();
+    assert_eq!(prices, vec![dec!(100), dec!(110), dec!(90)]);
+
+    let req = actix_test::TestRequest::get()
+        .uri("/history/btc-usdt/candles?resolution=5m&from=2025-04-08T09:00:00Z&to=2025-04-08T10:00:00Z")
+        .to_request();
+    let json: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        json[0],
+        serde_json::json!({
+            "start": "2025-04-08T09:00:00.000Z",
+            "open": "100",
+            "high": "110",
+            "low": "90",
+            "close": "95",
+            "ticks": 4,
+        })
+    );
+    assert_eq!(json.as_array().unwrap().len(), 2);
+
+    for uri in [
+        "/history/BTC-USDT?from=yesterday",
+        "/history/BTC-USDT?from=2025-04-08T10:00:00Z&to=2025-04-08T09:00:00Z",
+        "/history/BTC-USDT/candles?resolution=2m",
+    ] {
+        let req = actix_test::TestRequest::get().uri(uri).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+    }
+
+    let req = actix_test::TestRequest::get()
+        .uri("/history/DOGE-USDT")
+        .to_request();
+    let resp = actix_test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let app = actix_test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(app_state(None)))
+            .route("/history/{symbol}", web::get().to(get_history)),
+    )
+    .await;
+    let req = actix_test::TestRequest::get()
+        .uri("/history/BTC-USDT")
+        .to_request();
+    let resp = actix_test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::ws::{btc_usdt_market, gzip_json, start_ws_server, wait_for_book, WsServer};
+use global_price_index::{
+    config::Settings,
+    exchanges::{huobi_ws::HuobiWsExchange, Exchange},
+    models::{timestamp_from_millis, Decimal},
+};
+use rust_decimal_macros::dec;
+use serde_json::json;
+
+/// Builds a depth.step0 push in the Huobi wire format
+fn depth(bids: serde_json::Value, asks: serde_json::Value) -> serde_json::Value {
+    json!({
+        "ch": "market.btcusdt.depth.step0",
+        "ts": 1_700_000_000_000u64,
+        "tick": {
+            "bids": bids,
+            "asks": asks,
+            "version": 1,
+            "ts": 1_700_000_000_000u64,
+        },
+    })
+}
+
+/// Connects to the stand-in server and answers the subscription with a first book
+async fn connect(server: &mut WsServer<serde_json::Value>) -> HuobiWsExchange {
+    let settings = Settings::default();
+    let ws_url = server.url.clone();
+    let connecting = async move {
+        HuobiWsExchange::with_url(
+            btc_usdt_market("btcusdt"),
+            ws_url,
+            &settings.exchange.huobi.ticker_url,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    };
+    server
+        .connect(connecting, |request| {
+            assert_eq!(request["sub"], "market.btcusdt.depth.step0");
+            vec![
+                json!({
+                    "id": request["id"],
+                    "status": "ok",
+                    "subbed": "market.btcusdt.depth.step0",
+                    "ts": 1_700_000_000_000u64,
+                }),
+                depth(json!([[100.5, 1.0], [100.0, 2.0]]), json!([[101.0, 1.5]])),
+            ]
+        })
+        .await
+}
+
+/// Tests that compressed depth pushes are decoded into the book.
+///
+/// This test verifies:
+/// 1. The client subscribes to the market's depth.step0 channel
+/// 2. The exchange is ready once the first book is received
+/// 3. Each push replaces the whole book
+/// 4. The book carries the exchange timestamp of the push
+#[tokio::test]
+async fn test_depth_pushes_replace_book() {
+    let mut server = start_ws_server(gzip_json).await;
+    let exchange = connect(&mut server).await;
+
+    let order_book = exchange.fetch_order_book().await.unwrap();
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(100.5), dec!(100.0)]);
+    assert_eq!(order_book.asks.first().map(|a| a.quantity), Some(dec!(1.5)));
+    assert_eq!(
+        order_book.exchange_timestamp,
+        Some(timestamp_from_millis(1_700_000_000_000))
+    );
+
+    server
+        .frames
+        .send(depth(json!([[99.25, 3.0]]), json!([[99.75, 0.125]])))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.bids.first().map(|b| b.price) == Some(dec!(99.25))
+    })
+    .await;
+
+    assert_eq!(order_book.bids.len(), 1);
+    assert_eq!(order_book.asks.len(), 1);
+    assert_eq!(order_book.asks[0].price, dec!(99.75));
+    assert_eq!(order_book.asks[0].quantity, dec!(0.125));
+}
+
+/// Tests that Huobi's application-level heartbeat is answered.
+///
+/// This test verifies:
+/// 1. A compressed `{"ping": ts}` message is answered with `{"pong": ts}`
+/// 2. The heartbeat does not disturb the book
+#[tokio::test]
+async fn test_heartbeat_is_answered() {
+    let mut server = start_ws_server(gzip_json).await;
+    let exchange = connect(&mut server).await;
+
+    server
+        .frames
+        .send(json!({ "ping": 1_700_000_000_123u64 }))
+        .unwrap();
+
+    let response = server.next_request().await;
+    assert_eq!(response, json!({ "pong": 1_700_000_000_123u64 }));
+
+    let order_book = exchange.fetch_order_book().await.unwrap();
+    assert_eq!(order_book.bids.first().map(|b| b.price), Some(dec!(100.5)));
+}
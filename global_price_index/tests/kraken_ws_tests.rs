@@ -0,0 +1,241 @@
+mod common;
+
+use common::ws::{btc_usdt_market, start_ws_server, text, wait_for_book, WsServer};
+use global_price_index::{
+    config::{MarketConfig, Settings},
+    exchanges::{
+        kraken_ws::{book_checksum, KrakenWsExchange},
+        Exchange,
+    },
+    models::{timestamp_from_millis, Decimal, Order, PriceLevelBook},
+};
+use rust_decimal_macros::dec;
+use serde_json::json;
+
+/// Builds book levels in the Kraken wire format
+fn levels(levels: &[(&str, &str)]) -> serde_json::Value {
+    levels
+        .iter()
+        .map(|(price, volume)| json!([price, volume, "1700000000.000000"]))
+        .collect()
+}
+
+/// Builds a book snapshot message
+fn snapshot(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    json!([42, {"as": levels(asks), "bs": levels(bids)}, "book-2", "XBT/USDT"]).to_string()
+}
+
+/// Builds a book update message carrying a checksum
+fn update(bids: &[(&str, &str)], asks: &[(&str, &str)], checksum: u32) -> String {
+    json!([
+        42,
+        {"a": levels(asks), "b": levels(bids), "c": checksum.to_string()},
+        "book-2",
+        "XBT/USDT"
+    ])
+    .to_string()
+}
+
+/// Returns Kraken's checksum of a book holding exactly the given levels
+fn checksum_of(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> u32 {
+    let parse = |levels: &[(&str, &str)]| -> Vec<Order> {
+        levels
+            .iter()
+            .map(|(price, volume)| Order {
+                price: price.parse().unwrap(),
+                quantity: volume.parse().unwrap(),
+            })
+            .collect()
+    };
+    book_checksum(&PriceLevelBook::from_levels(&parse(bids), &parse(asks)))
+}
+
+/// Connects to the stand-in server and answers the subscription with a snapshot
+async fn connect(server: &mut WsServer<String>, initial: String) -> KrakenWsExchange {
+    let settings = Settings::default();
+    let market = MarketConfig {
+        ws_symbol: Some("XBT/USDT".to_string()),
+        ..btc_usdt_market("XBTUSDT")
+    };
+    let ws_url = server.url.clone();
+    let connecting = async move {
+        KrakenWsExchange::with_url(
+            market,
+            ws_url,
+            &settings.exchange.kraken.ticker_url,
+            2,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    };
+    server
+        .connect(connecting, |request| {
+            assert_eq!(request["event"], "subscribe");
+            assert_eq!(request["pair"], json!(["XBT/USDT"]));
+            assert_eq!(request["subscription"], json!({"name": "book", "depth": 2}));
+            vec![initial]
+        })
+        .await
+}
+
+/// Tests the checksum against a hand-built checksum string.
+///
+/// This test verifies:
+/// 1. Asks are listed before bids, from the best price outwards
+/// 2. Decimal points and leading zeros are removed while trailing zeros are kept
+/// 3. Only the top 10 levels of each side are covered
+#[test]
+fn test_book_checksum() {
+    let mut asks: Vec<Order> = (1..=11)
+        .map(|i| Order {
+            price: "0.05005".parse::<Decimal>().unwrap() + Decimal::new(i - 1, 5),
+            quantity: "0.00000500".parse().unwrap(),
+        })
+        .collect();
+    asks.reverse();
+    let bids = vec![Order {
+        price: "0.05004".parse().unwrap(),
+        quantity: "1.00000000".parse().unwrap(),
+    }];
+    let book = PriceLevelBook::from_levels(&bids, &asks);
+
+    let mut expected = String::new();
+    for i in 0..10 {
+        expected.push_str(&format!("{}500", 5005 + i));
+    }
+    expected.push_str("5004100000000");
+    assert_eq!(book_checksum(&book), crc32fast::hash(expected.as_bytes()));
+}
+
+/// Tests that the book is built from the snapshot and kept in sync by
+/// checksummed updates.
+///
+/// This test verifies:
+/// 1. The exchange is ready once the snapshot is received
+/// 2. Updates are applied and levels beyond the subscribed depth are dropped
+/// 3. Zero volumes remove levels
+/// 4. Matching checksums do not renew the subscription
+/// 5. The book carries the latest timestamp of its levels
+#[tokio::test]
+async fn test_snapshot_and_updates_maintain_book() {
+    let mut server = start_ws_server(text).await;
+    let exchange = connect(
+        &mut server,
+        snapshot(
+            &[("100.00000", "1.00000000"), ("99.00000", "1.00000000")],
+            &[("101.00000", "1.00000000"), ("102.00000", "1.00000000")],
+        ),
+    )
+    .await;
+
+    let order_book = exchange.fetch_order_book().await.unwrap();
+    assert_eq!(order_book.bids.first().map(|b| b.price), Some(dec!(100)));
+    assert_eq!(order_book.asks.first().map(|a| a.price), Some(dec!(101)));
+    assert_eq!(
+        order_book.exchange_timestamp,
+        Some(timestamp_from_millis(1_700_000_000_000))
+    );
+
+    // Pushes the 99 bid out of the depth-2 book
+    server
+        .frames
+        .send(update(
+            &[("100.50000", "2.00000000")],
+            &[],
+            checksum_of(
+                &[("100.50000", "2.00000000"), ("100.00000", "1.00000000")],
+                &[("101.00000", "1.00000000"), ("102.00000", "1.00000000")],
+            ),
+        ))
+        .unwrap();
+    // Removes the best ask
+    server
+        .frames
+        .send(update(
+            &[],
+            &[("101.00000", "0.00000000")],
+            checksum_of(
+                &[("100.50000", "2.00000000"), ("100.00000", "1.00000000")],
+                &[("102.00000", "1.00000000")],
+            ),
+        ))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.asks.first().map(|a| a.price) == Some(dec!(102))
+    })
+    .await;
+
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(100.5), dec!(100)]);
+    assert_eq!(order_book.asks.len(), 1);
+    assert!(server.requests.try_recv().is_err());
+}
+
+/// Tests that a checksum mismatch renews the subscription and that the
+/// book is rebuilt from the new snapshot.
+///
+/// This test verifies:
+/// 1. A mismatching checksum makes the client unsubscribe and subscribe again
+/// 2. The corrupt book is not served until the new snapshot arrives
+/// 3. Updates received before the new snapshot are ignored
+/// 4. The book is replaced by the new snapshot and kept in sync afterwards
+#[tokio::test]
+async fn test_checksum_mismatch_resubscribes() {
+    let mut server = start_ws_server(text).await;
+    let exchange = connect(
+        &mut server,
+        snapshot(
+            &[("100.00000", "1.00000000")],
+            &[("101.00000", "1.00000000")],
+        ),
+    )
+    .await;
+
+    server
+        .frames
+        .send(update(&[("100.50000", "1.00000000")], &[], 12345))
+        .unwrap();
+
+    let request = server.next_request().await;
+    assert_eq!(request["event"], "unsubscribe");
+    let request = server.next_request().await;
+    assert_eq!(request["event"], "subscribe");
+    assert_eq!(request["pair"], json!(["XBT/USDT"]));
+    assert!(exchange.fetch_order_book().await.is_err());
+
+    // Belongs to the old subscription, must not be applied
+    server
+        .frames
+        .send(update(&[("150.00000", "1.00000000")], &[], 0))
+        .unwrap();
+    server
+        .frames
+        .send(snapshot(
+            &[("200.00000", "1.00000000")],
+            &[("201.00000", "1.00000000")],
+        ))
+        .unwrap();
+    server
+        .frames
+        .send(update(
+            &[("200.50000", "1.00000000")],
+            &[],
+            checksum_of(
+                &[("200.50000", "1.00000000"), ("200.00000", "1.00000000")],
+                &[("201.00000", "1.00000000")],
+            ),
+        ))
+        .unwrap();
+
+    let order_book = wait_for_book(&exchange, |book| {
+        book.bids.first().map(|b| b.price) == Some(dec!(200.5))
+    })
+    .await;
+
+    let bid_prices: Vec<Decimal> = order_book.bids.iter().map(|b| b.price).collect();
+    assert_eq!(bid_prices, vec![dec!(200.5), dec!(200)]);
+    assert_eq!(order_book.asks.first().map(|a| a.price), Some(dec!(201)));
+    assert!(server.requests.try_recv().is_err());
+}
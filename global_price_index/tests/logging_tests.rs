@@ -0,0 +1,102 @@
+use actix_web::{http::StatusCode, test, web, App};
+use global_price_index::{
+    aggregator::Aggregator,
+    api::{get_global_price_for_symbol, AppState},
+    config::{LogFormat, Logging},
+    exchanges::registry::ExchangeRegistry,
+    logging::{build_subscriber, env_filter},
+};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Log output shared between the subscriber and the test
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    /// Returns the JSON events written so far
+    fn events(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Log line is not JSON"))
+            .collect()
+    }
+}
+
+/// Tests that the configured filter selects the logged events.
+///
+/// This test verifies:
+/// 1. The filter directives of the configuration are used
+/// 2. Invalid directives fall back to the "info" level
+#[tokio::test]
+async fn test_env_filter_from_config() {
+    if std::env::var("RUST_LOG").is_ok() {
+        // RUST_LOG takes precedence over the configuration
+        return;
+    }
+
+    let logging = |filter: &str| Logging {
+        format: LogFormat::Json,
+        filter: filter.to_string(),
+    };
+    assert_eq!(
+        env_filter(&logging("warn,global_price_index::exchanges=debug")).to_string(),
+        "global_price_index::exchanges=debug,warn"
+    );
+    assert_eq!(env_filter(&logging("=nope=")).to_string(), "info");
+}
+
+/// Tests that /global-price requests are logged as JSON in a request span.
+///
+/// This test verifies:
+/// 1. Every event is written as one JSON object per line
+/// 2. Events carry their level and structured fields
+/// 3. Events of a request are in a `global_price` span carrying the requested symbol
+#[actix_web::test]
+async fn test_global_price_request_span() {
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let logging = Logging {
+        format: LogFormat::Json,
+        filter: "debug".to_string(),
+    };
+    let _guard =
+        tracing::subscriber::set_default(build_subscriber(&logging, move || writer.clone()));
+
+    let state = AppState::new(
+        vec!["BTC/USDT".to_string()],
+        ExchangeRegistry::new(),
+        Aggregator::new(16),
+    );
+    let app = test::init_service(App::new().app_data(web::Data::new(state)).route(
+        "/global-price/{symbol}",
+        web::get().to(get_global_price_for_symbol),
+    ))
+    .await;
+    let request = test::TestRequest::get()
+        .uri("/global-price/btc-usdt")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let events = buffer.events();
+    let event = events
+        .iter()
+        .find(|event| event["fields"]["message"] == "No price data available")
+        .expect("Request not logged");
+    assert_eq!(event["level"], "WARN");
+    assert_eq!(event["fields"]["symbol"], "BTC/USDT");
+    assert_eq!(event["span"]["name"], "global_price");
+    assert_eq!(event["span"]["requested"], "btc-usdt");
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, web, App};
+use async_trait::async_trait;
+use common::price;
+use global_price_index::{
+    aggregator::{Aggregator, IndexSettings},
+    config::Settings,
+    error::{PriceIndexError, Result},
+    exchanges::Exchange,
+    metrics::{get_metrics, METRICS},
+    models::{GlobalPriceIndex, Order, OrderBook},
+};
+use rust_decimal_macros::dec;
+use std::time::{Duration, SystemTime};
+
+/// Exchange stand-in whose book was updated by the venue two seconds before its receipt
+struct LaggingExchange;
+
+#[async_trait]
+impl Exchange for LaggingExchange {
+    fn name(&self) -> &'static str {
+        "Kraken"
+    }
+
+    fn symbol(&self) -> &str {
+        "MTL/USDT"
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        let timestamp = SystemTime::now();
+        Ok(OrderBook {
+            bids: vec![Order {
+                price: dec!(100),
+                quantity: dec!(1),
+            }],
+            asks: vec![Order {
+                price: dec!(101),
+                quantity: dec!(1),
+            }],
+            timestamp,
+            exchange_timestamp: Some(timestamp - Duration::from_secs(2)),
+        })
+    }
+}
+
+/// Returns the value of the sample of a rendered metric with the given labels
+fn sample(rendered: &str, metric: &str, labels: &str) -> Option<f64> {
+    let prefix = format!("{}{{{}}} ", metric, labels);
+    rendered
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Tests that feed health and index quality measurements are rendered.
+///
+/// This test verifies:
+/// 1. Fetch latencies are observed in the histogram of their exchange market
+/// 2. Errors are counted by `PriceIndexError` variant
+/// 3. WebSocket reconnections are counted
+/// 4. Book ages are computed from the last update as of the scrape
+/// 5. The index value and each venue's deviation in basis points are exported
+#[tokio::test]
+async fn test_metrics_are_rendered() {
+    let labels = "exchange=\"Binance\",symbol=\"MTR/USDT\"";
+
+    METRICS.record_fetch("Binance", "MTR/USDT", Duration::from_millis(20));
+    METRICS.record_error(
+        "Binance",
+        "MTR/USDT",
+        &PriceIndexError::InvalidPriceData("empty book".to_string()),
+    );
+    METRICS.record_error(
+        "Binance",
+        "MTR/USDT",
+        &PriceIndexError::WebSocketError("closed".to_string()),
+    );
+    METRICS.record_error(
+        "Binance",
+        "MTR/USDT",
+        &PriceIndexError::WebSocketError("closed".to_string()),
+    );
+    METRICS.record_reconnect("Binance", "MTR/USDT");
+    METRICS.record_book_update(
+        "Binance",
+        "MTR/USDT",
+        SystemTime::now() - Duration::from_secs(30),
+    );
+    METRICS.record_index(&GlobalPriceIndex {
+        symbol: "MTR/USDT".to_string(),
+        price: dec!(100),
+        timestamp: SystemTime::now(),
+        exchange_prices: vec![price("Binance", dec!(100.5)), price("Kraken", dec!(99.9))],
+        excluded: Vec::new(),
+        venues: 2,
+        min_venues: 2,
+        quorum_met: true,
+    });
+
+    let rendered = METRICS.render();
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_exchange_fetch_duration_seconds_count",
+            labels
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_exchange_fetch_duration_seconds_bucket",
+            &format!("{},le=\"0.01\"", labels)
+        ),
+        Some(0.0)
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_exchange_fetch_duration_seconds_bucket",
+            &format!("{},le=\"0.025\"", labels)
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_errors_total",
+            "error=\"invalid_price_data\",exchange=\"Binance\",symbol=\"MTR/USDT\""
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_errors_total",
+            "error=\"websocket\",exchange=\"Binance\",symbol=\"MTR/USDT\""
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(&rendered, "price_index_websocket_reconnects_total", labels),
+        Some(1.0)
+    );
+
+    let age = sample(&rendered, "price_index_book_age_seconds", labels).unwrap();
+    assert!((30.0..60.0).contains(&age), "Unexpected book age: {}", age);
+
+    assert_eq!(
+        sample(&rendered, "price_index_value", "symbol=\"MTR/USDT\""),
+        Some(100.0)
+    );
+    assert_eq!(
+        sample(&rendered, "price_index_venue_deviation_bps", labels),
+        Some(50.0)
+    );
+    assert_eq!(
+        sample(
+            &rendered,
+            "price_index_venue_deviation_bps",
+            "exchange=\"Kraken\",symbol=\"MTR/USDT\""
+        ),
+        Some(-10.0)
+    );
+}
+
+/// Tests that the feed latency of a venue is measured from its exchange timestamp.
+///
+/// This test verifies:
+/// 1. The price keeps both the exchange and the local receive time of the book
+/// 2. The latency between them is reported on the price in milliseconds
+/// 3. The latency is exported per exchange market
+#[tokio::test]
+async fn test_feed_latency_is_measured() {
+    let price = LaggingExchange.get_mid_price().await.unwrap();
+
+    assert_eq!(
+        price.exchange_timestamp,
+        Some(price.timestamp - Duration::from_secs(2))
+    );
+    assert_eq!(price.latency_ms, Some(2000));
+    assert_eq!(
+        sample(
+            &METRICS.render(),
+            "price_index_feed_latency_seconds",
+            "exchange=\"Kraken\",symbol=\"MTL/USDT\""
+        ),
+        Some(2.0)
+    );
+}
+
+/// Tests that the /metrics endpoint serves the index computed by the aggregator.
+///
+/// This test verifies:
+/// 1. The response is in the Prometheus text format
+/// 2. Every recomputed index is exported with its venue deviations
+#[actix_web::test]
+async fn test_metrics_endpoint() {
+    let aggregator = Aggregator::new(16).with_settings(IndexSettings {
+        min_venues: 1,
+        ..IndexSettings::from(&Settings::default())
+    });
+    aggregator.record_price("MTE/USDT", price("Huobi", dec!(2500)));
+
+    let app = test::init_service(App::new().route("/metrics", web::get().to(get_metrics))).await;
+    let response =
+        test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+
+    let body = test::read_body(response).await;
+    let rendered = std::str::from_utf8(&body).unwrap();
+    assert_eq!(
+        sample(rendered, "price_index_value", "symbol=\"MTE/USDT\""),
+        Some(2500.0)
+    );
+    assert_eq!(
+        sample(
+            rendered,
+            "price_index_venue_deviation_bps",
+            "exchange=\"Huobi\",symbol=\"MTE/USDT\""
+        ),
+        Some(0.0)
+    );
+}
@@ -0,0 +1,179 @@
+use global_price_index::{
+    config::{MidPriceCalculator, MidPriceMethod},
+    models::{Decimal, Order, OrderBook, Side},
+};
+use rust_decimal_macros::dec;
+use std::time::SystemTime;
+
+/// Builds an order of a given price and quantity
+fn order(price: Decimal, quantity: Decimal) -> Order {
+    Order { price, quantity }
+}
+
+/// Builds an order book from its bids and asks, best first
+fn book(bids: Vec<Order>, asks: Vec<Order>) -> OrderBook {
+    OrderBook {
+        bids,
+        asks,
+        timestamp: SystemTime::now(),
+        exchange_timestamp: None,
+    }
+}
+
+/// Returns a calculator of the given method with a $1000 notional and a size of 2
+fn calculator(method: MidPriceMethod) -> MidPriceCalculator {
+    MidPriceCalculator {
+        method,
+        vwap_notional: 1000.0,
+        impact_size: 2.0,
+    }
+}
+
+/// Tests that the volume-weighted mid-price looks through dust-size top levels.
+///
+/// This test verifies:
+/// 1. Each side is walked until the notional is filled, the last level partially
+/// 2. A dust-size best ask barely moves the VWAP mid while it moves the top-of-book mid
+/// 3. A side quoting less than the notional is averaged over all its levels
+/// 4. A non-positive notional or an invalid book has no VWAP mid
+#[test]
+fn test_vwap_mid_price() {
+    // 400 @ 100 + 600 @ 99 on the bids, 404 @ 101 + 596 @ 102 on the asks
+    let order_book = book(
+        vec![order(dec!(100), dec!(4)), order(dec!(99), dec!(10))],
+        vec![order(dec!(101), dec!(4)), order(dec!(102), dec!(10))],
+    );
+    let bid_vwap = dec!(1000) / (dec!(4) + dec!(600) / dec!(99));
+    let ask_vwap = dec!(1000) / (dec!(4) + dec!(596) / dec!(102));
+    assert_eq!(
+        order_book.calculate_vwap_mid_price(dec!(1000)),
+        Some(((bid_vwap + ask_vwap) / dec!(2)).round_dp(8))
+    );
+
+    // A dust-size ask far from the book moves the top-of-book mid by 2.5
+    let dusty = book(
+        vec![order(dec!(100), dec!(10))],
+        vec![order(dec!(100.1), dec!(0.0001)), order(dec!(105), dec!(10))],
+    );
+    assert_eq!(dusty.calculate_mid_price(), Some(dec!(100.05)));
+    let vwap_mid = dusty.calculate_vwap_mid_price(dec!(500)).unwrap();
+    assert!((vwap_mid - dec!(102.5)).abs() < dec!(0.001));
+
+    // Both sides quote less than the notional
+    assert_eq!(
+        dusty.calculate_vwap_mid_price(dec!(1000000)),
+        Some(((dec!(100) + dec!(1050.01001) / dec!(10.0001)) / dec!(2)).round_dp(8))
+    );
+
+    assert_eq!(order_book.calculate_vwap_mid_price(dec!(0)), None);
+    let crossed = book(
+        vec![order(dec!(101), dec!(1))],
+        vec![order(dec!(100), dec!(1))],
+    );
+    assert_eq!(crossed.calculate_vwap_mid_price(dec!(1000)), None);
+}
+
+/// Tests the size-weighted microprice.
+///
+/// This test verifies:
+/// 1. The microprice leans toward the best level of the side with less size
+/// 2. Equal sizes give the top-of-book mid
+/// 3. A book without size at its best levels has no microprice
+#[test]
+fn test_microprice() {
+    let order_book = book(
+        vec![order(dec!(100), dec!(1)), order(dec!(99), dec!(50))],
+        vec![order(dec!(101), dec!(3)), order(dec!(102), dec!(50))],
+    );
+    assert_eq!(order_book.calculate_microprice(), Some(dec!(100.25)));
+
+    let balanced = book(
+        vec![order(dec!(100), dec!(2))],
+        vec![order(dec!(101), dec!(2))],
+    );
+    assert_eq!(balanced.calculate_microprice(), Some(dec!(100.5)));
+
+    let empty_levels = book(
+        vec![order(dec!(100), dec!(0))],
+        vec![order(dec!(101), dec!(0))],
+    );
+    assert_eq!(empty_levels.calculate_microprice(), None);
+}
+
+/// Tests the impact price of a market order and the impact mid-price.
+///
+/// This test verifies:
+/// 1. A buy walks the asks and a sell walks the bids
+/// 2. The impact mid-price averages the buy and sell impact prices
+/// 3. A size larger than the side or not positive has no impact price
+#[test]
+fn test_impact_price() {
+    let order_book = book(
+        vec![order(dec!(100), dec!(1)), order(dec!(99), dec!(3))],
+        vec![order(dec!(101), dec!(1)), order(dec!(102), dec!(3))],
+    );
+
+    assert_eq!(
+        order_book.calculate_impact_price(Side::Buy, dec!(2)),
+        Some(dec!(101.5))
+    );
+    assert_eq!(
+        order_book.calculate_impact_price(Side::Sell, dec!(2)),
+        Some(dec!(99.5))
+    );
+    assert_eq!(
+        order_book.calculate_impact_price(Side::Buy, dec!(0.5)),
+        Some(dec!(101))
+    );
+    assert_eq!(
+        order_book.calculate_impact_mid_price(dec!(2)),
+        Some(dec!(100.5))
+    );
+
+    assert_eq!(order_book.calculate_impact_price(Side::Buy, dec!(5)), None);
+    assert_eq!(order_book.calculate_impact_mid_price(dec!(5)), None);
+    assert_eq!(order_book.calculate_impact_price(Side::Sell, dec!(0)), None);
+}
+
+/// Tests that the configured method selects the mid-price calculation.
+///
+/// This test verifies:
+/// 1. Each method gives the result of its calculator with the configured size
+/// 2. Every method rejects a crossed book
+#[test]
+fn test_configured_mid_price() {
+    let order_book = book(
+        vec![order(dec!(100), dec!(1)), order(dec!(99), dec!(30))],
+        vec![order(dec!(101), dec!(3)), order(dec!(102), dec!(30))],
+    );
+
+    assert_eq!(
+        order_book.calculate_mid_price_with(&calculator(MidPriceMethod::TopOfBook)),
+        order_book.calculate_mid_price()
+    );
+    assert_eq!(
+        order_book.calculate_mid_price_with(&calculator(MidPriceMethod::Vwap)),
+        order_book.calculate_vwap_mid_price(dec!(1000))
+    );
+    assert_eq!(
+        order_book.calculate_mid_price_with(&calculator(MidPriceMethod::Microprice)),
+        Some(dec!(100.25))
+    );
+    assert_eq!(
+        order_book.calculate_mid_price_with(&calculator(MidPriceMethod::Impact)),
+        Some(dec!(100.25))
+    );
+
+    let crossed = book(
+        vec![order(dec!(101), dec!(1))],
+        vec![order(dec!(100), dec!(1))],
+    );
+    for method in [
+        MidPriceMethod::TopOfBook,
+        MidPriceMethod::Vwap,
+        MidPriceMethod::Microprice,
+        MidPriceMethod::Impact,
+    ] {
+        assert_eq!(crossed.calculate_mid_price_with(&calculator(method)), None);
+    }
+}
@@ -0,0 +1,106 @@
+use global_price_index::models::{Decimal, Order, Precision, PriceLevelBook};
+use rust_decimal_macros::dec;
+
+/// Builds an order from a price and a quantity
+fn order(price: Decimal, quantity: Decimal) -> Order {
+    Order { price, quantity }
+}
+
+/// Tests that a book built from unsorted snapshot levels exposes them sorted,
+/// with the best levels cached.
+///
+/// This test verifies:
+/// 1. Bids are returned from highest to lowest price
+/// 2. Asks are returned from lowest to highest price
+/// 3. The best bid and best ask match the first level of each side
+#[test]
+fn test_price_level_book_sorted_snapshot() {
+    let book = PriceLevelBook::from_levels(
+        &[
+            order(dec!(99.0), dec!(1.0)),
+            order(dec!(100.0), dec!(2.0)),
+            order(dec!(98.0), dec!(3.0)),
+        ],
+        &[
+            order(dec!(102.0), dec!(1.0)),
+            order(dec!(101.0), dec!(2.0)),
+            order(dec!(103.0), dec!(3.0)),
+        ],
+    );
+
+    let bid_prices: Vec<Decimal> = book.bids().map(|o| o.price).collect();
+    let ask_prices: Vec<Decimal> = book.asks().map(|o| o.price).collect();
+    assert_eq!(bid_prices, vec![dec!(100.0), dec!(99.0), dec!(98.0)]);
+    assert_eq!(ask_prices, vec![dec!(101.0), dec!(102.0), dec!(103.0)]);
+
+    assert_eq!(book.best_bid(), Some(&order(dec!(100.0), dec!(2.0))));
+    assert_eq!(book.best_ask(), Some(&order(dec!(101.0), dec!(2.0))));
+}
+
+/// Tests that incremental updates add, update and remove levels and keep the
+/// cached best levels current.
+///
+/// This test verifies:
+/// 1. Updating an existing price changes its quantity without adding a level
+/// 2. A zero quantity removes the level, promoting the next best one
+/// 3. A new better price becomes the best level
+/// 4. Removing a price that does not exist is a no-op
+#[test]
+fn test_price_level_book_updates() {
+    let mut book = PriceLevelBook::from_levels(
+        &[order(dec!(100.0), dec!(1.0)), order(dec!(99.0), dec!(1.0))],
+        &[order(dec!(101.0), dec!(1.0)), order(dec!(102.0), dec!(1.0))],
+    );
+
+    book.apply_updates(
+        &[order(dec!(100.0), dec!(5.0))],
+        &[order(dec!(101.0), dec!(0.0))],
+    );
+    assert_eq!(book.bid_depth(), 2);
+    assert_eq!(book.best_bid(), Some(&order(dec!(100.0), dec!(5.0))));
+    assert_eq!(book.best_ask(), Some(&order(dec!(102.0), dec!(1.0))));
+
+    book.apply_updates(
+        &[order(dec!(100.5), dec!(1.0)), order(dec!(97.0), dec!(0.0))],
+        &[],
+    );
+    assert_eq!(book.bid_depth(), 3);
+    assert_eq!(book.best_bid(), Some(&order(dec!(100.5), dec!(1.0))));
+    assert!(!book.is_crossed());
+
+    book.apply_updates(&[order(dec!(102.0), dec!(1.0))], &[]);
+    assert!(book.is_crossed());
+}
+
+/// Tests that the same price quoted with different trailing zeros maps to
+/// a single level.
+///
+/// This test verifies:
+/// 1. "100.10" and "100.1" update the same level
+/// 2. No floating-point epsilon is involved in level matching
+#[test]
+fn test_price_level_book_levels_are_exact() {
+    let mut book = PriceLevelBook::from_levels(&[order(dec!(100.10), dec!(1.0))], &[]);
+    book.update_bid(dec!(100.1), dec!(2.0));
+    assert_eq!(book.bid_depth(), 1);
+
+    book.update_bid(dec!(100.1) + dec!(0.0000000001), dec!(1.0));
+    assert_eq!(book.bid_depth(), 2);
+}
+
+/// Tests that a venue's precision expresses prices and quantities with its
+/// tick and lot decimal places.
+///
+/// This test verifies:
+/// 1. Trailing zeros beyond the tick are dropped and missing ones are added
+/// 2. The tick and lot sizes match the configured scales
+#[test]
+fn test_precision_rescales_orders() {
+    let precision = Precision::new(2, 5);
+    let normalized = precision.order(order(dec!(50000.01000000), dec!(0.1)));
+
+    assert_eq!(normalized.price.to_string(), "50000.01");
+    assert_eq!(normalized.quantity.to_string(), "0.10000");
+    assert_eq!(precision.tick_size(), dec!(0.01));
+    assert_eq!(precision.lot_size(), dec!(0.00001));
+}
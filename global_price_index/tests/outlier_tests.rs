@@ -0,0 +1,229 @@
+mod common;
+
+use common::price;
+use global_price_index::{
+    config::{OutlierFilter, Settings},
+    models::{ExchangePrice, ExclusionReason, GlobalPriceIndex},
+    outlier::{filter_outliers, filter_stale, median},
+};
+use rust_decimal_macros::dec;
+use std::time::{Duration, SystemTime};
+
+/// Venue quorum the outliers are rejected with
+const MIN_VENUES: usize = 3;
+
+/// Returns an enabled filter with the given limits
+fn filter(max_deviation_pct: f64, mad_threshold: f64) -> OutlierFilter {
+    OutlierFilter {
+        enabled: true,
+        max_deviation_pct,
+        mad_threshold,
+    }
+}
+
+/// Returns the names of the kept venues
+fn names(kept: &[&ExchangePrice]) -> Vec<String> {
+    kept.iter().map(|price| price.exchange.clone()).collect()
+}
+
+/// Tests the median of odd and even numbers of values.
+///
+/// This test verifies:
+/// 1. The median of an odd number of values is the middle one
+/// 2. The median of an even number of values is the average of the middle ones
+/// 3. An empty list has no median
+#[test]
+fn test_median() {
+    assert_eq!(median(vec![dec!(3), dec!(1), dec!(2)]), Some(dec!(2)));
+    assert_eq!(
+        median(vec![dec!(4), dec!(1), dec!(2), dec!(3)]),
+        Some(dec!(2.5))
+    );
+    assert_eq!(median(vec![]), None);
+}
+
+/// Tests that the prices of books not updated within the maximum age are rejected.
+///
+/// This test verifies:
+/// 1. A price older than the maximum age is excluded as stale
+/// 2. Prices within the maximum age are kept
+/// 3. A maximum age beyond the oldest price keeps every price
+#[test]
+fn test_stale_prices_are_rejected() {
+    let now = SystemTime::now();
+    let mut frozen = price("Binance", dec!(50000));
+    frozen.timestamp = now - Duration::from_secs(60);
+    let prices = vec![frozen, price("Kraken", dec!(50010))];
+
+    let (fresh, excluded) = filter_stale(&prices, Duration::from_secs(30), now);
+    assert_eq!(fresh.len(), 1);
+    assert_eq!(fresh[0].exchange, "Kraken");
+    assert_eq!(excluded.len(), 1);
+    assert_eq!(excluded[0].exchange, "Binance");
+    assert_eq!(excluded[0].mid_price, dec!(50000));
+    assert_eq!(excluded[0].reason, ExclusionReason::Stale);
+
+    let (fresh, excluded) = filter_stale(&prices, Duration::from_secs(120), now);
+    assert_eq!(fresh.len(), 2);
+    assert!(excluded.is_empty());
+}
+
+/// Tests that a price too far from the median in percent is rejected.
+///
+/// This test verifies:
+/// 1. A price beyond the maximum percent deviation is excluded with its reason
+/// 2. Prices within the limit are kept
+#[test]
+fn test_max_deviation_rejects_bad_quote() {
+    let prices = vec![
+        price("Binance", dec!(50000)),
+        price("Kraken", dec!(50010)),
+        price("Huobi", dec!(55000)),
+    ];
+
+    let (kept, excluded) = filter_outliers(&prices, &filter(2.0, 0.0), MIN_VENUES);
+
+    assert_eq!(names(&kept), vec!["Binance", "Kraken"]);
+    assert_eq!(excluded.len(), 1);
+    assert_eq!(excluded[0].exchange, "Huobi");
+    assert_eq!(excluded[0].mid_price, dec!(55000));
+    assert_eq!(excluded[0].reason, ExclusionReason::MaxDeviation);
+}
+
+/// Tests that a price too far from the median in median absolute deviations
+/// is rejected.
+///
+/// This test verifies:
+/// 1. A price beyond the MAD threshold is excluded even within the percent limit
+/// 2. The MAD check is skipped when the MAD is zero
+#[test]
+fn test_mad_rejects_bad_quote() {
+    let prices = vec![
+        price("A", dec!(100.00)),
+        price("B", dec!(100.02)),
+        price("C", dec!(99.98)),
+        price("D", dec!(100.01)),
+        price("E", dec!(100.50)),
+    ];
+
+    let (kept, excluded) = filter_outliers(&prices, &filter(0.0, 5.0), MIN_VENUES);
+
+    assert_eq!(names(&kept), vec!["A", "B", "C", "D"]);
+    assert_eq!(excluded.len(), 1);
+    assert_eq!(excluded[0].exchange, "E");
+    assert_eq!(excluded[0].reason, ExclusionReason::MedianAbsoluteDeviation);
+
+    // Two identical prices make the MAD zero
+    let prices = vec![
+        price("A", dec!(100)),
+        price("B", dec!(100)),
+        price("C", dec!(101)),
+    ];
+    let (kept, excluded) = filter_outliers(&prices, &filter(0.0, 5.0), MIN_VENUES);
+    assert_eq!(kept.len(), 3);
+    assert!(excluded.is_empty());
+}
+
+/// Tests that outliers are only rejected with enough venues and that invalid
+/// prices are always rejected.
+///
+/// This test verifies:
+/// 1. Non-positive prices are excluded with their reason
+/// 2. Fewer valid venues than the quorum disables outlier rejection
+/// 3. A disabled filter keeps every valid price
+/// 4. Every valid price is kept when none is within the limits
+#[test]
+fn test_quorum_and_invalid_prices() {
+    let prices = vec![
+        price("Binance", dec!(50000)),
+        price("Kraken", dec!(0)),
+        price("Huobi", dec!(60000)),
+    ];
+
+    let (kept, excluded) = filter_outliers(&prices, &filter(2.0, 5.0), MIN_VENUES);
+    assert_eq!(names(&kept), vec!["Binance", "Huobi"]);
+    assert_eq!(excluded.len(), 1);
+    assert_eq!(excluded[0].exchange, "Kraken");
+    assert_eq!(excluded[0].reason, ExclusionReason::NonPositivePrice);
+
+    let prices = vec![
+        price("Binance", dec!(50000)),
+        price("Kraken", dec!(50010)),
+        price("Huobi", dec!(55000)),
+    ];
+    let disabled = OutlierFilter {
+        enabled: false,
+        ..filter(2.0, 5.0)
+    };
+    let (kept, excluded) = filter_outliers(&prices, &disabled, MIN_VENUES);
+    assert_eq!(kept.len(), 3);
+    assert!(excluded.is_empty());
+
+    let prices = vec![
+        price("A", dec!(100)),
+        price("B", dec!(100)),
+        price("C", dec!(200)),
+        price("D", dec!(200)),
+    ];
+    let (kept, excluded) = filter_outliers(&prices, &filter(2.0, 5.0), MIN_VENUES);
+    assert_eq!(kept.len(), 4);
+    assert!(excluded.is_empty());
+}
+
+/// Tests that the index leaves outliers out of its price and reports them.
+///
+/// This test verifies:
+/// 1. The index is computed from the remaining venues only
+/// 2. The excluded venue is listed with its snake_case reason in the JSON response
+/// 3. Every venue price is still listed in `exchange_prices`
+#[test]
+fn test_index_reports_excluded_venues() {
+    let index = GlobalPriceIndex::configured(
+        "BTC/USDT",
+        vec![
+            price("Binance", dec!(50000)),
+            price("Kraken", dec!(50010)),
+            price("Huobi", dec!(5000)),
+        ],
+    )
+    .expect("No index computed");
+
+    assert_eq!(index.price, dec!(50005));
+    assert_eq!(index.exchange_prices.len(), 3);
+
+    let json = serde_json::to_value(&index).unwrap();
+    assert_eq!(
+        json["excluded"],
+        serde_json::json!([{
+            "exchange": "Huobi",
+            "mid_price": "5000",
+            "reason": "max_deviation",
+        }])
+    );
+}
+
+/// Tests that the venues rejected as outliers do not count towards the quorum.
+///
+/// This test verifies:
+/// 1. Outliers are rejected among as many fresh venues as the quorum
+/// 2. The index then counts only the remaining venues and misses its quorum
+#[test]
+fn test_outliers_do_not_count_towards_quorum() {
+    let index = GlobalPriceIndex::new(
+        "BTC/USDT",
+        vec![
+            price("Binance", dec!(50000)),
+            price("Kraken", dec!(50010)),
+            price("Huobi", dec!(5000)),
+        ],
+        &Settings::default().price_weighting,
+        &filter(2.0, 5.0),
+        MIN_VENUES,
+    )
+    .expect("No index computed");
+
+    assert_eq!(index.excluded.len(), 1);
+    assert_eq!(index.excluded[0].reason, ExclusionReason::MaxDeviation);
+    assert_eq!((index.venues, index.min_venues), (2, MIN_VENUES));
+    assert!(!index.quorum_met);
+}
@@ -1,13 +1,18 @@
-use global_price_index::models::{Order, OrderBook};
+use global_price_index::models::{Decimal, Order, OrderBook, PriceLevelBook};
 use proptest::prelude::*;
 use std::time::SystemTime;
 
+/// Converts an integer number of hundredths to a two-decimal price or quantity
+fn cents(value: i64) -> Decimal {
+    Decimal::new(value, 2)
+}
+
 // Configure proptest to explicitly use a specific regression file
 proptest! {
     #![proptest_config(ProptestConfig {
         // Explicitly set the regression file path
         failure_persistence: Some(Box::new(proptest::test_runner::FileFailurePersistence::Direct(
-            "tests/property_tests.proptest-regressions".into()
+            "tests/property_tests.proptest-regressions"
         ))),
         cases: 100, // Number of test cases to run
         .. ProptestConfig::default()
@@ -18,15 +23,15 @@ proptest! {
     ///
     /// This test verifies:
     /// 1. The mid price is always between the bid and ask prices
-    /// 2. The mid price is exactly the average of bid and ask prices (within rounding precision)
-    /// 3. The rounding precision is consistent with our expected 2 decimal places
+    /// 2. The mid price is exactly the average of bid and ask prices, without rounding
+    /// 3. The mid price has at most one more decimal place than the quotes
     #[test]
     fn test_mid_price_properties(
-        // Generate random values for bid and ask prices and quantities
-        bid_price in 10000.0..100000.0f64,
-        bid_quantity in 0.1..10.0f64,
-        ask_price in 10000.0..100000.0f64,
-        ask_quantity in 0.1..10.0f64,
+        // Generate random values for bid and ask prices and quantities, with a 0.01 tick
+        bid_price in (1_000_000i64..10_000_000).prop_map(cents),
+        bid_quantity in (10i64..1000).prop_map(cents),
+        ask_price in (1_000_000i64..10_000_000).prop_map(cents),
+        ask_quantity in (10i64..1000).prop_map(cents),
     ) {
         // Skip invalid market conditions: in a valid market, ask price must be greater than bid price
         // This matches the real OrderBook.calculate_mid_price() implementation which returns None when ask <= bid
@@ -38,6 +43,7 @@ proptest! {
             bids: vec![Order { price: bid_price, quantity: bid_quantity }],
             asks: vec![Order { price: ask_price, quantity: ask_quantity }],
             timestamp: SystemTime::now(),
+            exchange_timestamp: None,
         };
 
         let mid_price = order_book.calculate_mid_price().unwrap();
@@ -46,15 +52,11 @@ proptest! {
         assert!(mid_price > bid_price);
         assert!(mid_price < ask_price);
 
-        // Property 2: Mid price should be the average of bid and ask prices, rounded to 2 decimal places
-        let expected_mid = (bid_price + ask_price) / 2.0;
-        let rounded_expected = (expected_mid * 100.0).round() / 100.0;
-        assert_eq!(mid_price, rounded_expected);
+        // Property 2: Mid price should be exactly the average of bid and ask prices
+        assert_eq!(mid_price * Decimal::TWO, bid_price + ask_price);
 
-        // Property 3: The absolute difference between mid price and expected mid price
-        // should be less than 0.01 (our rounding precision)
-        let absolute_diff = (mid_price - expected_mid).abs();
-        assert!(absolute_diff <= 0.01);
+        // Property 3: Mid price should have at most 3 decimal places for a 0.01 tick
+        assert!(mid_price.scale() <= 3);
     }
 
     /// Tests that an order book with an empty side (either bids or asks)
@@ -68,7 +70,10 @@ proptest! {
         // Generate a random boolean to decide whether to have empty bids or empty asks
         is_empty_bids in proptest::bool::ANY,
         // Generate a small number of orders for the non-empty side
-        valid_prices in prop::collection::vec((10000.0..100000.0f64, 0.1..10.0f64), 1..5),
+        valid_prices in prop::collection::vec(
+            ((1_000_000i64..10_000_000).prop_map(cents), (10i64..1000).prop_map(cents)),
+            1..5
+        ),
     ) {
         let mut bids = Vec::new();
         let mut asks = Vec::new();
@@ -80,20 +85,21 @@ proptest! {
                 asks.push(Order { price, quantity });
             }
             // Sort asks in ascending order
-            asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
+            asks.sort_by_key(|order| order.price);
         } else {
             // Leave asks empty, populate bids
             for (price, quantity) in valid_prices {
                 bids.push(Order { price, quantity });
             }
             // Sort bids in descending order
-            bids.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap_or(std::cmp::Ordering::Equal));
+            bids.sort_by_key(|order| std::cmp::Reverse(order.price));
         }
 
         let order_book = OrderBook {
             bids,
             asks,
             timestamp: SystemTime::now(),
+            exchange_timestamp: None,
         };
 
         // Property: An order book with empty bids or empty asks should not have a valid mid price
@@ -111,17 +117,18 @@ proptest! {
         // Generate a random boolean to decide whether to have non-positive bid or ask
         is_non_positive_bid in proptest::bool::ANY,
         // Generate a non-positive price
-        non_positive_price in -100.0..=0.0f64,
+        non_positive_price in (-10_000i64..=0).prop_map(cents),
         // Generate a positive price for the other side
-        positive_price in 10000.0..100000.0f64,
+        positive_price in (1_000_000i64..10_000_000).prop_map(cents),
         // Generate quantities
-        quantity1 in 0.1..10.0f64,
-        quantity2 in 0.1..10.0f64,
+        quantity1 in (10i64..1000).prop_map(cents),
+        quantity2 in (10i64..1000).prop_map(cents),
     ) {
         let mut order_book = OrderBook {
             bids: vec![],
             asks: vec![],
             timestamp: SystemTime::now(),
+            exchange_timestamp: None,
         };
 
         if is_non_positive_bid {
@@ -148,7 +155,7 @@ proptest! {
     #[test]
     fn test_order_book_validation(
         prices in prop::collection::vec(
-            (10000.0..100000.0f64, 0.1..10.0f64),
+            ((1_000_000i64..10_000_000).prop_map(cents), (10i64..1000).prop_map(cents)),
             0..10
         ),
     ){
@@ -156,7 +163,7 @@ proptest! {
         let mut asks = Vec::new();
 
         for (price, quantity) in prices {
-            if price < 50000.0 {
+            if price < Decimal::from(50000) {
                 bids.push(Order { price, quantity });
             } else {
                 asks.push(Order { price, quantity });
@@ -164,13 +171,14 @@ proptest! {
         }
 
         // Sort bids in descending order, asks in ascending order
-        bids.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap_or(std::cmp::Ordering::Equal));
-        asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
+        bids.sort_by_key(|order| std::cmp::Reverse(order.price));
+        asks.sort_by_key(|order| order.price);
 
         let order_book = OrderBook {
             bids,
             asks,
             timestamp: SystemTime::now(),
+            exchange_timestamp: None,
         };
 
         // Property 1: If we have both bids and asks, the best bid should be less than the best ask
@@ -200,4 +208,47 @@ proptest! {
         }
     }
 
+    /// Tests that a PriceLevelBook stays sorted and consistent under a random
+    /// sequence of level updates, including removals.
+    ///
+    /// This test verifies:
+    /// 1. Bids are in strictly descending order and asks in strictly ascending order
+    /// 2. The cached best bid and best ask match the first level of each side
+    /// 3. No level with a zero quantity is kept
+    #[test]
+    fn test_price_level_book_invariants(
+        updates in prop::collection::vec(
+            (proptest::bool::ANY, 1i64..200, prop_oneof![Just(0i64), 10i64..1000].prop_map(cents)),
+            0..200
+        ),
+    ) {
+        let mut book = PriceLevelBook::new();
+
+        for (is_bid, tick, quantity) in updates {
+            let price = Decimal::new(tick * 5, 1);
+            if is_bid {
+                book.update_bid(price, quantity);
+            } else {
+                book.update_ask(price, quantity);
+            }
+        }
+
+        let bids: Vec<Order> = book.bids().collect();
+        let asks: Vec<Order> = book.asks().collect();
+
+        // Property 1: Each side is strictly sorted
+        for pair in bids.windows(2) {
+            assert!(pair[0].price > pair[1].price, "Bids should be in descending order");
+        }
+        for pair in asks.windows(2) {
+            assert!(pair[0].price < pair[1].price, "Asks should be in ascending order");
+        }
+
+        // Property 2: Cached best levels match the sorted sides
+        assert_eq!(book.best_bid(), bids.first());
+        assert_eq!(book.best_ask(), asks.first());
+
+        // Property 3: Removed levels are gone
+        assert!(bids.iter().chain(asks.iter()).all(|o| o.quantity > Decimal::ZERO));
+    }
 }
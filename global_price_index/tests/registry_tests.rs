@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use global_price_index::{
+    aggregator::Aggregator,
+    config::{Settings, CONFIG_FILE},
+    error::{PriceIndexError, Result},
+    exchanges::{enabled_markets, registry::ExchangeRegistry, Exchange, ExchangeKind},
+    models::{Order, OrderBook},
+};
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::{sleep, Duration};
+
+/// Exchange stand-in quoting a fixed BTC/USDT book
+struct MockExchange;
+
+#[async_trait]
+impl Exchange for MockExchange {
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        Ok(OrderBook {
+            bids: vec![Order {
+                price: dec!(99),
+                quantity: dec!(1),
+            }],
+            asks: vec![Order {
+                price: dec!(101),
+                quantity: dec!(1),
+            }],
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        })
+    }
+}
+
+/// Huobi stand-in recording whether its feed was stopped
+#[derive(Default)]
+struct StoppableExchange {
+    stopped: AtomicBool,
+}
+
+#[async_trait]
+impl Exchange for StoppableExchange {
+    fn name(&self) -> &'static str {
+        "Huobi"
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        MockExchange.fetch_order_book().await
+    }
+}
+
+/// Tests that an exchange failing to connect is retried in the background
+/// and joins the registry once it succeeds.
+///
+/// This test verifies:
+/// 1. Failed connection attempts are retried
+/// 2. The exchange is not registered before it connects
+/// 3. The connected exchange is registered and feeds the aggregator
+#[tokio::test(start_paused = true)]
+async fn test_failed_exchange_is_retried() {
+    let registry = ExchangeRegistry::new();
+    let aggregator = Aggregator::new(16);
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let counter = attempts.clone();
+    let connect = move || {
+        let attempt = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if attempt < 2 {
+                Err(PriceIndexError::ExchangeError(
+                    "Exchange unavailable".to_string(),
+                ))
+            } else {
+                Ok(Arc::new(MockExchange) as Arc<dyn Exchange>)
+            }
+        }
+    };
+    registry.connect_in_background("Mock", connect, Duration::from_secs(1), &aggregator);
+
+    sleep(Duration::from_millis(100)).await;
+    assert!(registry.exchanges().is_empty());
+
+    // Retries after the initial delay and twice that
+    sleep(Duration::from_secs(10)).await;
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(registry.for_symbol("BTC/USDT").len(), 1);
+    assert!(registry.for_symbol("ETH/USDT").is_empty());
+    assert_eq!(aggregator.latest("BTC/USDT").unwrap().price, dec!(100));
+}
+
+/// Tests that only enabled exchanges and markets are built from the configuration.
+///
+/// This test verifies:
+/// 1. Every indexed symbol is mapped to the exchanges listing it
+/// 2. A disabled exchange contributes no market
+/// 3. A disabled market is skipped while the exchange's other markets remain
+#[test]
+fn test_configured_markets_respect_enabled_flags() {
+    let mut settings = Settings::load(CONFIG_FILE).expect("Failed to load config.toml");
+    settings.index.symbols = vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()];
+    let markets = enabled_markets(&settings);
+    assert!(markets.contains(&(ExchangeKind::Kraken, "BTC/USDT".to_string())));
+    assert!(markets.contains(&(ExchangeKind::Binance, "ETH/USDT".to_string())));
+
+    settings.exchange.kraken.enabled = false;
+    for market in settings.exchange.binance.markets.iter_mut() {
+        market.enabled = market.symbol != "ETH/USDT";
+    }
+    let markets = enabled_markets(&settings);
+
+    assert!(markets
+        .iter()
+        .all(|(kind, _)| *kind != ExchangeKind::Kraken));
+    assert!(!markets.contains(&(ExchangeKind::Binance, "ETH/USDT".to_string())));
+    assert!(markets.contains(&(ExchangeKind::Binance, "BTC/USDT".to_string())));
+    assert!(markets.contains(&(ExchangeKind::Huobi, "ETH/USDT".to_string())));
+}
+
+/// Tests that a stopped market leaves the registry and the index.
+///
+/// This test verifies:
+/// 1. A market still connecting is no longer listed nor retried
+/// 2. A connected exchange is unregistered and its feed stopped
+/// 3. Its venue is removed from the index and no longer read
+#[tokio::test(start_paused = true)]
+async fn test_stopped_market_is_removed() {
+    let registry = ExchangeRegistry::new();
+    let aggregator = Aggregator::new(16);
+
+    // No Huobi market is configured for this symbol, so it keeps retrying
+    registry.start(ExchangeKind::Huobi, "MTR/USDT".to_string(), &aggregator);
+    assert_eq!(registry.markets().len(), 1);
+    registry
+        .stop(ExchangeKind::Huobi, "MTR/USDT", &aggregator)
+        .await;
+    assert!(registry.markets().is_empty());
+
+    let exchange = Arc::new(StoppableExchange::default());
+    let connected = exchange.clone();
+    registry.connect_in_background(
+        "Huobi",
+        move || {
+            let exchange = connected.clone();
+            async move { Ok(exchange as Arc<dyn Exchange>) }
+        },
+        Duration::from_secs(1),
+        &aggregator,
+    );
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(registry.for_symbol("BTC/USDT").len(), 1);
+    assert!(aggregator.latest("BTC/USDT").is_some());
+
+    registry
+        .stop(ExchangeKind::Huobi, "BTC/USDT", &aggregator)
+        .await;
+    assert!(registry.exchanges().is_empty());
+    assert!(exchange.stopped.load(Ordering::SeqCst));
+    assert!(aggregator.latest("BTC/USDT").is_none());
+
+    sleep(Duration::from_secs(5)).await;
+    assert!(aggregator.exchange_prices("BTC/USDT").is_empty());
+}
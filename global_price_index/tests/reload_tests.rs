@@ -0,0 +1,270 @@
+use actix_web::{http::StatusCode, test as actix_test, web, App};
+use global_price_index::{
+    aggregator::{Aggregator, IndexSettings},
+    api::AppState,
+    config::{ConfigSources, Settings, CONFIG_FILE},
+    exchanges::{enabled_markets, registry::ExchangeRegistry, ExchangeKind},
+    reload::{post_reload, ConfigReloader, MarketChanges},
+};
+use std::path::{Path, PathBuf};
+use tokio::time::{sleep, Duration, Instant};
+
+/// Returns the checked-in configuration
+fn settings() -> Settings {
+    Settings::load(CONFIG_FILE).expect("Failed to load config.toml")
+}
+
+/// Writes the checked-in configuration with one line replaced to a temporary file
+fn write_config(path: &PathBuf, line: &str, replacement: &str) {
+    let config = std::fs::read_to_string(CONFIG_FILE).unwrap();
+    assert!(config.contains(line), "config.toml has no line {:?}", line);
+    std::fs::write(path, config.replace(line, replacement)).unwrap();
+}
+
+/// Returns the state of a service started with the checked-in configuration
+fn app_state() -> AppState {
+    let settings = settings();
+    let aggregator = Aggregator::new(16).with_settings(IndexSettings::from(&settings));
+    AppState::new(
+        settings.index.symbols.clone(),
+        ExchangeRegistry::with_settings(settings.clone()),
+        aggregator,
+    )
+    .with_settings(settings)
+}
+
+/// Returns the sources of a configuration file alone
+fn file_sources(path: &Path) -> ConfigSources {
+    ConfigSources {
+        file: path.to_path_buf(),
+        env: false,
+        ..ConfigSources::default()
+    }
+}
+
+/// Returns a path in the temporary directory unique to this test run
+fn temp_config(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "global_price_index_{}_{}.toml",
+        name,
+        std::process::id()
+    ))
+}
+
+/// Tests the exchange markets affected by a configuration change.
+///
+/// This test verifies:
+/// 1. Settings read on every use, such as the decay factor or the poll
+///    intervals, affect no market
+/// 2. A changed exchange URL restarts every market of the exchange
+/// 3. Changed connection settings restart every market
+/// 4. A disabled market or exchange is stopped
+/// 5. A newly enabled market is started
+#[test]
+fn test_market_changes() {
+    let old = settings();
+    let market = |kind, symbol: &str| (kind, symbol.to_string());
+
+    let mut new = old.clone();
+    new.price_weighting.decay_factor = 60.0;
+    new.health.min_venues = 1;
+    new.exchange.binance.poll_interval_ms *= 2;
+    new.exchange.kraken.poll_interval_ms *= 2;
+    new.exchange.huobi.poll_interval_ms *= 2;
+    assert!(MarketChanges::between(&old, &new).is_empty());
+
+    let mut new = old.clone();
+    new.exchange.config.max_reconnect_delay = 10;
+    let changes = MarketChanges::between(&old, &new);
+    assert!(changes.stopped.is_empty() && changes.started.is_empty());
+    assert_eq!(changes.restarted, enabled_markets(&old));
+
+    let mut new = old.clone();
+    new.exchange.kraken.ws_url = "wss://ws.kraken.example".to_string();
+    let changes = MarketChanges::between(&old, &new);
+    assert!(changes.stopped.is_empty() && changes.started.is_empty());
+    assert_eq!(changes.restarted.len(), old.exchange.kraken.markets.len());
+    assert!(changes
+        .restarted
+        .iter()
+        .all(|(kind, _)| *kind == ExchangeKind::Kraken));
+
+    let mut new = old.clone();
+    new.exchange.huobi.enabled = false;
+    new.exchange.binance.markets[0].enabled = false;
+    let changes = MarketChanges::between(&old, &new);
+    assert!(changes
+        .stopped
+        .contains(&market(ExchangeKind::Binance, "BTC/USDT")));
+    assert!(changes
+        .stopped
+        .contains(&market(ExchangeKind::Huobi, "BTC/USDT")));
+    assert!(!changes
+        .stopped
+        .contains(&market(ExchangeKind::Binance, "ETH/USDT")));
+    assert!(changes.restarted.is_empty());
+
+    let changes = MarketChanges::between(&new, &old);
+    assert!(changes
+        .started
+        .contains(&market(ExchangeKind::Binance, "BTC/USDT")));
+    assert!(changes.stopped.is_empty());
+}
+
+/// Tests the validation of the settings.
+///
+/// This test verifies:
+/// 1. The checked-in configuration is valid
+/// 2. Invalid URLs and out-of-range values are rejected with the offending key
+/// 3. A missing file is an error rather than a fallback to defaults
+#[test]
+fn test_settings_validation() {
+    assert!(settings().validate().is_ok());
+
+    let mut invalid = settings();
+    invalid.exchange.binance.ws_url = "not a url".to_string();
+    let error = invalid.validate().unwrap_err().to_string();
+    assert!(error.contains("exchange.binance.ws_url"), "{}", error);
+
+    let mut invalid = settings();
+    invalid.price_weighting.max_weight = 2.0;
+    assert!(invalid.validate().is_err());
+
+    assert!(Settings::load("missing.toml").is_err());
+}
+
+/// Tests the POST /admin/reload endpoint.
+///
+/// This test verifies:
+/// 1. A valid configuration is applied and the affected markets are returned
+/// 2. The requests are served, the markets connected and the index computed
+///    with the reloaded settings
+/// 3. An invalid configuration answers 422 and the running one is kept
+/// 4. Requests from other hosts than the loopback interface answer 403
+///    without reloading
+#[actix_web::test]
+async fn test_reload_endpoint() {
+    let path = temp_config("endpoint");
+    let state = app_state();
+    let aggregator = state.aggregator.clone();
+    let reloader = ConfigReloader::new(file_sources(&path), state.clone());
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(web::Data::new(reloader))
+            .route("/admin/reload", web::post().to(post_reload)),
+    )
+    .await;
+    let reload = || {
+        actix_test::TestRequest::post()
+            .uri("/admin/reload")
+            .peer_addr("127.0.0.1:40000".parse().unwrap())
+            .to_request()
+    };
+
+    write_config(&path, "decay_factor = 300", "decay_factor = 120");
+    let response = actix_test::call_service(&app, reload()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let changes: serde_json::Value = actix_test::read_body_json(response).await;
+    assert_eq!(
+        changes,
+        serde_json::json!({"stopped": [], "started": [], "restarted": []})
+    );
+    assert_eq!(state.settings().price_weighting.decay_factor, 120.0);
+    assert_eq!(
+        state.registry.settings().price_weighting.decay_factor,
+        120.0
+    );
+    assert_eq!(aggregator.settings().price_weighting.decay_factor, 120.0);
+
+    write_config(&path, "max_weight = 0", "max_weight = 2");
+    let response = actix_test::call_service(&app, reload()).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: serde_json::Value = actix_test::read_body_json(response).await;
+    assert!(error["error"].as_str().unwrap().contains("max_weight"));
+    assert_eq!(state.settings().price_weighting.decay_factor, 120.0);
+    assert_eq!(aggregator.settings().price_weighting.max_weight, 0.0);
+
+    std::fs::write(&path, std::fs::read_to_string(CONFIG_FILE).unwrap()).unwrap();
+    let response = actix_test::call_service(&app, reload()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    write_config(&path, "decay_factor = 300", "decay_factor = 60");
+    for peer in [Some("203.0.113.7:40000"), None] {
+        let mut request = actix_test::TestRequest::post().uri("/admin/reload");
+        if let Some(peer) = peer {
+            request = request.peer_addr(peer.parse().unwrap());
+        }
+        let response = actix_test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    assert_eq!(state.settings().price_weighting.decay_factor, 300.0);
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Tests that a service started without its optional configuration file reloads.
+///
+/// This test verifies:
+/// 1. A missing optional file reloads the default values
+/// 2. The file is applied once created
+/// 3. A missing required file is an error
+#[actix_web::test]
+async fn test_reload_without_optional_file() {
+    let path = temp_config("optional");
+    let _ = std::fs::remove_file(&path);
+    let state = app_state();
+
+    let reloader = ConfigReloader::new(file_sources(&path), state.clone());
+    reloader
+        .reload()
+        .await
+        .expect("Missing optional file rejected");
+    write_config(&path, "decay_factor = 300", "decay_factor = 90");
+    reloader.reload().await.expect("Created file rejected");
+    assert_eq!(state.settings().price_weighting.decay_factor, 90.0);
+    let _ = std::fs::remove_file(&path);
+
+    let required = ConfigReloader::new(
+        ConfigSources {
+            required: true,
+            ..file_sources(&path)
+        },
+        state.clone(),
+    );
+    assert!(required.reload().await.is_err());
+    assert_eq!(state.settings().price_weighting.decay_factor, 90.0);
+}
+
+/// Tests that changes to the watched configuration file are applied.
+///
+/// This test verifies:
+/// 1. Saving the file reloads the configuration
+/// 2. Saving an invalid file keeps the running configuration
+#[tokio::test]
+async fn test_config_file_watch() {
+    let path = temp_config("watch");
+    write_config(&path, "decay_factor = 300", "decay_factor = 300");
+    let state = app_state();
+    let reloader = ConfigReloader::new(file_sources(&path), state.clone());
+    let watch = reloader.watch().expect("Failed to watch the configuration");
+
+    let decay_factor = || state.settings().price_weighting.decay_factor;
+    let wait_for_decay_factor = |expected: f64| async move {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while decay_factor() != expected {
+            assert!(Instant::now() < deadline, "Configuration not reloaded");
+            sleep(Duration::from_millis(20)).await;
+        }
+    };
+
+    write_config(&path, "decay_factor = 300", "decay_factor = 150");
+    wait_for_decay_factor(150.0).await;
+
+    write_config(&path, "decay_factor = 300", "decay_factor = -1");
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(decay_factor(), 150.0);
+
+    write_config(&path, "decay_factor = 300", "decay_factor = 300");
+    wait_for_decay_factor(300.0).await;
+    watch.abort();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,260 @@
+use global_price_index::{
+    aggregator::Aggregator,
+    capture::{Capture, CaptureEvent, FeedRecorder},
+    config::{
+        get_binance_market, get_huobi_market, get_huobi_url, get_kraken_market, get_kraken_url,
+        Settings, CONFIG_FILE,
+    },
+    exchanges::{
+        binance::BinanceExchange, huobi::HuobiExchange, kraken::KrakenExchange, Exchange,
+        ExchangeKind,
+    },
+    replay::ReplayServer,
+};
+use rust_decimal_macros::dec;
+use tokio::time::{sleep, Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Starts replaying a capture from the fixtures directory
+async fn replay(fixture: &str) -> (Capture, ReplayServer) {
+    let capture =
+        Capture::load(format!("tests/fixtures/{}", fixture)).expect("Failed to load capture");
+    let server = ReplayServer::start(&capture)
+        .await
+        .expect("Failed to start replay server");
+    (capture, server)
+}
+
+/// Tests that a recorded feed is read back as it was received.
+///
+/// This test verifies:
+/// 1. REST bodies, connections, text, binary and close frames are written as
+///    JSON lines once flushed
+/// 2. Ping and pong frames are not recorded
+/// 3. The records are read back in order with non-decreasing receive times
+/// 4. Frames are grouped by the connection they were received on
+/// 5. A disabled recorder writes nothing
+#[test]
+fn test_record_and_load_capture() {
+    let path = std::env::temp_dir().join(format!(
+        "global-price-index-capture-{}.jsonl",
+        std::process::id()
+    ));
+    let recorder = FeedRecorder::create(&path).expect("Failed to create capture");
+    assert_eq!(recorder.path(), Some(path.as_path()));
+
+    recorder.record_rest(
+        "https://api.huobi.pro/market/depth?symbol=btcusdt",
+        "{\"status\":\"ok\"}",
+    );
+    recorder.record_connect("wss://api.huobi.pro/ws");
+    recorder.record_frame(&Message::Binary(vec![0x1f, 0x8b, 0x00, 0xff]));
+    recorder.record_frame(&Message::Ping(vec![1]));
+    recorder.clone().record_frame(&Message::Close(None));
+    recorder.record_connect("wss://api.huobi.pro/ws");
+    recorder.record_frame(&Message::Text("{\"ping\":1}".to_string()));
+    recorder.record_frame(&Message::Pong(vec![1]));
+    recorder.flush().expect("Failed to flush capture");
+
+    let capture = Capture::load(&path).expect("Failed to load capture");
+    std::fs::remove_file(&path).unwrap();
+
+    let events: Vec<CaptureEvent> = capture
+        .records
+        .iter()
+        .map(|record| record.event.clone())
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            CaptureEvent::Rest {
+                url: "https://api.huobi.pro/market/depth?symbol=btcusdt".to_string(),
+                body: "{\"status\":\"ok\"}".to_string(),
+            },
+            CaptureEvent::Connect {
+                url: "wss://api.huobi.pro/ws".to_string(),
+            },
+            CaptureEvent::Binary {
+                data: "H4sA/w==".to_string(),
+            },
+            CaptureEvent::Close,
+            CaptureEvent::Connect {
+                url: "wss://api.huobi.pro/ws".to_string(),
+            },
+            CaptureEvent::Text {
+                data: "{\"ping\":1}".to_string(),
+            },
+        ]
+    );
+    assert!(capture
+        .records
+        .windows(2)
+        .all(|pair| pair[0].timestamp_ms <= pair[1].timestamp_ms));
+
+    let sessions = capture.sessions();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(
+        sessions[0].frames,
+        vec![
+            Message::Binary(vec![0x1f, 0x8b, 0x00, 0xff]),
+            Message::Close(None)
+        ]
+    );
+    assert_eq!(
+        sessions[1].frames,
+        vec![Message::Text("{\"ping\":1}".to_string())]
+    );
+
+    let disabled = FeedRecorder::disabled();
+    disabled.record_connect("wss://api.huobi.pro/ws");
+    assert_eq!(disabled.path(), None);
+}
+
+/// Tests that captured Binance, Kraken and Huobi feeds give a reproducible index offline.
+///
+/// This test verifies:
+/// 1. Each exchange client is created against its replayed feed
+/// 2. REST responses are served in the order they were recorded, the last
+///    one repeating once all were served
+/// 3. The Binance book is merged from the replayed snapshot and depth stream
+/// 4. The index of the replayed venue prices is always the same
+/// 5. Requests without a recorded response are answered with 404
+#[tokio::test]
+async fn test_replayed_pipeline() {
+    let (binance_capture, binance_server) = replay("binance-BTC-USDT.jsonl").await;
+    let (_, kraken_server) = replay("kraken-BTC-USDT.jsonl").await;
+    let (_, huobi_server) = replay("huobi-BTC-USDT.jsonl").await;
+
+    let settings = Settings::default();
+    let exchange = &settings.exchange;
+    let (snapshot_url, _) = binance_capture.rest_responses().next().unwrap();
+    let binance = BinanceExchange::with_urls(
+        get_binance_market("BTC/USDT").unwrap(),
+        binance_server.rest_url(snapshot_url),
+        binance_server.ws_url(),
+        &exchange.binance.ticker_url,
+        &exchange.config,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Binance exchange");
+    let kraken = KrakenExchange::with_url(
+        get_kraken_market("BTC/USDT").unwrap(),
+        kraken_server.rest_url(&get_kraken_url()),
+        &exchange.kraken.ticker_url,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Kraken exchange");
+    let huobi = HuobiExchange::with_url(
+        get_huobi_market("BTC/USDT").unwrap(),
+        huobi_server.rest_url(&get_huobi_url()),
+        &exchange.huobi.ticker_url,
+        &settings.capture,
+    )
+    .await
+    .expect("Failed to create Huobi exchange");
+
+    assert_eq!(
+        kraken.get_mid_price().await.unwrap().mid_price,
+        dec!(78894.35)
+    );
+    assert_eq!(
+        huobi.get_mid_price().await.unwrap().mid_price,
+        dec!(78891.51)
+    );
+
+    // The Binance stream is fully replayed after its reconnection
+    binance_server.wait_until_replayed().await;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let binance_price = loop {
+        let price = binance.get_mid_price().await.unwrap();
+        if price.mid_price == dec!(78890.35) {
+            break price;
+        }
+        assert!(Instant::now() < deadline, "Binance book never caught up");
+        sleep(Duration::from_millis(20)).await;
+    };
+
+    let aggregator = Aggregator::new(16);
+    aggregator.record_price("BTC/USDT", binance_price);
+    for _ in 0..2 {
+        aggregator.record_price("BTC/USDT", kraken.get_mid_price().await.unwrap());
+        aggregator.record_price("BTC/USDT", huobi.get_mid_price().await.unwrap());
+    }
+
+    let index = aggregator.latest("BTC/USDT").expect("No index computed");
+    let venues: Vec<(&str, _)> = index
+        .exchange_prices
+        .iter()
+        .map(|price| (price.exchange.as_str(), price.mid_price))
+        .collect();
+    assert_eq!(
+        venues,
+        vec![
+            ("Binance", dec!(78890.35)),
+            ("Huobi", dec!(78892.15)),
+            ("Kraken", dec!(78892.95)),
+        ]
+    );
+    assert!(index.excluded.is_empty());
+    assert_eq!(index.price, dec!(78891.81666667));
+
+    let response = reqwest::get(kraken_server.rest_url("https://api.kraken.com/0/public/Ticker"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+/// Tests that exchanges are created from explicit configurations, independently of the global one.
+///
+/// This test verifies:
+/// 1. A Kraken client is created against the endpoint of its configuration
+/// 2. `ExchangeKind::connect_with` creates the REST client of settings that do not stream
+/// 3. A symbol whose market is disabled or missing in the configuration is an error
+#[tokio::test]
+async fn test_explicitly_configured_exchanges() {
+    let (_, kraken_server) = replay("kraken-BTC-USDT.jsonl").await;
+    let (_, huobi_server) = replay("huobi-BTC-USDT.jsonl").await;
+
+    let mut settings = Settings::load(CONFIG_FILE).expect("Failed to load config.toml");
+    settings.exchange.kraken.url = kraken_server.rest_url(&settings.exchange.kraken.url);
+    settings.exchange.huobi.url = huobi_server.rest_url(&settings.exchange.huobi.url);
+    settings.exchange.huobi.use_websocket = false;
+
+    let kraken = KrakenExchange::new("BTC/USDT", &settings.exchange.kraken, &settings.capture)
+        .await
+        .expect("Failed to create Kraken exchange");
+    assert_eq!(
+        kraken.get_mid_price().await.unwrap().mid_price,
+        dec!(78894.35)
+    );
+
+    let huobi = ExchangeKind::Huobi
+        .connect_with(&settings, "BTC/USDT")
+        .await
+        .expect("Failed to create Huobi exchange");
+    assert_eq!(huobi.name(), "Huobi");
+    assert_eq!(
+        huobi.get_mid_price().await.unwrap().mid_price,
+        dec!(78891.51)
+    );
+
+    let mut disabled = settings.exchange.kraken.clone();
+    for market in disabled.markets.iter_mut() {
+        market.enabled = false;
+    }
+    for (symbol, config) in [
+        ("BTC/USDT", &disabled),
+        ("MTR/USDT", &settings.exchange.kraken),
+    ] {
+        match KrakenExchange::new(symbol, config, &settings.capture).await {
+            Err(e) => assert!(
+                e.to_string().contains("No Kraken market configured"),
+                "{}",
+                e
+            ),
+            Ok(_) => panic!("Created a Kraken exchange without a market for {}", symbol),
+        }
+    }
+}
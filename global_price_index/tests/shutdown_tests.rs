@@ -0,0 +1,160 @@
+mod common;
+
+use async_trait::async_trait;
+use common::ws::{btc_usdt_market, gzip_json, start_ws_server};
+use global_price_index::{
+    aggregator::Aggregator,
+    api::AppState,
+    config::Settings,
+    error::Result,
+    exchanges::{huobi_ws::HuobiWsExchange, registry::ExchangeRegistry, Exchange},
+    history::HistoryStore,
+    models::{Order, OrderBook},
+};
+use rust_decimal_macros::dec;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+/// Exchange stand-in counting its reads and recording whether it was shut down
+#[derive(Default)]
+struct RecordingExchange {
+    reads: AtomicUsize,
+    shut_down: AtomicBool,
+}
+
+#[async_trait]
+impl Exchange for RecordingExchange {
+    fn name(&self) -> &'static str {
+        "Binance"
+    }
+
+    fn symbol(&self) -> &str {
+        "BTC/USDT"
+    }
+
+    async fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::SeqCst);
+    }
+
+    async fn fetch_order_book(&self) -> Result<OrderBook> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Ok(OrderBook {
+            bids: vec![Order {
+                price: dec!(99),
+                quantity: dec!(1),
+            }],
+            asks: vec![Order {
+                price: dec!(101),
+                quantity: dec!(1),
+            }],
+            timestamp: SystemTime::now(),
+            exchange_timestamp: None,
+        })
+    }
+}
+
+/// Tests that a WebSocket feed closes its connection cleanly when shut down.
+///
+/// This test verifies:
+/// 1. The exchange sends a close frame with the normal closure code
+/// 2. Shutting down waits for the feed to end and reports it disconnected
+/// 3. The feed does not reconnect afterwards
+#[tokio::test]
+async fn test_feed_sends_close_frame_on_shutdown() {
+    let mut server = start_ws_server(gzip_json).await;
+    let settings = Settings::default();
+    let ws_url = server.url.clone();
+    let connecting = async move {
+        HuobiWsExchange::with_url(
+            btc_usdt_market("btcusdt"),
+            ws_url,
+            &settings.exchange.huobi.ticker_url,
+            &settings.exchange.config,
+            &settings.capture,
+        )
+        .await
+    };
+    let exchange = server
+        .connect(connecting, |_| {
+            vec![json!({
+                "ch": "market.btcusdt.depth.step0",
+                "tick": { "bids": [[100.0, 1.0]], "asks": [[101.0, 1.0]] },
+            })]
+        })
+        .await;
+    assert_eq!(server.connections.recv().await, Some(1));
+    assert!(exchange.is_connected());
+
+    timeout(Duration::from_secs(5), exchange.shutdown())
+        .await
+        .expect("Feed did not shut down");
+    let close = timeout(Duration::from_secs(5), server.closes.recv())
+        .await
+        .expect("No close frame received");
+    assert_eq!(close, Some(Some(CloseCode::Normal)));
+    assert!(!exchange.is_connected());
+
+    assert!(timeout(Duration::from_secs(2), server.connections.recv())
+        .await
+        .is_err());
+}
+
+/// Tests that shutting the service down stops the exchanges and flushes the history.
+///
+/// This test verifies:
+/// 1. Every registered exchange is shut down and unregistered
+/// 2. The exchanges are no longer read once the shutdown completes
+/// 3. The indices recorded in the history's write-ahead log are moved
+///    into the database file
+#[tokio::test]
+async fn test_app_state_shutdown() {
+    let path = std::env::temp_dir().join(format!(
+        "global_price_index_shutdown_{}.db",
+        std::process::id()
+    ));
+    let wal = path.with_extension("db-wal");
+    let history = HistoryStore::open(path.to_str().unwrap()).unwrap();
+    let aggregator = Aggregator::new(16).with_history(history);
+    let registry = ExchangeRegistry::new();
+    let exchange = Arc::new(RecordingExchange::default());
+
+    let connected = exchange.clone();
+    registry.connect_in_background(
+        "Binance",
+        move || {
+            let exchange = connected.clone() as Arc<dyn Exchange>;
+            async move { Ok(exchange) }
+        },
+        Duration::from_millis(10),
+        &aggregator,
+    );
+    let state = AppState::new(vec!["BTC/USDT".to_string()], registry, aggregator);
+    // Indices are stored by the history's writer thread once computed
+    timeout(Duration::from_secs(5), async {
+        while state.aggregator.latest("BTC/USDT").is_none()
+            || std::fs::metadata(&wal).map_or(true, |wal| wal.len() == 0)
+        {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("No index stored");
+
+    state.shutdown().await;
+    assert!(exchange.shut_down.load(Ordering::SeqCst));
+    assert!(state.registry.exchanges().is_empty());
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+
+    let reads = exchange.reads.load(Ordering::SeqCst);
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(exchange.reads.load(Ordering::SeqCst), reads);
+
+    drop(state);
+    for file in [path.clone(), wal, path.with_extension("db-shm")] {
+        let _ = std::fs::remove_file(file);
+    }
+}
@@ -0,0 +1,130 @@
+use actix_web::body::MessageBody;
+use actix_web::{http::StatusCode, test, web};
+use global_price_index::{
+    aggregator::Aggregator,
+    api::AppState,
+    exchanges::registry::ExchangeRegistry,
+    models::{Decimal, ExchangePrice, GlobalPriceIndex, Liquidity},
+    stream::{stream_global_price, IndexPublisher},
+};
+use rust_decimal_macros::dec;
+use std::pin::Pin;
+use std::time::SystemTime;
+use tokio::time::{Duration, Instant};
+
+/// Builds an index of a symbol priced by a single exchange
+fn index(symbol: &str, mid_price: Decimal) -> GlobalPriceIndex {
+    GlobalPriceIndex::configured(
+        symbol,
+        vec![ExchangePrice {
+            exchange: "Binance".to_string(),
+            mid_price,
+            timestamp: SystemTime::now(),
+            liquidity: Liquidity::default(),
+            exchange_timestamp: None,
+            latency_ms: None,
+        }],
+    )
+    .expect("No index computed")
+}
+
+/// Tests that a subscription starts from the latest index and only
+/// receives the symbols it subscribed to.
+///
+/// This test verifies:
+/// 1. The latest published index of a subscribed symbol is delivered first
+/// 2. Updates of other symbols are ignored
+/// 3. Later updates of the subscribed symbol are delivered in order
+#[tokio::test]
+async fn test_subscription_filters_symbols() {
+    let publisher = IndexPublisher::new(16);
+    publisher.publish(index("BTC/USDT", dec!(50000)));
+
+    let mut subscription = publisher.subscribe(vec!["BTC/USDT".to_string()], Duration::ZERO);
+    assert_eq!(subscription.next().await.unwrap().price, dec!(50000));
+
+    publisher.publish(index("ETH/USDT", dec!(3000)));
+    publisher.publish(index("BTC/USDT", dec!(50001)));
+
+    let update = subscription.next().await.unwrap();
+    assert_eq!(update.symbol, "BTC/USDT");
+    assert_eq!(update.price, dec!(50001));
+}
+
+/// Tests that throttled updates are coalesced into the most recent one.
+///
+/// This test verifies:
+/// 1. The first update is delivered immediately
+/// 2. Updates arriving within the throttle delay are not delivered early
+/// 3. Only the most recent of them is delivered once the delay elapsed
+#[tokio::test(start_paused = true)]
+async fn test_subscription_throttle_coalesces_updates() {
+    let publisher = IndexPublisher::new(16);
+    let mut subscription =
+        publisher.subscribe(vec!["BTC/USDT".to_string()], Duration::from_secs(1));
+
+    publisher.publish(index("BTC/USDT", dec!(50000)));
+    let start = Instant::now();
+    assert_eq!(subscription.next().await.unwrap().price, dec!(50000));
+
+    publisher.publish(index("BTC/USDT", dec!(50001)));
+    publisher.publish(index("BTC/USDT", dec!(50002)));
+
+    assert_eq!(subscription.next().await.unwrap().price, dec!(50002));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+/// Tests that the /stream endpoint sends index updates as Server-Sent Events.
+///
+/// This test verifies:
+/// 1. The response is an event stream
+/// 2. Each event is named `index` and carries the GlobalPriceIndex JSON
+/// 3. Unknown symbols are answered with 404
+#[actix_web::test]
+async fn test_stream_endpoint_sends_events() {
+    let aggregator = Aggregator::new(16);
+    aggregator
+        .publisher()
+        .publish(index("BTC/USDT", dec!(50000)));
+
+    let app = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(AppState::new(
+                vec!["BTC/USDT".to_string(), "ETH/USDT".to_string()],
+                ExchangeRegistry::new(),
+                aggregator,
+            )))
+            .route("/stream", web::get().to(stream_global_price)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/stream?symbols=btc-usdt&throttle_ms=0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+
+    let mut body = resp.into_body();
+    let chunk = futures::future::poll_fn(|cx| Pin::new(&mut body).poll_next(cx))
+        .await
+        .unwrap()
+        .unwrap();
+    let event = std::str::from_utf8(&chunk).unwrap();
+    let data = event
+        .strip_prefix("event: index\ndata: ")
+        .and_then(|rest| rest.strip_suffix("\n\n"))
+        .expect("Malformed event");
+    let streamed: GlobalPriceIndex = serde_json::from_str(data).unwrap();
+    assert_eq!(streamed.symbol, "BTC/USDT");
+    assert_eq!(streamed.price, dec!(50000));
+
+    let req = test::TestRequest::get()
+        .uri("/stream?symbols=DOGE-USDT")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
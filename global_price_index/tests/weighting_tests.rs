@@ -0,0 +1,282 @@
+mod common;
+
+use common::price;
+use global_price_index::{
+    config::Clock,
+    exchanges::volume::{binance_volume, huobi_volume, kraken_volume},
+    models::{Decimal, ExchangePrice, Liquidity, Order, OrderBook},
+    weighting::{
+        cap_weights, weighted_average, DepthWithinBps, TimeDecay, TopOfBookDepth, TradedVolume,
+    },
+};
+use rust_decimal_macros::dec;
+use serde_json::json;
+use std::time::{Duration, SystemTime};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Builds an order of a given price and quantity
+fn order(price: Decimal, quantity: Decimal) -> Order {
+    Order { price, quantity }
+}
+
+/// Tests that weights are normalized and capped.
+///
+/// This test verifies:
+/// 1. Without a cap, shares are proportional to the weights
+/// 2. A share above the cap is lowered to it and the excess is
+///    redistributed in proportion to the other shares
+/// 3. An infeasible cap gives equal shares to the weighted venues
+/// 4. A total weight of zero cannot be normalized
+#[test]
+fn test_cap_weights() {
+    assert_eq!(
+        cap_weights(&[dec!(6), dec!(3), dec!(1)], Decimal::ZERO),
+        Some(vec![dec!(0.6), dec!(0.3), dec!(0.1)])
+    );
+
+    assert_eq!(
+        cap_weights(&[dec!(6), dec!(3), dec!(1)], dec!(0.5)),
+        Some(vec![dec!(0.5), dec!(0.375), dec!(0.125)])
+    );
+
+    // Two weighted venues cannot both stay below 40%
+    assert_eq!(
+        cap_weights(&[dec!(3), dec!(1), dec!(0)], dec!(0.4)),
+        Some(vec![dec!(0.5), dec!(0.5), dec!(0)])
+    );
+
+    assert_eq!(cap_weights(&[dec!(0), dec!(0)], dec!(0.5)), None);
+}
+
+/// Tests that each strategy weights prices by its own measure.
+///
+/// This test verifies:
+/// 1. Top-of-book depth, depth within bps and volume each select their measure
+/// 2. Prices lacking the measure count with a zero weight
+/// 3. No average is computed when no price has a weight
+/// 4. The weight cap limits the influence of the deepest venue
+/// 5. Simultaneous prices get equal time-decay weights
+#[test]
+fn test_weighted_average_strategies() {
+    let now = SystemTime::now();
+    let deep = ExchangePrice {
+        liquidity: Liquidity {
+            top_of_book_depth: Some(dec!(3)),
+            depth_within_bps: Some(dec!(1)),
+            volume: None,
+        },
+        ..price("Binance", dec!(100))
+    };
+    let shallow = ExchangePrice {
+        liquidity: Liquidity {
+            top_of_book_depth: Some(dec!(1)),
+            depth_within_bps: Some(dec!(1)),
+            volume: Some(dec!(5)),
+        },
+        ..price("Kraken", dec!(110))
+    };
+    let prices = vec![&deep, &shallow];
+
+    assert_eq!(
+        weighted_average(&prices, &TopOfBookDepth, Decimal::ZERO, now),
+        Some(dec!(102.5))
+    );
+    assert_eq!(
+        weighted_average(&prices, &DepthWithinBps, Decimal::ZERO, now),
+        Some(dec!(105))
+    );
+    assert_eq!(
+        weighted_average(&prices, &TradedVolume, Decimal::ZERO, now),
+        Some(dec!(110))
+    );
+    assert_eq!(
+        weighted_average(&[&deep], &TradedVolume, Decimal::ZERO, now),
+        None
+    );
+
+    assert_eq!(
+        weighted_average(&prices, &TopOfBookDepth, dec!(0.6), now),
+        Some(dec!(104))
+    );
+
+    let time_decay = TimeDecay {
+        decay_factor: 300.0,
+        clock: Clock::Local,
+    };
+    let simultaneous = price("Huobi", dec!(110));
+    let deep = ExchangePrice {
+        timestamp: simultaneous.timestamp,
+        ..deep
+    };
+    assert_eq!(
+        weighted_average(&[&deep, &simultaneous], &time_decay, Decimal::ZERO, now),
+        Some(dec!(105))
+    );
+}
+
+/// Tests time decay weighting on the exchange clock.
+///
+/// This test verifies:
+/// 1. On the local clock, prices received at the same time weigh the same
+/// 2. On the exchange clock, prices are aged by the venue's book update time
+/// 3. Venues that do not stamp their updates fall back to the local clock
+#[test]
+fn test_time_decay_exchange_clock() {
+    let now = SystemTime::now();
+    let fresh = ExchangePrice {
+        exchange_timestamp: Some(now),
+        timestamp: now,
+        ..price("Binance", dec!(100))
+    };
+    // Received now, but updated by the venue ten minutes ago
+    let lagging = ExchangePrice {
+        exchange_timestamp: Some(now - Duration::from_secs(600)),
+        timestamp: now,
+        ..price("Kraken", dec!(110))
+    };
+    let unstamped = ExchangePrice {
+        timestamp: now,
+        ..price("Huobi", dec!(110))
+    };
+    let time_decay = |clock| TimeDecay {
+        decay_factor: 300.0,
+        clock,
+    };
+
+    assert_eq!(
+        weighted_average(
+            &[&fresh, &lagging],
+            &time_decay(Clock::Local),
+            Decimal::ZERO,
+            now
+        ),
+        Some(dec!(105))
+    );
+    let average = weighted_average(
+        &[&fresh, &lagging],
+        &time_decay(Clock::Exchange),
+        Decimal::ZERO,
+        now,
+    )
+    .unwrap();
+    assert!(average > dec!(101) && average < dec!(102));
+    assert_eq!(
+        weighted_average(
+            &[&fresh, &unstamped],
+            &time_decay(Clock::Exchange),
+            Decimal::ZERO,
+            now
+        ),
+        Some(dec!(105))
+    );
+}
+
+/// Tests the liquidity measured from an order book.
+///
+/// This test verifies:
+/// 1. The top-of-book depth is the notional of the best bid and best ask
+/// 2. The depth within bps only counts levels within the band around the mid price
+/// 3. An empty book has no liquidity
+#[test]
+fn test_order_book_liquidity() {
+    let order_book = OrderBook {
+        bids: vec![order(dec!(100), dec!(2)), order(dec!(99), dec!(1))],
+        asks: vec![order(dec!(101), dec!(1)), order(dec!(103), dec!(4))],
+        timestamp: SystemTime::now(),
+        exchange_timestamp: None,
+    };
+
+    // The mid price is 100.5, so 100 bps spans 99.495 to 101.505
+    let liquidity = order_book.liquidity(100);
+    assert_eq!(liquidity.top_of_book_depth, Some(dec!(301)));
+    assert_eq!(liquidity.depth_within_bps, Some(dec!(301)));
+    assert_eq!(liquidity.volume, None);
+
+    // 200 bps spans 98.49 to 102.51
+    assert_eq!(order_book.liquidity(200).depth_within_bps, Some(dec!(400)));
+
+    let empty = OrderBook {
+        bids: vec![],
+        asks: vec![],
+        timestamp: SystemTime::now(),
+        exchange_timestamp: None,
+    };
+    assert_eq!(empty.liquidity(100), Liquidity::default());
+}
+
+/// Tests that the trailing volume is read from each exchange's ticker.
+///
+/// This test verifies:
+/// 1. The market is requested with the exchange's own symbol
+/// 2. Binance, Kraken and Huobi volumes are parsed from their wire formats
+/// 3. API errors reported in the response body are returned as errors
+#[tokio::test]
+async fn test_volume_tickers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/24hr"))
+        .and(query_param("symbol", "BTCUSDT"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "symbol": "BTCUSDT", "volume": "1234.50000000" })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/0/public/Ticker"))
+        .and(query_param("pair", "XBTUSDT"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "error": [],
+            "result": { "XBTUSDT": { "v": ["10.5", "250.25"] } },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/0/public/Ticker"))
+        .and(query_param("pair", "UNKNOWN"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "error": ["EQuery:Unknown asset pair"] })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/market/detail"))
+        .and(query_param("symbol", "btcusdt"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "ok",
+            "tick": { "amount": 1520.123, "vol": 75_000_000.5 },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/market/detail"))
+        .and(query_param("symbol", "unknown"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "error",
+            "err-msg": "invalid symbol",
+        })))
+        .mount(&server)
+        .await;
+
+    let binance_url = format!("{}/api/v3/ticker/24hr", server.uri());
+    assert_eq!(
+        binance_volume(&binance_url, "BTCUSDT").await.unwrap(),
+        dec!(1234.5)
+    );
+
+    let kraken_url = format!("{}/0/public/Ticker", server.uri());
+    assert_eq!(
+        kraken_volume(&kraken_url, "XBTUSDT").await.unwrap(),
+        dec!(250.25)
+    );
+    assert!(kraken_volume(&kraken_url, "UNKNOWN").await.is_err());
+
+    let huobi_url = format!("{}/market/detail", server.uri());
+    assert_eq!(
+        huobi_volume(&huobi_url, "btcusdt").await.unwrap(),
+        dec!(1520.123)
+    );
+    assert!(huobi_volume(&huobi_url, "unknown").await.is_err());
+}